@@ -1,6 +1,7 @@
 #!/usr/bin/env node
 /**
- * Synchronizes the version number across package.json, tauri.conf.json, and Cargo.toml
+ * Synchronizes the version number across package.json, tauri.conf.json, Cargo.toml and the
+ * sidecar's version.ts
  * 
  * Usage:
  *   node scripts/sync-version.js           # Syncs from package.json version
@@ -58,6 +59,13 @@ function syncVersion(newVersion) {
   fs.writeFileSync(cargoPath, cargo);
   console.log(`   ✅ src-tauri/Cargo.toml`);
 
+  // Update the version the sidecar reports in its handshake
+  const sidecarVersionPath = path.join(ROOT, 'sidecar/src/version.ts');
+  let sidecarVersion = fs.readFileSync(sidecarVersionPath, 'utf8');
+  sidecarVersion = sidecarVersion.replace(/^export const APP_VERSION = '.*';$/m, `export const APP_VERSION = '${version}';`);
+  fs.writeFileSync(sidecarVersionPath, sidecarVersion);
+  console.log(`   ✅ sidecar/src/version.ts`);
+
   console.log(`\n✨ All files synced to version ${version}`);
 }
 
@@ -69,7 +69,7 @@ export interface KeyVaultErrorData {
   };
 }
 
-class KeyVaultError extends Error {
+export class KeyVaultError extends Error {
   readonly code: string;
   readonly action: string;
   readonly actionUrl?: string;
@@ -466,7 +466,30 @@ async function initializeMsalClient(config: TokenAppConfig): Promise<MsalClientS
   return state;
 }
 
-export async function handleAppToken(params: unknown): Promise<msal.AuthenticationResult> {
+export interface OboTokenParams {
+  config: TokenAppConfig;
+  /** The incoming user access token being exchanged */
+  userAssertion: string;
+  scopes: string[];
+}
+
+// Invalidate client on auth errors that suggest stale credentials
+// AADSTS7000215: Invalid client secret
+// AADSTS7000222: The provided client secret keys are expired
+function invalidateOnCredentialError(cacheKey: string, err: any): void {
+  const errorCode = err?.errorCode || err?.error || '';
+  const message = err?.message || '';
+  if (
+    errorCode === 'invalid_client' ||
+    message.includes('AADSTS7000215') ||
+    message.includes('AADSTS7000222') ||
+    message.includes('invalid client secret')
+  ) {
+    msalClients.delete(cacheKey);
+  }
+}
+
+export async function handleAppToken(params: unknown, signal?: AbortSignal): Promise<msal.AuthenticationResult> {
   const { config, scopes } = params as AppTokenParams;
   
   // Validate config
@@ -476,6 +499,8 @@ export async function handleAppToken(params: unknown): Promise<msal.Authenticati
 
   const cacheKey = getMsalCacheKey(config);
   const msalState = await initializeMsalClient(config);
+  // MSAL has no abort support; at least skip the token request if the host gave up meanwhile.
+  signal?.throwIfAborted();
   
   try {
     const result = await msalState.app.acquireTokenByClientCredential({
@@ -488,19 +513,42 @@ export async function handleAppToken(params: unknown): Promise<msal.Authenticati
 
     return result;
   } catch (err: any) {
-    // Invalidate client on auth errors that suggest stale credentials
-    // AADSTS7000215: Invalid client secret
-    // AADSTS7000222: The provided client secret keys are expired
-    const errorCode = err?.errorCode || err?.error || '';
-    const message = err?.message || '';
-    if (
-      errorCode === 'invalid_client' ||
-      message.includes('AADSTS7000215') ||
-      message.includes('AADSTS7000222') ||
-      message.includes('invalid client secret')
-    ) {
-      msalClients.delete(cacheKey);
+    invalidateOnCredentialError(cacheKey, err);
+    throw err;
+  }
+}
+
+/**
+ * On-behalf-of exchange: trade a user token sent to this app for a token to a downstream
+ * API, authenticating with the same Key Vault credential as app tokens.
+ */
+export async function handleOboToken(params: unknown, signal?: AbortSignal): Promise<msal.AuthenticationResult> {
+  const { config, userAssertion, scopes } = params as OboTokenParams;
+
+  if (!config?.clientId || !config?.tenantId || !config?.keyVault?.uri) {
+    throw new Error('Invalid configuration: clientId, tenantId, and keyVault.uri are required');
+  }
+  if (!userAssertion) {
+    throw new Error('A user token is required for the on-behalf-of exchange');
+  }
+
+  const cacheKey = getMsalCacheKey(config);
+  const msalState = await initializeMsalClient(config);
+  signal?.throwIfAborted();
+
+  try {
+    const result = await msalState.app.acquireTokenOnBehalfOf({
+      oboAssertion: userAssertion,
+      scopes,
+    });
+
+    if (!result) {
+      throw new Error('Failed to acquire token - no result returned');
     }
+
+    return result;
+  } catch (err: any) {
+    invalidateOnCredentialError(cacheKey, err);
     throw err;
   }
 }
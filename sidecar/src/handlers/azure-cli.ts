@@ -9,6 +9,7 @@ import { execFile } from 'node:child_process';
 import fs from 'node:fs';
 import path from 'node:path';
 import { promisify } from 'node:util';
+import { childProcessEnv } from '../session.js';
 
 const execFileAsync = promisify(execFile);
 
@@ -146,12 +147,16 @@ function formatAzureCliError(err: unknown): string {
   return error.stderr?.trim() || message || 'Azure CLI request failed';
 }
 
-async function runAzJson<T>(args: string[]): Promise<AzureCliResult<T>> {
+async function runAzJson<T>(args: string[], signal?: AbortSignal): Promise<AzureCliResult<T>> {
   try {
     const { command, args: commandArgs } = resolveAzCommand(args);
+    // The abort signal kills the `az` child process when the host cancels the request.
+    // `az` runs with a scrubbed environment: it has no business seeing host variables.
     const { stdout, stderr } = await execFileAsync(command, commandArgs, {
       timeout: AZ_TIMEOUT_MS,
       maxBuffer: AZ_MAX_BUFFER,
+      signal,
+      env: childProcessEnv(),
     });
 
     if (stderr && !stdout) {
@@ -169,7 +174,7 @@ function escapeOdataValue(value: string): string {
   return value.replace(/'/g, "''");
 }
 
-export async function handleListSubscriptions(): Promise<AzureCliResult<unknown>> {
+export async function handleListSubscriptions(signal?: AbortSignal): Promise<AzureCliResult<unknown>> {
   return runAzJson([
     'account',
     'list',
@@ -177,11 +182,12 @@ export async function handleListSubscriptions(): Promise<AzureCliResult<unknown>
     '[].{id:id,name:name,tenantId:tenantId,isDefault:isDefault,state:state}',
     '-o',
     'json',
-  ]);
+  ], signal);
 }
 
 export async function handleListAppRegistrations(
-  params?: AzureAppFilters
+  params?: AzureAppFilters,
+  signal?: AbortSignal
 ): Promise<AzureCliResult<unknown>> {
   const args = ['ad', 'app', 'list'];
   const appId = params?.appId?.trim();
@@ -212,10 +218,13 @@ export async function handleListAppRegistrations(
   }
   args.push('--query', '[].{appId:appId,displayName:displayName}');
   args.push('-o', 'json');
-  return runAzJson(args);
+  return runAzJson(args, signal);
 }
 
-export async function handleListKeyVaults(params?: { subscriptionId?: string }): Promise<AzureCliResult<unknown>> {
+export async function handleListKeyVaults(
+  params?: { subscriptionId?: string },
+  signal?: AbortSignal
+): Promise<AzureCliResult<unknown>> {
   const args = [
     'keyvault',
     'list',
@@ -227,10 +236,13 @@ export async function handleListKeyVaults(params?: { subscriptionId?: string }):
   if (params?.subscriptionId) {
     args.push('--subscription', params.subscriptionId);
   }
-  return runAzJson(args);
+  return runAzJson(args, signal);
 }
 
-export async function handleListSecrets(params: { vaultName?: string; subscriptionId?: string }): Promise<AzureCliResult<unknown>> {
+export async function handleListSecrets(
+  params: { vaultName?: string; subscriptionId?: string },
+  signal?: AbortSignal
+): Promise<AzureCliResult<unknown>> {
   if (!params?.vaultName) {
     return { success: false, error: 'vaultName is required' };
   }
@@ -248,10 +260,13 @@ export async function handleListSecrets(params: { vaultName?: string; subscripti
   if (params.subscriptionId) {
     args.push('--subscription', params.subscriptionId);
   }
-  return runAzJson(args);
+  return runAzJson(args, signal);
 }
 
-export async function handleListCertificates(params: { vaultName?: string; subscriptionId?: string }): Promise<AzureCliResult<unknown>> {
+export async function handleListCertificates(
+  params: { vaultName?: string; subscriptionId?: string },
+  signal?: AbortSignal
+): Promise<AzureCliResult<unknown>> {
   if (!params?.vaultName) {
     return { success: false, error: 'vaultName is required' };
   }
@@ -269,5 +284,5 @@ export async function handleListCertificates(params: { vaultName?: string; subsc
   if (params.subscriptionId) {
     args.push('--subscription', params.subscriptionId);
   }
-  return runAzJson(args);
+  return runAzJson(args, signal);
 }
@@ -11,13 +11,13 @@ export interface CredentialStatus {
   message: string;
 }
 
-export async function handleCredentialStatus(): Promise<CredentialStatus> {
+export async function handleCredentialStatus(signal?: AbortSignal): Promise<CredentialStatus> {
   try {
     const credential = new DefaultAzureCredential();
     
     // Try to get a token for Azure management to verify credentials work
     // Using a well-known scope that should be available
-    await credential.getToken('https://management.azure.com/.default');
+    await credential.getToken('https://management.azure.com/.default', { abortSignal: signal });
     
     return {
       available: true,
@@ -22,7 +22,7 @@ export interface ValidationResult {
   message?: string;
 }
 
-export async function handleValidateKeyVault(params: unknown): Promise<ValidationResult> {
+export async function handleValidateKeyVault(params: unknown, signal?: AbortSignal): Promise<ValidationResult> {
   const config = params as KeyVaultConfig;
 
   if (!config?.uri) {
@@ -38,7 +38,7 @@ export async function handleValidateKeyVault(params: unknown): Promise<Validatio
 
     try {
       const certClient = new CertificateClient(config.uri, credential);
-      const cert = await certClient.getCertificate(config.certName);
+      const cert = await certClient.getCertificate(config.certName, { abortSignal: signal });
       
       if (!cert.properties.x509Thumbprint) {
         return { valid: false, credentialType: 'certificate', message: `Certificate '${config.certName}' does not have a valid thumbprint` };
@@ -66,7 +66,7 @@ export async function handleValidateKeyVault(params: unknown): Promise<Validatio
 
   try {
     const secretClient = new SecretClient(config.uri, credential);
-    const secret = await secretClient.getSecret(config.secretName);
+    const secret = await secretClient.getSecret(config.secretName, { abortSignal: signal });
     
     if (!secret.value) {
       return { valid: false, credentialType: 'secret', message: `Secret '${config.secretName}' value is empty` };
@@ -0,0 +1,153 @@
+/**
+ * Device code flow tests
+ *
+ * MSAL's `acquireTokenByDeviceCode` is replaced with a fake that calls the request's
+ * `deviceCodeCallback` the way Entra's device authorization response does, so
+ * `startDeviceCode` and its `device_code/progress` notifications run without a network.
+ */
+
+import assert from 'node:assert/strict';
+import { afterEach, beforeEach, mock, test } from 'node:test';
+import fs from 'fs';
+import os from 'os';
+import path from 'path';
+import * as msal from '@azure/msal-node';
+import { DEVICE_CODE_PROGRESS } from '../notify.js';
+import { cancelDeviceCode, startDeviceCode } from './user-token.js';
+
+const CLIENT_ID = '7a1c2b3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d';
+const TENANT_ID = 'contoso.onmicrosoft.com';
+
+const DEVICE_CODE: Parameters<msal.DeviceCodeRequest['deviceCodeCallback']>[0] = {
+  userCode: 'ABCD-EFGH',
+  deviceCode: 'device-code',
+  verificationUri: 'https://microsoft.com/devicelogin',
+  expiresIn: 900,
+  interval: 5,
+  message: 'To sign in, use a web browser to open https://microsoft.com/devicelogin and enter the code ABCD-EFGH.',
+};
+
+type FakeDeviceCode = (request: msal.DeviceCodeRequest) => Promise<msal.AuthenticationResult | null>;
+
+const originalDeviceCode = msal.PublicClientApplication.prototype.acquireTokenByDeviceCode;
+let notifications: Array<{ flowId: string; status: string; [key: string]: unknown }> = [];
+let dataDir = '';
+
+function fakeDeviceCode(fake: FakeDeviceCode) {
+  msal.PublicClientApplication.prototype.acquireTokenByDeviceCode = fake;
+}
+
+function params(flowId: string, scopes: string[] = ['User.Read']) {
+  return { flowId, clientId: CLIENT_ID, tenantId: TENANT_ID, scopes };
+}
+
+/** Wait until the flow has sent a final (non-`pending`) notification */
+async function finalStatus(flowId: string) {
+  for (let i = 0; i < 100; i++) {
+    const final = notifications.find((n) => n.flowId === flowId && n.status !== 'pending');
+    if (final) return final;
+    await new Promise((resolve) => setTimeout(resolve, 5));
+  }
+  assert.fail(`no final notification for ${flowId}`);
+}
+
+beforeEach(() => {
+  dataDir = fs.mkdtempSync(path.join(os.tmpdir(), 'entra-token-studio-sidecar-'));
+  process.env.ENTRA_TOKEN_STUDIO_DATA_DIR = dataDir;
+  notifications = [];
+  mock.method(console, 'log', (line: string) => {
+    const message = JSON.parse(line);
+    assert.equal(message.method, DEVICE_CODE_PROGRESS);
+    notifications.push(message.params);
+  });
+});
+
+afterEach(() => {
+  mock.restoreAll();
+  msal.PublicClientApplication.prototype.acquireTokenByDeviceCode = originalDeviceCode;
+  fs.rmSync(dataDir, { recursive: true, force: true });
+});
+
+test('start_device_code returns the user code and reports the token', async () => {
+  let requested: string[] = [];
+  fakeDeviceCode(async (request) => {
+    requested = request.scopes;
+    request.deviceCodeCallback(DEVICE_CODE);
+    return {
+      accessToken: 'access-token',
+      tokenType: 'Bearer',
+      expiresOn: new Date('2030-01-01T00:00:00Z'),
+      scopes: ['User.Read'],
+      account: { homeAccountId: 'uid.utid', environment: 'login.microsoftonline.com', tenantId: 'utid', username: 'user@contoso.com', localAccountId: 'uid' },
+    } as msal.AuthenticationResult;
+  });
+
+  const info = await startDeviceCode(params('flow-1', [' User.Read ', 'User.Read', '', 'Mail.Read']));
+  assert.deepEqual(info, {
+    flowId: 'flow-1',
+    userCode: 'ABCD-EFGH',
+    verificationUri: 'https://microsoft.com/devicelogin',
+    message: DEVICE_CODE.message,
+    expiresIn: 900,
+    interval: 5,
+  });
+  assert.deepEqual(requested, ['User.Read', 'Mail.Read']);
+
+  const final = await finalStatus('flow-1');
+  assert.equal(final.status, 'completed');
+  const result = final.result as Record<string, unknown>;
+  assert.equal(result.accessToken, 'access-token');
+  assert.equal(result.expiresOn, '2030-01-01T00:00:00.000Z');
+});
+
+test('start_device_code rejects when no code is issued', async () => {
+  fakeDeviceCode(async () => {
+    throw Object.assign(new Error('AADSTS700016: Application not found'), { errorCode: 'unauthorized_client' });
+  });
+
+  await assert.rejects(startDeviceCode(params('flow-2')), /AADSTS700016/);
+  assert.deepEqual(notifications, []);
+});
+
+test('start_device_code reports a sign-in without a token as failed', async () => {
+  fakeDeviceCode(async (request) => {
+    request.deviceCodeCallback(DEVICE_CODE);
+    return null;
+  });
+
+  await startDeviceCode(params('flow-6'));
+  const final = await finalStatus('flow-6');
+  assert.equal(final.status, 'failed');
+  assert.equal((final.error as { message: string }).message, 'Device code sign-in returned no token');
+});
+
+test('cancel_device_code stops a running flow', async () => {
+  fakeDeviceCode(async (request) => {
+    request.deviceCodeCallback(DEVICE_CODE);
+    while (!request.cancel) await new Promise((resolve) => setTimeout(resolve, 5));
+    throw Object.assign(new Error('Device code polling cancelled'), { errorCode: 'device_code_cancelled' });
+  });
+
+  await startDeviceCode(params('flow-3'));
+  await assert.rejects(startDeviceCode(params('flow-3')), /already running/);
+  assert.equal(await cancelDeviceCode({ flowId: 'flow-3' }), true);
+
+  const final = await finalStatus('flow-3');
+  assert.equal(final.status, 'cancelled');
+  assert.equal((final.error as { code?: string }).code, 'device_code_cancelled');
+  assert.equal(await cancelDeviceCode({ flowId: 'unknown-flow' }), false);
+});
+
+test('start_device_code reports an expired code', async () => {
+  fakeDeviceCode(async (request) => {
+    request.deviceCodeCallback(DEVICE_CODE);
+    throw Object.assign(new Error('AADSTS70020: The provided value for the input parameter device_code was expired'), { errorCode: 'expired_token' });
+  });
+
+  await startDeviceCode(params('flow-4'));
+  assert.equal((await finalStatus('flow-4')).status, 'expired');
+});
+
+test('start_device_code requires its parameters', async () => {
+  await assert.rejects(startDeviceCode({ ...params('flow-5'), scopes: [] }), /are required/);
+});
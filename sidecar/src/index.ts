@@ -10,10 +10,18 @@
 
 import { createInterface } from 'readline';
 import path from 'node:path';
-import { handleAppToken } from './handlers/app-token.js';
+import { handleAppToken, handleOboToken, KeyVaultError } from './handlers/app-token.js';
 import { handleValidateKeyVault } from './handlers/keyvault.js';
 import { handleCredentialStatus } from './handlers/credential-status.js';
-import { handleUserToken, clearUserTokenCache, getUserAccounts, getAuthStorageStatus } from './handlers/user-token.js';
+import {
+  handleUserToken,
+  handleRedeemAuthCode,
+  clearUserTokenCache,
+  getUserAccounts,
+  getAuthStorageStatus,
+  startDeviceCode,
+  cancelDeviceCode,
+} from './handlers/user-token.js';
 import {
   handleListSubscriptions,
   handleListAppRegistrations,
@@ -21,6 +29,8 @@ import {
   handleListSecrets,
   handleListCertificates,
 } from './handlers/azure-cli.js';
+import { SESSION_KEY_METHOD, scrubHostEnv, setSessionKey, type SessionKeyParams } from './session.js';
+import { HELLO_METHOD, handleHello, type HelloParams } from './protocol.js';
 
 interface JsonRpcRequest {
   jsonrpc: '2.0';
@@ -29,6 +39,12 @@ interface JsonRpcRequest {
   params?: unknown;
 }
 
+interface JsonRpcNotification {
+  jsonrpc: '2.0';
+  method: string;
+  params?: unknown;
+}
+
 interface JsonRpcResponse {
   jsonrpc: '2.0';
   id: string | number;
@@ -40,7 +56,15 @@ interface JsonRpcResponse {
   };
 }
 
-type JsonRpcHandler = (params?: unknown) => Promise<unknown>;
+type JsonRpcHandler = (params: unknown, signal: AbortSignal) => Promise<unknown>;
+
+/** Notification sent by the host to abort an in-flight request. */
+const CANCEL_METHOD = '$/cancelRequest';
+/** JSON-RPC error code for a request aborted by the host (LSP convention). */
+const REQUEST_CANCELLED = -32800;
+
+// Abort controllers for in-flight requests, keyed by JSON-RPC id
+const inFlight = new Map<string | number, AbortController>();
 
 const COMMON_CLI_PATHS: Record<NodeJS.Platform, string[]> = {
   darwin: ['/opt/homebrew/bin', '/usr/local/bin', '/usr/local/sbin'],
@@ -69,23 +93,62 @@ function ensureCliPaths() {
   process.env.PATH = [...additions, ...parts].join(path.delimiter);
 }
 
-function withParams<T>(handler: (params: T) => Promise<unknown>): JsonRpcHandler {
-  return (params?: unknown) => handler(params as T);
+function withParams<T>(handler: (params: T, signal: AbortSignal) => Promise<unknown>): JsonRpcHandler {
+  return (params, signal) => handler(params as T, signal);
+}
+
+function withOptionalParams<T>(handler: (params: T | undefined, signal: AbortSignal) => Promise<unknown>): JsonRpcHandler {
+  return (params, signal) => handler(params as T | undefined, signal);
+}
+
+/**
+ * Resolve with the handler's result, or reject as soon as the request is cancelled.
+ * Handlers that support cancellation stop their own work when the signal fires; this
+ * guarantees a prompt response even for those that cannot.
+ */
+function raceAbort<T>(work: Promise<T>, signal: AbortSignal): Promise<T> {
+  if (signal.aborted) return Promise.reject(new RequestCancelledError());
+  return new Promise<T>((resolve, reject) => {
+    const onAbort = () => reject(new RequestCancelledError());
+    signal.addEventListener('abort', onAbort, { once: true });
+    work.then(resolve, reject).finally(() => signal.removeEventListener('abort', onAbort));
+  });
+}
+
+class RequestCancelledError extends Error {
+  constructor() {
+    super('Request cancelled');
+    this.name = 'RequestCancelledError';
+  }
 }
 
-function withOptionalParams<T>(handler: (params?: T) => Promise<unknown>): JsonRpcHandler {
-  return (params?: unknown) => handler(params as T | undefined);
+function handleNotification(notification: JsonRpcNotification) {
+  if (notification.method === SESSION_KEY_METHOD) {
+    setSessionKey(notification.params as SessionKeyParams | undefined);
+    return;
+  }
+  if (notification.method === CANCEL_METHOD) {
+    const { id } = (notification.params ?? {}) as { id?: string | number };
+    if (id !== undefined) {
+      inFlight.get(id)?.abort();
+    }
+  }
 }
 
 const handlers: Record<string, JsonRpcHandler> = {
+  [HELLO_METHOD]: withOptionalParams<HelloParams>(async (params) => handleHello(params, Object.keys(handlers))),
   'acquire_app_token': handleAppToken,
+  'acquire_obo_token': handleOboToken,
   'acquire_user_token': handleUserToken,
+  'redeem_auth_code': handleRedeemAuthCode,
+  'start_device_code': startDeviceCode,
+  'cancel_device_code': cancelDeviceCode,
   'clear_user_cache': clearUserTokenCache,
   'get_user_accounts': getUserAccounts,
   'get_auth_storage_status': () => getAuthStorageStatus(),
   'validate_keyvault': handleValidateKeyVault,
-  'get_credential_status': handleCredentialStatus,
-  'list_azure_subscriptions': () => handleListSubscriptions(),
+  'get_credential_status': (_params, signal) => handleCredentialStatus(signal),
+  'list_azure_subscriptions': (_params, signal) => handleListSubscriptions(signal),
   'list_azure_apps': withOptionalParams(handleListAppRegistrations),
   'list_keyvaults': withOptionalParams(handleListKeyVaults),
   'list_keyvault_secrets': withParams(handleListSecrets),
@@ -106,8 +169,11 @@ async function handleRequest(request: JsonRpcRequest): Promise<JsonRpcResponse>
     };
   }
 
+  const controller = new AbortController();
+  inFlight.set(request.id, controller);
+
   try {
-    const result = await handler(request.params);
+    const result = await raceAbort(handler(request.params, controller.signal), controller.signal);
     return {
       jsonrpc: '2.0',
       id: request.id,
@@ -117,21 +183,51 @@ async function handleRequest(request: JsonRpcRequest): Promise<JsonRpcResponse>
     };
   } catch (err) {
     const error = err as Error;
+    if (controller.signal.aborted) {
+      return {
+        jsonrpc: '2.0',
+        id: request.id,
+        error: { code: REQUEST_CANCELLED, message: 'Request cancelled' },
+      };
+    }
     return {
       jsonrpc: '2.0',
       id: request.id,
       error: {
         code: -32000,
         message: error.message || 'Internal error',
-        data: process.env.NODE_ENV === 'development' ? error.stack : undefined,
+        data: errorData(error),
       },
     };
+  } finally {
+    inFlight.delete(request.id);
+  }
+}
+
+/**
+ * Structured detail attached to a JSON-RPC error so the app can act on it without
+ * parsing the message: Key Vault errors carry their code and suggested action, MSAL
+ * errors their error code and correlation id.
+ */
+function errorData(error: Error): unknown {
+  if (error instanceof KeyVaultError) {
+    return error.toJSON();
+  }
+  const msalError = error as Error & { errorCode?: unknown; subError?: unknown; correlationId?: unknown };
+  if (typeof msalError.errorCode === 'string' && msalError.errorCode) {
+    return {
+      code: msalError.errorCode,
+      subError: msalError.subError || undefined,
+      correlationId: msalError.correlationId || undefined,
+    };
   }
+  return process.env.NODE_ENV === 'development' ? { stack: error.stack } : undefined;
 }
 
 function main() {
   // GUI-launched apps may not inherit shell PATH; ensure common CLI locations are available.
   ensureCliPaths();
+  scrubHostEnv();
   const rl = createInterface({
     input: process.stdin,
     output: process.stdout,
@@ -140,8 +236,12 @@ function main() {
 
   rl.on('line', async (line) => {
     try {
-      const request = JSON.parse(line) as JsonRpcRequest;
-      const response = await handleRequest(request);
+      const message = JSON.parse(line) as JsonRpcRequest | JsonRpcNotification;
+      if (!('id' in message) || message.id === undefined || message.id === null) {
+        handleNotification(message);
+        return;
+      }
+      const response = await handleRequest(message);
       console.log(JSON.stringify(response));
     } catch (err) {
       // Invalid JSON
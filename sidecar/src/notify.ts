@@ -0,0 +1,14 @@
+/**
+ * Host notifications
+ *
+ * Long-running work (e.g. device code polling) reports progress to the Tauri host as
+ * JSON-RPC notifications: messages on stdout without an `id`, which the host routes by
+ * method instead of matching them to a request.
+ */
+
+/** Progress of a device code sign-in; see `startDeviceCode` in handlers/user-token.ts. */
+export const DEVICE_CODE_PROGRESS = 'device_code/progress';
+
+export function sendNotification(method: string, params: unknown): void {
+  console.log(JSON.stringify({ jsonrpc: '2.0', method, params }));
+}
@@ -0,0 +1,60 @@
+/**
+ * Protocol handshake with the host
+ *
+ * The host sends `hello` right after the session key, before any other request. It
+ * refuses to use a sidecar whose protocol version, app version or method list doesn't
+ * match its own build, so a stale `dist/index.cjs` fails at startup instead of answering
+ * with another schema. See `hello` in src-tauri/src/sidecar.rs.
+ */
+
+import { createHash } from 'node:crypto';
+import { readFileSync } from 'node:fs';
+
+import { APP_VERSION } from './version.js';
+
+/** Bump together with `PROTOCOL_VERSION` in src-tauri/src/sidecar.rs on incompatible changes. */
+export const PROTOCOL_VERSION = 1;
+export const HELLO_METHOD = 'hello';
+
+export interface HelloParams {
+  protocolVersion?: number;
+}
+
+export interface HelloResult {
+  protocolVersion: number;
+  /** Version of the app this bundle was built for */
+  appVersion: string;
+  /** Short hash of this bundle, to tell builds of the same version apart */
+  buildHash: string | null;
+  nodeVersion: string;
+  methods: string[];
+}
+
+let buildHash: string | null | undefined;
+
+/** Hash of the running bundle, computed on first use; null if it can't be read. */
+function getBuildHash(): string | null {
+  if (buildHash === undefined) {
+    try {
+      buildHash = createHash('sha256').update(readFileSync(__filename)).digest('hex').slice(0, 12);
+    } catch {
+      buildHash = null;
+    }
+  }
+  return buildHash;
+}
+
+export function handleHello(params: HelloParams | undefined, methods: string[]): HelloResult {
+  if (params?.protocolVersion !== undefined && params.protocolVersion !== PROTOCOL_VERSION) {
+    console.error(
+      `[sidecar] Host speaks protocol version ${params.protocolVersion}, this sidecar version ${PROTOCOL_VERSION}`,
+    );
+  }
+  return {
+    protocolVersion: PROTOCOL_VERSION,
+    appVersion: APP_VERSION,
+    buildHash: getBuildHash(),
+    nodeVersion: process.versions.node,
+    methods,
+  };
+}
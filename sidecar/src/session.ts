@@ -0,0 +1,67 @@
+/**
+ * Session secrets from the host
+ *
+ * The MSAL cache key is not passed in the environment (readable by other processes of the
+ * same user and inherited by every child). The host writes it as the first line on stdin,
+ * a `session/key` notification, and it is kept here in memory only.
+ */
+
+/** First message from the host; see `session_key_message` in src-tauri/src/sidecar.rs. */
+export const SESSION_KEY_METHOD = 'session/key';
+
+export type CacheKeySource = 'keyring' | 'file' | 'none';
+
+export interface SessionKeyParams {
+  cacheKey?: string | null;
+  cacheKeySource?: string;
+}
+
+let cacheKey: Buffer | null = null;
+let cacheKeySource: CacheKeySource | null = null;
+
+export function setSessionKey(params: SessionKeyParams | undefined): void {
+  cacheKey?.fill(0);
+  cacheKey = null;
+
+  const raw = params?.cacheKey?.trim();
+  if (raw) {
+    const key = Buffer.from(raw, 'base64');
+    if (key.length === 32) {
+      cacheKey = key;
+    } else {
+      key.fill(0);
+    }
+  }
+  const source = params?.cacheKeySource;
+  cacheKeySource = source === 'keyring' || source === 'file' ? source : 'none';
+}
+
+/** The 32-byte cache key, or `null` if the host has none (or hasn't sent it). */
+export function getSessionCacheKey(): Buffer | null {
+  return cacheKey;
+}
+
+/** Where the host keeps the key; `null` until the host has sent it. */
+export function getSessionCacheKeySource(): CacheKeySource | null {
+  return cacheKeySource;
+}
+
+/**
+ * Drop cache key variables (`ENTRA_TOKEN_STUDIO_CACHE_KEY*`, e.g. set by an older host)
+ * from this process, so children spawned by libraries such as `AzureCliCredential`
+ * don't inherit them either.
+ */
+export function scrubHostEnv(): void {
+  for (const name of Object.keys(process.env)) {
+    if (name.startsWith('ENTRA_TOKEN_STUDIO_CACHE_KEY')) {
+      delete process.env[name];
+    }
+  }
+}
+
+/** Environment for child processes such as `az`: ours without host-only variables. */
+export function childProcessEnv(): NodeJS.ProcessEnv {
+  return Object.fromEntries(
+    Object.entries(process.env).filter(([name]) => !name.startsWith('ENTRA_TOKEN_STUDIO_')),
+  );
+}
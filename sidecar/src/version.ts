@@ -0,0 +1,5 @@
+/**
+ * Version of the app this sidecar is built for, checked by the host in the `hello`
+ * handshake. Kept in sync with package.json by `pnpm version:sync`; don't edit it by hand.
+ */
+export const APP_VERSION = '1.0.5';
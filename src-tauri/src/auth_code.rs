@@ -0,0 +1,520 @@
+//! Interactive user sign-in: the OAuth 2.0 authorization code flow with PKCE.
+//!
+//! [`acquire_interactive`] opens the system browser on the authorize endpoint and waits for
+//! the redirect on a loopback listener (`http://localhost:<port>`; Entra accepts any port
+//! for Mobile/Desktop redirect URIs). Each sign-in gets its own `state`, `nonce` and PKCE
+//! verifier. A callback is only accepted if its `state` matches a pending sign-in, and is
+//! answered with the same success or failure page either way. The code is redeemed
+//! by the sidecar so the account lands in the encrypted MSAL cache, and MSAL rejects an ID
+//! token whose `nonce` doesn't match.
+//!
+//! The listener uses a port from `ENTRA_TOKEN_STUDIO_REDIRECT_PORTS` (`50100` or
+//! `50100-50110`) if set, otherwise any free port.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::CONTENT_TYPE;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::time::Instant;
+
+use crate::backend::UserTokenRequest;
+use crate::client_credentials::{DEFAULT_AUTHORITY_HOST, HANDLER_ERROR_CODE};
+use crate::error::CommandError;
+use crate::models::TokenResponse;
+use crate::sidecar::{self, CallOptions};
+
+const REDIRECT_PORTS_ENV_VAR: &str = "ENTRA_TOKEN_STUDIO_REDIRECT_PORTS";
+/// Requested on every interactive sign-in, like MSAL does
+const BASE_SCOPES: [&str; 3] = ["openid", "profile", "offline_access"];
+/// How long to wait for the browser redirect when the caller doesn't set a timeout
+const AUTH_CODE_TIMEOUT: Duration = Duration::from_secs(120);
+const AUTH_CODE_TIMEOUT_MESSAGE: &str = "Authentication timed out waiting for the browser to redirect. If you see AADSTS50011, add http://localhost to the app's Mobile/Desktop redirect URIs and try again.";
+/// `data.code` of the sidecar's silent-request errors (`acquire_user_token` in
+/// sidecar/src/handlers/user-token.ts)
+const NO_CACHED_ACCOUNT: &str = "no_cached_account";
+const INTERACTION_REQUIRED: &str = "interaction_required";
+
+/// A redirect that matched a pending sign-in
+struct Callback {
+    code: String,
+    /// Where the code was delivered; the token request must name the same URI
+    redirect_uri: String,
+}
+
+struct PendingSignIn {
+    /// Caller's `requestId`, for `cancel_request`
+    request_id: Option<String>,
+    sender: oneshot::Sender<Result<Callback, CommandError>>,
+}
+
+lazy_static::lazy_static! {
+    /// Sign-ins waiting for their redirect, by `state`
+    static ref PENDING: Mutex<HashMap<String, PendingSignIn>> = Mutex::new(HashMap::new());
+}
+
+/// Removes a sign-in from [`PENDING`] however its wait ends.
+struct PendingGuard(String);
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        PENDING.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Arguments the sidecar needs to redeem a code (`redeem_auth_code`)
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RedeemRequest<'a> {
+    client_id: &'a str,
+    tenant_id: &'a str,
+    scopes: &'a [String],
+    code: String,
+    redirect_uri: String,
+    code_verifier: String,
+    state: String,
+    nonce: String,
+}
+
+/// Arguments of a silent (cache-only) `acquire_user_token` call
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SilentRequest<'a> {
+    client_id: &'a str,
+    tenant_id: &'a str,
+    scopes: &'a [String],
+    account_home_account_id: Option<&'a str>,
+}
+
+fn random_token() -> Result<String, CommandError> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(CommandError::random)?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Ports to try for the loopback listener; `[0]` (any free port) unless configured.
+fn redirect_ports() -> Result<Vec<u16>, CommandError> {
+    let Ok(value) = std::env::var(REDIRECT_PORTS_ENV_VAR) else {
+        return Ok(vec![0]);
+    };
+    let invalid = || {
+        CommandError::validation(
+            REDIRECT_PORTS_ENV_VAR,
+            format!("{} must be a port or a range like 50100-50110, not '{}'", REDIRECT_PORTS_ENV_VAR, value),
+        )
+    };
+    let (start, end) = match value.trim().split_once('-') {
+        Some((start, end)) => (start.trim(), end.trim()),
+        None => (value.trim(), value.trim()),
+    };
+    let start: u16 = start.parse().map_err(|_| invalid())?;
+    let end: u16 = end.parse().map_err(|_| invalid())?;
+    if start == 0 || end < start {
+        return Err(invalid());
+    }
+    Ok((start..=end).collect())
+}
+
+async fn bind_loopback() -> Result<TcpListener, CommandError> {
+    let ports = redirect_ports()?;
+    let mut last_error = None;
+    for port in &ports {
+        match TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, *port))).await {
+            Ok(listener) => return Ok(listener),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let range = match (ports.first(), ports.last()) {
+        (Some(first), Some(last)) if first != last => format!("{}-{}", first, last),
+        (Some(port), _) => port.to_string(),
+        _ => String::new(),
+    };
+    Err(CommandError::Io {
+        path: format!("127.0.0.1:{}", range),
+        message: format!(
+            "No free port for the sign-in redirect in {}: {}",
+            range,
+            last_error.map(|e| e.to_string()).unwrap_or_default()
+        ),
+    })
+}
+
+/// `{authority}/{tenant}/oauth2/v2.0/authorize` with the PKCE challenge for `code_verifier`
+fn authorize_url(
+    request: &UserTokenRequest,
+    scopes: &[String],
+    redirect_uri: &str,
+    state: &str,
+    nonce: &str,
+    code_verifier: &str,
+) -> Result<url::Url, CommandError> {
+    let challenge = URL_SAFE_NO_PAD.encode(ring::digest::digest(&ring::digest::SHA256, code_verifier.as_bytes()));
+    let endpoint = format!("{}/{}/oauth2/v2.0/authorize", DEFAULT_AUTHORITY_HOST, request.tenant_id);
+    let mut params = vec![
+        ("client_id", request.client_id.as_str()),
+        ("response_type", "code"),
+        ("response_mode", "query"),
+        ("redirect_uri", redirect_uri),
+        ("state", state),
+        ("nonce", nonce),
+        ("code_challenge", challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ];
+    let scope = scopes.join(" ");
+    params.push(("scope", scope.as_str()));
+    if let Some(prompt) = request.prompt.as_deref() {
+        params.push(("prompt", prompt));
+    }
+    url::Url::parse_with_params(&endpoint, &params)
+        .map_err(|e| CommandError::validation("tenantId", format!("Invalid authorize URL: {}", e)))
+}
+
+/// Outcome of a redirect: the message for the page shown in the browser, `Err` for a failure
+type PageMessage = Result<&'static str, &'static str>;
+
+/// Hand a redirect's query parameters to the pending sign-in with the same `state`.
+/// `Err` if it doesn't match one, and nothing was accepted.
+fn complete(params: &HashMap<String, String>, redirect_uri: String) -> Result<PageMessage, &'static str> {
+    let Some(state) = params.get("state") else {
+        return Err("The sign-in response is missing its state parameter.");
+    };
+    let Some(pending) = PENDING.lock().unwrap().remove(state) else {
+        log::warn!("Ignoring a sign-in redirect that doesn't match a pending request");
+        return Err("This sign-in response doesn't match a sign-in started by Entra Token Studio.");
+    };
+
+    let (result, page) = if let Some(error) = params.get("error") {
+        let message = params.get("error_description").unwrap_or(error).clone();
+        let error = CommandError::Rpc {
+            code: HANDLER_ERROR_CODE,
+            message,
+            data: Some(serde_json::json!({ "code": error })),
+        };
+        (Err(error), Err("Sign-in failed. Return to Entra Token Studio for details."))
+    } else if let Some(code) = params.get("code") {
+        let callback = Callback {
+            code: code.clone(),
+            redirect_uri,
+        };
+        (Ok(callback), Ok("You can close this tab and return to Entra Token Studio."))
+    } else {
+        let error = CommandError::validation("code", "The sign-in response has neither a code nor an error");
+        (Err(error), Err("The sign-in response has neither a code nor an error."))
+    };
+    // The waiter is gone if it timed out or was cancelled in the meantime.
+    let _ = pending.sender.send(result);
+    Ok(page)
+}
+
+/// Abort the sign-in started with `request_id`. Returns `false` if there is none.
+pub fn cancel(request_id: &str) -> bool {
+    let mut pending = PENDING.lock().unwrap();
+    let state = pending
+        .iter()
+        .find(|(_, pending)| pending.request_id.as_deref() == Some(request_id))
+        .map(|(state, _)| state.clone());
+    match state.and_then(|state| pending.remove(&state)) {
+        Some(pending) => {
+            let _ = pending.sender.send(Err(CommandError::cancelled()));
+            true
+        }
+        None => false,
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// The page shown in the browser after a redirect
+fn result_page(outcome: PageMessage) -> Response<Full<Bytes>> {
+    let (status, title, accent, icon, message) = match outcome {
+        Ok(message) => (StatusCode::OK, "Authentication Successful", "#22c55e", "&#10003;", message),
+        Err(message) => (StatusCode::BAD_REQUEST, "Authentication Failed", "#ef4444", "&#10007;", message),
+    };
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="UTF-8">
+  <meta name="viewport" content="width=device-width, initial-scale=1.0">
+  <title>{title} | Entra Token Studio</title>
+  <style>
+    body {{ margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center;
+      font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+      background: linear-gradient(135deg, #0f172a 0%, #1e293b 100%); color: #f1f5f9; }}
+    .card {{ background: rgba(30, 41, 59, 0.8); border: 1px solid rgba(148, 163, 184, 0.2); border-radius: 16px;
+      padding: 48px; text-align: center; max-width: 420px; }}
+    .brand {{ font-size: 14px; font-weight: 600; color: #94a3b8; margin-bottom: 24px; }}
+    .icon {{ font-size: 56px; margin-bottom: 20px; color: {accent}; }}
+    h1 {{ font-size: 22px; font-weight: 600; margin-bottom: 12px; }}
+    p {{ color: #94a3b8; font-size: 14px; line-height: 1.6; }}
+  </style>
+</head>
+<body>
+  <div class="card">
+    <div class="brand">Entra Token Studio</div>
+    <div class="icon">{icon}</div>
+    <h1>{title}</h1>
+    <p>{message}</p>
+  </div>
+</body>
+</html>"#,
+        message = escape_html(message),
+    );
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Full::new(Bytes::from(html)))
+        .unwrap()
+}
+
+async fn handle_redirect(request: Request<Incoming>, redirect_uri: String) -> Response<Full<Bytes>> {
+    if request.method() != Method::GET || request.uri().path() != "/" {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+    }
+    let params: HashMap<String, String> = request
+        .uri()
+        .query()
+        .map(|q| url::form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    result_page(complete(&params, redirect_uri).and_then(|page| page))
+}
+
+/// Answer redirects on `listener` until `shutdown` fires.
+async fn serve(listener: TcpListener, redirect_uri: String, mut shutdown: oneshot::Receiver<()>) {
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::warn!("Sign-in redirect listener failed to accept a connection: {}", e);
+                    continue;
+                }
+            },
+        };
+        let redirect_uri = redirect_uri.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let redirect_uri = redirect_uri.clone();
+                async move { Ok::<_, Infallible>(handle_redirect(request, redirect_uri).await) }
+            });
+            if let Err(e) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                log::debug!("Sign-in redirect connection from {} ended with error: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// How long to wait for the user to finish signing in, across all browser sign-ins of one
+/// request: the caller's timeout, if any.
+fn redirect_timeout(options: &CallOptions) -> Duration {
+    options.timeout.unwrap_or(AUTH_CODE_TIMEOUT)
+}
+
+/// Sign in through the browser for `scopes` and redeem the code with the sidecar. The
+/// redirect must arrive by `deadline`, which is shared by every sign-in of one request.
+async fn sign_in(
+    request: &UserTokenRequest,
+    scopes: &[String],
+    options: &CallOptions,
+    deadline: Instant,
+) -> Result<TokenResponse, CommandError> {
+    let listener = bind_loopback().await?;
+    let port = listener.local_addr().map(|a| a.port()).unwrap_or_default();
+    // Mobile/Desktop platform uses http://localhost (no path), which avoids matching an
+    // SPA's /auth/callback registration.
+    let redirect_uri = format!("http://localhost:{}", port);
+
+    let state = random_token()?;
+    let nonce = random_token()?;
+    let code_verifier = random_token()?;
+    let url = authorize_url(request, scopes, &redirect_uri, &state, &nonce, &code_verifier)?;
+
+    let (sender, receiver) = oneshot::channel();
+    PENDING.lock().unwrap().insert(
+        state.clone(),
+        PendingSignIn {
+            request_id: options.request_id.clone(),
+            sender,
+        },
+    );
+    let _guard = PendingGuard(state.clone());
+    let (shutdown, shutdown_rx) = oneshot::channel();
+    tokio::spawn(serve(listener, redirect_uri, shutdown_rx));
+
+    if let Err(e) = tauri_plugin_opener::open_url(url.as_str(), None::<&str>) {
+        log::warn!("Failed to open the browser for sign-in: {}", e);
+    }
+
+    let waited = tokio::time::timeout_at(deadline, receiver).await;
+    let _ = shutdown.send(());
+    let callback = match waited {
+        Ok(Ok(result)) => result?,
+        Ok(Err(_)) => return Err(CommandError::cancelled()),
+        Err(_) => {
+            return Err(CommandError::Timeout {
+                method: "acquire_user_token".to_string(),
+                timeout_ms: redirect_timeout(options).as_millis() as u64,
+                message: AUTH_CODE_TIMEOUT_MESSAGE.to_string(),
+            })
+        }
+    };
+
+    let redeem = RedeemRequest {
+        client_id: &request.client_id,
+        tenant_id: &request.tenant_id,
+        scopes,
+        code: callback.code,
+        redirect_uri: callback.redirect_uri,
+        code_verifier,
+        state,
+        nonce,
+    };
+    sidecar::call_typed("redeem_auth_code", serde_json::json!(redeem), options.clone()).await
+}
+
+async fn acquire_silent(
+    request: &UserTokenRequest,
+    scopes: &[String],
+    account_home_account_id: Option<&str>,
+    options: &CallOptions,
+) -> Result<TokenResponse, CommandError> {
+    let silent = SilentRequest {
+        client_id: &request.client_id,
+        tenant_id: &request.tenant_id,
+        scopes,
+        account_home_account_id,
+    };
+    sidecar::call_typed("acquire_user_token", serde_json::json!(silent), options.clone()).await
+}
+
+/// `Rpc` error the sidecar reports (as `data.code`) when the cache can't answer without the user
+fn is_rpc_error(error: &CommandError, code: &str) -> bool {
+    match error {
+        CommandError::Rpc { data: Some(data), .. } => data.get("code").and_then(|c| c.as_str()) == Some(code),
+        _ => false,
+    }
+}
+
+/// Deduplicated, trimmed scopes with the OIDC base scopes first
+fn interactive_scopes(scopes: &[&str]) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for scope in BASE_SCOPES.iter().chain(scopes) {
+        let scope = scope.trim();
+        if !scope.is_empty() && !result.iter().any(|s| s == scope) {
+            result.push(scope.to_string());
+        }
+    }
+    result
+}
+
+/// Resource-prefixed scopes (e.g. Azure management, custom APIs); the v2 endpoint doesn't
+/// allow mixing several resources in one request.
+fn is_resource_prefixed(scope: &str) -> bool {
+    let scope = scope.trim();
+    !scope.is_empty() && (scope.contains("://") || scope.contains('/'))
+}
+
+/// User token from the cache, or through a browser sign-in if the cache can't provide one
+/// (always with `prompt`). `silent_only` requests fail with `no_cached_account` or
+/// `interaction_required` instead of opening the browser.
+pub async fn acquire_interactive(request: &UserTokenRequest, options: CallOptions) -> Result<TokenResponse, CommandError> {
+    let account = request.account_home_account_id.as_deref();
+    let cached_account = if request.prompt.is_none() {
+        match acquire_silent(request, &request.scopes, account, &options).await {
+            Ok(token) => return Ok(token),
+            Err(e) if request.silent_only.unwrap_or(false) => return Err(e),
+            Err(e) if is_rpc_error(&e, NO_CACHED_ACCOUNT) => false,
+            // Any other cache failure (e.g. `interaction_required`) falls back to the browser.
+            Err(CommandError::Rpc { .. }) => true,
+            Err(e) => return Err(e),
+        }
+    } else {
+        true
+    };
+
+    let requested: Vec<&str> = request.scopes.iter().map(String::as_str).collect();
+    let deadline = Instant::now() + redirect_timeout(&options);
+    if cached_account {
+        return sign_in(request, &interactive_scopes(&requested), &options, deadline).await;
+    }
+
+    // First sign-in: establish a baseline session (OIDC + offline_access) the way the web
+    // app does. Graph-style scopes can include User.Read for the profile; other resources
+    // can't be mixed with Graph, so they get a baseline login and then their own token.
+    if !requested.iter().any(|scope| is_resource_prefixed(scope)) {
+        let mut scopes = vec!["User.Read"];
+        scopes.extend(&requested);
+        return sign_in(request, &interactive_scopes(&scopes), &options, deadline).await;
+    }
+    let baseline = sign_in(request, &interactive_scopes(&["User.Read"]), &options, deadline).await?;
+    let account = baseline.account.as_ref().map(|account| account.home_account_id.as_str());
+    match acquire_silent(request, &request.scopes, account, &options).await {
+        Err(e) if is_rpc_error(&e, INTERACTION_REQUIRED) => {
+            sign_in(request, &interactive_scopes(&requested), &options, deadline).await
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc_error(message: &str, data: Option<serde_json::Value>) -> CommandError {
+        CommandError::Rpc {
+            code: -32000,
+            message: message.to_string(),
+            data,
+        }
+    }
+
+    #[test]
+    fn browser_fallback_matches_the_error_code() {
+        let no_account = rpc_error(
+            "No account is signed in for this app",
+            Some(serde_json::json!({ "code": NO_CACHED_ACCOUNT })),
+        );
+        assert!(is_rpc_error(&no_account, NO_CACHED_ACCOUNT));
+        assert!(!is_rpc_error(&no_account, INTERACTION_REQUIRED));
+
+        // The message alone doesn't count, nor does a non-RPC error.
+        assert!(!is_rpc_error(&rpc_error(NO_CACHED_ACCOUNT, None), NO_CACHED_ACCOUNT));
+        assert!(!is_rpc_error(
+            &rpc_error("x", Some(serde_json::json!({ "code": 7 }))),
+            INTERACTION_REQUIRED
+        ));
+        assert!(!is_rpc_error(&CommandError::transport(INTERACTION_REQUIRED), INTERACTION_REQUIRED));
+    }
+
+    #[test]
+    fn redirect_wait_honors_the_callers_timeout() {
+        assert_eq!(redirect_timeout(&CallOptions::default()), AUTH_CODE_TIMEOUT);
+        let long = CallOptions::new(Some(600_000), None);
+        assert_eq!(redirect_timeout(&long), Duration::from_secs(600));
+        let short = CallOptions::new(Some(5_000), None);
+        assert_eq!(redirect_timeout(&short), Duration::from_secs(5));
+    }
+}
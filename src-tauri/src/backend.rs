@@ -0,0 +1,787 @@
+//! Token backends: where commands get tokens, accounts and Azure listings from.
+//!
+//! Commands in `lib.rs` validate their arguments and then delegate to a [`TokenBackend`].
+//! [`SidecarBackend`] forwards to the Node sidecar (MSAL + Azure SDK) and is the default.
+//! [`NativeBackend`] acquires app tokens in Rust and defers the rest to the sidecar.
+//! [`DemoBackend`] answers from canned data without Node, Azure or network access, which
+//! is useful for demos, screenshots and exercising command logic offline.
+//!
+//! The default backend is chosen at startup from `ENTRA_TOKEN_STUDIO_BACKEND`
+//! (`sidecar`, `native` or `demo`); an app can override it with its own `backend` setting.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::auth_code;
+use crate::device_code::{self, DeviceCodeInfo, DeviceCodeProgress, DeviceCodeRequest};
+use crate::error::CommandError;
+use crate::models::{
+    AccountInfo, AuthStorageStatus, AzureAppRegistration, AzureCliResult, AzureKeyVault, AzureSubscription,
+    AzureVaultCredential, CredentialStatus, TokenResponse, ValidationResult,
+};
+use crate::native::NativeBackend;
+use crate::sidecar::{self, CallOptions};
+use crate::{AzureAppFilters, KeyVaultConfig, TokenAppConfig};
+
+const BACKEND_ENV_VAR: &str = "ENTRA_TOKEN_STUDIO_BACKEND";
+
+/// Which [`TokenBackend`] serves a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    Sidecar,
+    Native,
+    Demo,
+}
+
+impl BackendKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sidecar => "sidecar",
+            Self::Native => "native",
+            Self::Demo => "demo",
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref DEFAULT_BACKEND: BackendKind = match std::env::var(BACKEND_ENV_VAR) {
+        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
+            "" | "sidecar" => BackendKind::Sidecar,
+            "native" => BackendKind::Native,
+            "demo" => BackendKind::Demo,
+            other => {
+                log::warn!("Unknown {} '{}'; using the sidecar backend", BACKEND_ENV_VAR, other);
+                BackendKind::Sidecar
+            }
+        },
+        Err(_) => BackendKind::Sidecar,
+    };
+}
+
+/// The backend used when an app doesn't choose one
+pub fn default_kind() -> BackendKind {
+    *DEFAULT_BACKEND
+}
+
+/// Resolve a per-app backend choice, falling back to the startup default.
+pub fn get(kind: Option<BackendKind>) -> &'static dyn TokenBackend {
+    match kind.unwrap_or_else(default_kind) {
+        BackendKind::Sidecar => &SidecarBackend,
+        BackendKind::Native => &NativeBackend,
+        BackendKind::Demo => &DemoBackend,
+    }
+}
+
+/// Backend for an app token request. Local credential sources can only be read
+/// in-process, so they always use the native backend (unless the app is in demo mode).
+pub fn for_app(config: &TokenAppConfig) -> &'static dyn TokenBackend {
+    get(Some(kind_for_app(config, default_kind())))
+}
+
+/// Which backend [`for_app`] picks when the startup default is `default`
+fn kind_for_app(config: &TokenAppConfig, default: BackendKind) -> BackendKind {
+    let kind = config.backend.unwrap_or(default);
+    if config.credential.is_some() && kind != BackendKind::Demo {
+        BackendKind::Native
+    } else {
+        kind
+    }
+}
+
+/// Arguments of a delegated (user) token request
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserTokenRequest {
+    pub client_id: String,
+    pub tenant_id: String,
+    pub scopes: Vec<String>,
+    pub prompt: Option<String>,
+    pub account_home_account_id: Option<String>,
+    pub silent_only: Option<bool>,
+}
+
+/// Everything the commands need from an identity provider
+#[async_trait]
+pub trait TokenBackend: Send + Sync {
+    async fn acquire_app_token(
+        &self,
+        config: &TokenAppConfig,
+        scopes: &[String],
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError>;
+
+    async fn acquire_user_token(
+        &self,
+        request: &UserTokenRequest,
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError>;
+
+    /// On-behalf-of exchange of `user_assertion` for `scopes`, authenticated as the app
+    async fn acquire_obo_token(
+        &self,
+        config: &TokenAppConfig,
+        user_assertion: &str,
+        scopes: &[String],
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError>;
+
+    /// Begin a device code sign-in; the outcome is reported through [`device_code::publish`].
+    async fn start_device_code(
+        &self,
+        request: &DeviceCodeRequest,
+        options: CallOptions,
+    ) -> Result<DeviceCodeInfo, CommandError>;
+
+    /// Stop polling a device code flow. Returns `false` if it already finished.
+    async fn cancel_device_code(&self, flow_id: &str) -> Result<bool, CommandError>;
+
+    async fn get_user_accounts(
+        &self,
+        client_id: &str,
+        tenant_id: &str,
+        options: CallOptions,
+    ) -> Result<Vec<AccountInfo>, CommandError>;
+
+    async fn clear_user_cache(&self, client_id: &str, tenant_id: &str) -> Result<(), CommandError>;
+
+    async fn validate_keyvault(
+        &self,
+        config: &KeyVaultConfig,
+        options: CallOptions,
+    ) -> Result<ValidationResult, CommandError>;
+
+    async fn get_credential_status(&self, options: CallOptions) -> Result<CredentialStatus, CommandError>;
+
+    async fn get_auth_storage_status(&self) -> Result<AuthStorageStatus, CommandError>;
+
+    async fn list_azure_subscriptions(
+        &self,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureSubscription>>, CommandError>;
+
+    async fn list_azure_apps(
+        &self,
+        filters: Option<&AzureAppFilters>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureAppRegistration>>, CommandError>;
+
+    async fn list_keyvaults(
+        &self,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureKeyVault>>, CommandError>;
+
+    async fn list_keyvault_secrets(
+        &self,
+        vault_name: &str,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError>;
+
+    async fn list_keyvault_certificates(
+        &self,
+        vault_name: &str,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError>;
+}
+
+/// Forwards every request to the Node sidecar
+pub struct SidecarBackend;
+
+#[async_trait]
+impl TokenBackend for SidecarBackend {
+    async fn acquire_app_token(
+        &self,
+        config: &TokenAppConfig,
+        scopes: &[String],
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        sidecar::call_typed(
+            "acquire_app_token",
+            serde_json::json!({ "config": config, "scopes": scopes }),
+            options,
+        )
+        .await
+    }
+
+    async fn acquire_user_token(
+        &self,
+        request: &UserTokenRequest,
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        // The browser part runs in Rust; the sidecar answers from and fills the MSAL cache.
+        auth_code::acquire_interactive(request, options).await
+    }
+
+    async fn acquire_obo_token(
+        &self,
+        config: &TokenAppConfig,
+        user_assertion: &str,
+        scopes: &[String],
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        sidecar::call_typed(
+            "acquire_obo_token",
+            serde_json::json!({ "config": config, "userAssertion": user_assertion, "scopes": scopes }),
+            options,
+        )
+        .await
+    }
+
+    async fn start_device_code(
+        &self,
+        request: &DeviceCodeRequest,
+        options: CallOptions,
+    ) -> Result<DeviceCodeInfo, CommandError> {
+        sidecar::call_typed("start_device_code", serde_json::json!(request), options).await
+    }
+
+    async fn cancel_device_code(&self, flow_id: &str) -> Result<bool, CommandError> {
+        sidecar::call_typed(
+            "cancel_device_code",
+            serde_json::json!({ "flowId": flow_id }),
+            CallOptions::default(),
+        )
+        .await
+    }
+
+    async fn get_user_accounts(
+        &self,
+        client_id: &str,
+        tenant_id: &str,
+        options: CallOptions,
+    ) -> Result<Vec<AccountInfo>, CommandError> {
+        sidecar::call_typed(
+            "get_user_accounts",
+            serde_json::json!({ "clientId": client_id, "tenantId": tenant_id }),
+            options,
+        )
+        .await
+    }
+
+    async fn clear_user_cache(&self, client_id: &str, tenant_id: &str) -> Result<(), CommandError> {
+        sidecar::call(
+            "clear_user_cache",
+            serde_json::json!({ "clientId": client_id, "tenantId": tenant_id }),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn validate_keyvault(
+        &self,
+        config: &KeyVaultConfig,
+        options: CallOptions,
+    ) -> Result<ValidationResult, CommandError> {
+        sidecar::call_typed("validate_keyvault", serde_json::json!(config), options).await
+    }
+
+    async fn get_credential_status(&self, options: CallOptions) -> Result<CredentialStatus, CommandError> {
+        sidecar::call_typed("get_credential_status", serde_json::json!({}), options).await
+    }
+
+    async fn get_auth_storage_status(&self) -> Result<AuthStorageStatus, CommandError> {
+        sidecar::call_typed("get_auth_storage_status", serde_json::json!({}), CallOptions::default()).await
+    }
+
+    async fn list_azure_subscriptions(
+        &self,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureSubscription>>, CommandError> {
+        sidecar::call_typed("list_azure_subscriptions", serde_json::json!({}), options).await
+    }
+
+    async fn list_azure_apps(
+        &self,
+        filters: Option<&AzureAppFilters>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureAppRegistration>>, CommandError> {
+        let params = match filters {
+            Some(filters) => serde_json::to_value(filters).unwrap_or_else(|_| serde_json::json!({})),
+            None => serde_json::json!({}),
+        };
+        sidecar::call_typed("list_azure_apps", params, options).await
+    }
+
+    async fn list_keyvaults(
+        &self,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureKeyVault>>, CommandError> {
+        sidecar::call_typed(
+            "list_keyvaults",
+            serde_json::json!({ "subscriptionId": subscription_id }),
+            options,
+        )
+        .await
+    }
+
+    async fn list_keyvault_secrets(
+        &self,
+        vault_name: &str,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+        sidecar::call_typed(
+            "list_keyvault_secrets",
+            serde_json::json!({ "vaultName": vault_name, "subscriptionId": subscription_id }),
+            options,
+        )
+        .await
+    }
+
+    async fn list_keyvault_certificates(
+        &self,
+        vault_name: &str,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+        sidecar::call_typed(
+            "list_keyvault_certificates",
+            serde_json::json!({ "vaultName": vault_name, "subscriptionId": subscription_id }),
+            options,
+        )
+        .await
+    }
+}
+
+const DEMO_TOKEN_LIFETIME_SECS: u64 = 3600;
+const DEMO_OBJECT_ID: &str = "00000000-0000-0000-0000-0000000000de";
+const DEMO_USERNAME: &str = "demo.user@contoso.example";
+const DEMO_SUBSCRIPTION_ID: &str = "00000000-0000-0000-0000-000000000001";
+const DEMO_VAULT_NAME: &str = "demo-vault";
+/// Demo device code sign-ins complete on their own after this long
+const DEMO_DEVICE_CODE_DELAY: Duration = Duration::from_secs(5);
+
+lazy_static::lazy_static! {
+    /// Demo device code flows that haven't completed or been cancelled yet
+    static ref DEMO_FLOWS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+/// Offline backend returning well-formed but unsigned tokens and canned Azure listings.
+///
+/// Tokens decode like real Entra v2 tokens (audience, scopes/roles, expiry) but carry
+/// `alg: none` and an empty signature, so nothing that validates signatures accepts them.
+pub struct DemoBackend;
+
+impl DemoBackend {
+    fn demo_account(tenant_id: &str) -> AccountInfo {
+        AccountInfo {
+            home_account_id: format!("{}.{}", DEMO_OBJECT_ID, tenant_id),
+            environment: "login.microsoftonline.com".to_string(),
+            tenant_id: tenant_id.to_string(),
+            username: DEMO_USERNAME.to_string(),
+            name: Some("Demo User".to_string()),
+        }
+    }
+
+    fn demo_token(
+        client_id: &str,
+        tenant_id: &str,
+        scopes: &[String],
+        account: Option<AccountInfo>,
+    ) -> TokenResponse {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let expires_at = now + DEMO_TOKEN_LIFETIME_SECS;
+
+        // `https://graph.microsoft.com/User.Read` -> audience + scope name; bare names are Graph scopes.
+        let audience = scopes
+            .iter()
+            .find_map(|scope| scope.rsplit_once('/').map(|(resource, _)| resource.to_string()))
+            .unwrap_or_else(|| "https://graph.microsoft.com".to_string());
+        let names: Vec<&str> = scopes
+            .iter()
+            .map(|scope| scope.rsplit_once('/').map_or(scope.as_str(), |(_, name)| name))
+            .filter(|name| *name != ".default")
+            .collect();
+
+        let mut claims = serde_json::json!({
+            "aud": audience,
+            "iss": format!("https://login.microsoftonline.com/{}/v2.0", tenant_id),
+            "tid": tenant_id,
+            "oid": DEMO_OBJECT_ID,
+            "azp": client_id,
+            "iat": now,
+            "nbf": now,
+            "exp": expires_at,
+            "ver": "2.0",
+        });
+        match &account {
+            Some(account) => {
+                claims["scp"] = serde_json::json!(if names.is_empty() { "User.Read".to_string() } else { names.join(" ") });
+                claims["preferred_username"] = serde_json::json!(account.username);
+                claims["name"] = serde_json::json!(account.name);
+            }
+            None => claims["roles"] = serde_json::json!(["Demo.Read.All"]),
+        }
+
+        let header = URL_SAFE_NO_PAD.encode(br#"{"alg":"none","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(claims.to_string());
+        TokenResponse {
+            access_token: format!("{}.{}.", header, payload),
+            token_type: "Bearer".to_string(),
+            expires_on: Some(iso8601(expires_at)),
+            scopes: scopes.to_vec(),
+            account,
+        }
+    }
+
+    fn demo_credentials(vault_name: &str, kind: &str) -> AzureCliResult<Vec<AzureVaultCredential>> {
+        AzureCliResult {
+            success: true,
+            data: Some(vec![AzureVaultCredential {
+                name: format!("{}-demo-{}", vault_name, kind),
+                enabled: Some(true),
+                expires: None,
+            }]),
+            error: None,
+        }
+    }
+}
+
+#[async_trait]
+impl TokenBackend for DemoBackend {
+    async fn acquire_app_token(
+        &self,
+        config: &TokenAppConfig,
+        scopes: &[String],
+        _options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        Ok(Self::demo_token(&config.client_id, &config.tenant_id, scopes, None))
+    }
+
+    async fn acquire_user_token(
+        &self,
+        request: &UserTokenRequest,
+        _options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        let account = Self::demo_account(&request.tenant_id);
+        Ok(Self::demo_token(&request.client_id, &request.tenant_id, &request.scopes, Some(account)))
+    }
+
+    async fn acquire_obo_token(
+        &self,
+        config: &TokenAppConfig,
+        _user_assertion: &str,
+        scopes: &[String],
+        _options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        let account = Self::demo_account(&config.tenant_id);
+        Ok(Self::demo_token(&config.client_id, &config.tenant_id, scopes, Some(account)))
+    }
+
+    async fn start_device_code(
+        &self,
+        request: &DeviceCodeRequest,
+        _options: CallOptions,
+    ) -> Result<DeviceCodeInfo, CommandError> {
+        let flow = request.clone();
+        DEMO_FLOWS.lock().unwrap().insert(flow.flow_id.clone());
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(DEMO_DEVICE_CODE_DELAY).await;
+            if !DEMO_FLOWS.lock().unwrap().remove(&flow.flow_id) {
+                return;
+            }
+            let account = Self::demo_account(&flow.tenant_id);
+            let token = Self::demo_token(&flow.client_id, &flow.tenant_id, &flow.scopes, Some(account));
+            device_code::publish(DeviceCodeProgress::completed(&flow.flow_id, token));
+        });
+        Ok(DeviceCodeInfo {
+            flow_id: request.flow_id.clone(),
+            user_code: "DEMO1234".to_string(),
+            verification_uri: "https://microsoft.com/devicelogin".to_string(),
+            message: format!(
+                "Demo backend: sign-in completes automatically in {} seconds",
+                DEMO_DEVICE_CODE_DELAY.as_secs()
+            ),
+            expires_in: 900,
+            interval: DEMO_DEVICE_CODE_DELAY.as_secs(),
+        })
+    }
+
+    async fn cancel_device_code(&self, flow_id: &str) -> Result<bool, CommandError> {
+        Ok(DEMO_FLOWS.lock().unwrap().remove(flow_id))
+    }
+
+    async fn get_user_accounts(
+        &self,
+        _client_id: &str,
+        tenant_id: &str,
+        _options: CallOptions,
+    ) -> Result<Vec<AccountInfo>, CommandError> {
+        Ok(vec![Self::demo_account(tenant_id)])
+    }
+
+    async fn clear_user_cache(&self, _client_id: &str, _tenant_id: &str) -> Result<(), CommandError> {
+        Ok(())
+    }
+
+    async fn validate_keyvault(
+        &self,
+        config: &KeyVaultConfig,
+        _options: CallOptions,
+    ) -> Result<ValidationResult, CommandError> {
+        Ok(ValidationResult {
+            valid: true,
+            credential_type: config.credential_type.clone(),
+            message: Some("Demo backend: Key Vault access is not checked".to_string()),
+        })
+    }
+
+    async fn get_credential_status(&self, _options: CallOptions) -> Result<CredentialStatus, CommandError> {
+        Ok(CredentialStatus {
+            available: true,
+            message: "Demo backend: no Azure credentials are used".to_string(),
+        })
+    }
+
+    async fn get_auth_storage_status(&self) -> Result<AuthStorageStatus, CommandError> {
+        Ok(AuthStorageStatus {
+            encrypted: false,
+            cache_dir: String::new(),
+            key_source: "none".to_string(),
+        })
+    }
+
+    async fn list_azure_subscriptions(
+        &self,
+        _options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureSubscription>>, CommandError> {
+        Ok(AzureCliResult {
+            success: true,
+            data: Some(vec![AzureSubscription {
+                id: DEMO_SUBSCRIPTION_ID.to_string(),
+                name: "Demo Subscription".to_string(),
+                tenant_id: "00000000-0000-0000-0000-000000000000".to_string(),
+                is_default: Some(true),
+                state: Some("Enabled".to_string()),
+            }]),
+            error: None,
+        })
+    }
+
+    async fn list_azure_apps(
+        &self,
+        _filters: Option<&AzureAppFilters>,
+        _options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureAppRegistration>>, CommandError> {
+        Ok(AzureCliResult {
+            success: true,
+            data: Some(vec![AzureAppRegistration {
+                app_id: "11111111-1111-1111-1111-111111111111".to_string(),
+                display_name: "Demo API".to_string(),
+            }]),
+            error: None,
+        })
+    }
+
+    async fn list_keyvaults(
+        &self,
+        _subscription_id: Option<&str>,
+        _options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureKeyVault>>, CommandError> {
+        Ok(AzureCliResult {
+            success: true,
+            data: Some(vec![AzureKeyVault {
+                name: DEMO_VAULT_NAME.to_string(),
+                uri: Some(format!("https://{}.vault.azure.net/", DEMO_VAULT_NAME)),
+                location: Some("westus2".to_string()),
+                resource_group: Some("demo-rg".to_string()),
+            }]),
+            error: None,
+        })
+    }
+
+    async fn list_keyvault_secrets(
+        &self,
+        vault_name: &str,
+        _subscription_id: Option<&str>,
+        _options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+        Ok(Self::demo_credentials(vault_name, "secret"))
+    }
+
+    async fn list_keyvault_certificates(
+        &self,
+        vault_name: &str,
+        _subscription_id: Option<&str>,
+        _options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+        Ok(Self::demo_credentials(vault_name, "cert"))
+    }
+}
+
+/// Format a Unix timestamp as an ISO 8601 UTC string (what MSAL returns for `expiresOn`).
+pub fn iso8601(secs: u64) -> String {
+    chrono::DateTime::from_timestamp(secs as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::credentials::CredentialSource;
+    use crate::jwt;
+
+    const CLIENT_ID: &str = "7a1c2b3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d";
+    const TENANT_ID: &str = "72f988bf-86f1-41af-91ab-2d7cd011db47";
+
+    fn config(backend: Option<BackendKind>, credential: Option<CredentialSource>) -> TokenAppConfig {
+        TokenAppConfig {
+            client_id: CLIENT_ID.to_string(),
+            tenant_id: TENANT_ID.to_string(),
+            key_vault: KeyVaultConfig::default(),
+            credential,
+            backend,
+            authority_host: None,
+            assertion_algorithm: None,
+        }
+    }
+
+    fn scopes(scopes: &[&str]) -> Vec<String> {
+        scopes.iter().map(|scope| scope.to_string()).collect()
+    }
+
+    #[test]
+    fn app_backend_defaults_to_the_startup_choice() {
+        for default in [BackendKind::Sidecar, BackendKind::Native, BackendKind::Demo] {
+            assert_eq!(kind_for_app(&config(None, None), default), default);
+        }
+    }
+
+    #[test]
+    fn per_app_backend_overrides_the_default() {
+        assert_eq!(kind_for_app(&config(Some(BackendKind::Demo), None), BackendKind::Sidecar), BackendKind::Demo);
+        assert_eq!(kind_for_app(&config(Some(BackendKind::Sidecar), None), BackendKind::Demo), BackendKind::Sidecar);
+        assert_eq!(kind_for_app(&config(Some(BackendKind::Native), None), BackendKind::Sidecar), BackendKind::Native);
+    }
+
+    #[test]
+    fn local_credentials_force_the_native_backend() {
+        let credential = || Some(CredentialSource::KeyringSecret);
+        assert_eq!(kind_for_app(&config(None, credential()), BackendKind::Sidecar), BackendKind::Native);
+        assert_eq!(
+            kind_for_app(&config(Some(BackendKind::Sidecar), credential()), BackendKind::Sidecar),
+            BackendKind::Native
+        );
+    }
+
+    #[test]
+    fn demo_wins_over_a_local_credential() {
+        let credential = || Some(CredentialSource::KeyringSecret);
+        assert_eq!(kind_for_app(&config(None, credential()), BackendKind::Demo), BackendKind::Demo);
+        assert_eq!(
+            kind_for_app(&config(Some(BackendKind::Demo), credential()), BackendKind::Sidecar),
+            BackendKind::Demo
+        );
+    }
+
+    #[tokio::test]
+    async fn demo_app_token_decodes_like_an_entra_v2_token() {
+        let token = DemoBackend
+            .acquire_app_token(
+                &config(None, None),
+                &scopes(&["api://my-api/.default"]),
+                CallOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert!(token.account.is_none());
+        assert_eq!(token.scopes, ["api://my-api/.default"]);
+
+        let decoded = jwt::decode(&token.access_token).unwrap();
+        assert_eq!(decoded.header.alg.as_deref(), Some("none"));
+        assert!(decoded.signature_bytes.is_empty());
+        assert_eq!(decoded.version, jwt::TokenVersion::V2);
+        assert_eq!(decoded.claims.aud, ["api://my-api"]);
+        assert_eq!(decoded.claims.azp.as_deref(), Some(CLIENT_ID));
+        assert_eq!(decoded.claims.tid.as_deref(), Some(TENANT_ID));
+        assert_eq!(decoded.claims.roles, ["Demo.Read.All"]);
+        assert!(decoded.claims.scp.is_empty());
+
+        let exp = decoded.claims.exp.unwrap();
+        assert_eq!(exp - decoded.claims.iat.unwrap(), DEMO_TOKEN_LIFETIME_SECS as i64);
+        assert_eq!(token.expires_on, Some(iso8601(exp as u64)));
+    }
+
+    #[tokio::test]
+    async fn demo_user_token_carries_scopes_and_the_demo_account() {
+        let request = UserTokenRequest {
+            client_id: CLIENT_ID.to_string(),
+            tenant_id: TENANT_ID.to_string(),
+            scopes: scopes(&["https://graph.microsoft.com/User.Read", "Mail.Read"]),
+            prompt: None,
+            account_home_account_id: None,
+            silent_only: None,
+        };
+        let token = DemoBackend.acquire_user_token(&request, CallOptions::default()).await.unwrap();
+        assert_eq!(token.account.as_ref().map(|a| a.username.as_str()), Some(DEMO_USERNAME));
+
+        let claims = jwt::decode(&token.access_token).unwrap().claims;
+        assert_eq!(claims.aud, ["https://graph.microsoft.com"]);
+        assert_eq!(claims.scp, ["User.Read", "Mail.Read"]);
+        assert!(claims.roles.is_empty());
+
+        // Only `.default` asked for: the token still carries a delegated scope.
+        let request = UserTokenRequest {
+            scopes: scopes(&["api://my-api/.default"]),
+            ..request
+        };
+        let token = DemoBackend.acquire_user_token(&request, CallOptions::default()).await.unwrap();
+        let claims = jwt::decode(&token.access_token).unwrap().claims;
+        assert_eq!(claims.aud, ["api://my-api"]);
+        assert_eq!(claims.scp, ["User.Read"]);
+    }
+
+    #[tokio::test]
+    async fn demo_accounts_and_listings_need_no_azure() {
+        let accounts = DemoBackend.get_user_accounts(CLIENT_ID, TENANT_ID, CallOptions::default()).await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].tenant_id, TENANT_ID);
+        assert_eq!(accounts[0].home_account_id, format!("{}.{}", DEMO_OBJECT_ID, TENANT_ID));
+
+        let vaults = DemoBackend.list_keyvaults(None, CallOptions::default()).await.unwrap();
+        assert!(vaults.success);
+        assert_eq!(vaults.data.unwrap()[0].name, DEMO_VAULT_NAME);
+
+        let secrets = DemoBackend.list_keyvault_secrets("kv", None, CallOptions::default()).await.unwrap();
+        assert_eq!(secrets.data.unwrap()[0].name, "kv-demo-secret");
+        let certificates = DemoBackend.list_keyvault_certificates("kv", None, CallOptions::default()).await.unwrap();
+        assert_eq!(certificates.data.unwrap()[0].name, "kv-demo-cert");
+
+        let status = DemoBackend.get_credential_status(CallOptions::default()).await.unwrap();
+        assert!(status.available);
+    }
+
+    #[tokio::test]
+    async fn demo_device_code_cancels_only_running_flows() {
+        assert!(!DemoBackend.cancel_device_code("never-started").await.unwrap());
+
+        let request = DeviceCodeRequest {
+            flow_id: "demo-flow".to_string(),
+            client_id: CLIENT_ID.to_string(),
+            tenant_id: TENANT_ID.to_string(),
+            scopes: vec!["User.Read".to_string()],
+        };
+        let info = DemoBackend.start_device_code(&request, CallOptions::default()).await.unwrap();
+        assert_eq!(info.flow_id, "demo-flow");
+        assert!(DemoBackend.cancel_device_code("demo-flow").await.unwrap());
+        assert!(!DemoBackend.cancel_device_code("demo-flow").await.unwrap());
+    }
+
+    #[test]
+    fn iso8601_formats_utc_with_milliseconds() {
+        assert_eq!(iso8601(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(iso8601(1_700_000_000), "2023-11-14T22:13:20.000Z");
+        assert_eq!(iso8601(951_782_400), "2000-02-29T00:00:00.000Z");
+    }
+}
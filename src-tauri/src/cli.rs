@@ -0,0 +1,1136 @@
+//! Headless command-line mode.
+//!
+//! `entra-token-studio <command> ...` runs a single command without opening a window,
+//! using the same sidecar, encrypted MSAL cache and saved apps as the desktop app.
+//! Results go to stdout and diagnostics to stderr, so the binary can be used in scripts.
+//!
+//! Exit codes:
+//!
+//! | Code | Meaning                                                        |
+//! |------|----------------------------------------------------------------|
+//! | 0    | Success                                                        |
+//! | 1    | Unexpected failure (file I/O, malformed sidecar response)      |
+//! | 2    | Usage error (unknown command, missing or invalid arguments)    |
+//! | 3    | Saved app not found (or the name is ambiguous)                 |
+//! | 4    | Sidecar unavailable (Node.js missing, sidecar not built/crashed) |
+//! | 5    | Token request failed (Entra, Key Vault or Azure CLI error)     |
+//! | 6    | Timed out or cancelled                                         |
+//! | 7    | The token passed to `decode` is malformed                      |
+//!
+//! The binary also works as an Azure CLI shim for `AzureCliCredential`: started as `az`
+//! (a copy or symlink named `az` earlier on `PATH`) or with `az` as the first argument, it
+//! answers `az account get-access-token` with the Azure CLI's JSON, using the saved app
+//! named by `ENTRA_TOKEN_STUDIO_AZ_APP`. Errors follow the Azure CLI's conventions there
+//! (`ERROR: ...` on stderr, exit code 1).
+//!
+//! `git-credential` is a git credential helper; see [`crate::git_credential`].
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::backend::{self, BackendKind};
+use crate::client_credentials::{self, AssertionAlgorithm, CertificateCredential, ClientCredential};
+use crate::device_code::{self, DeviceCodeStatus};
+use crate::error::CommandError;
+use crate::git_credential::{self, GitIdentity};
+use crate::models::TokenResponse;
+use crate::saved_apps::{self, SavedApp};
+use crate::sidecar::{self, CallOptions};
+use crate::token_server::resource_scope;
+use crate::TokenAppConfig;
+
+/// Must match `identifier` in tauri.conf.json so the CLI shares the desktop app's data.
+pub(crate) const APP_IDENTIFIER: &str = "com.entra-token-studio";
+
+pub(crate) const EXIT_OK: i32 = 0;
+pub(crate) const EXIT_FAILURE: i32 = 1;
+pub(crate) const EXIT_USAGE: i32 = 2;
+const EXIT_APP_NOT_FOUND: i32 = 3;
+const EXIT_SIDECAR_UNAVAILABLE: i32 = 4;
+const EXIT_TOKEN_FAILED: i32 = 5;
+const EXIT_TIMED_OUT: i32 = 6;
+const EXIT_INVALID_TOKEN: i32 = 7;
+
+const COMMANDS: &[&str] = &["token", "decode", "apps", "az", "git-credential", "help", "--help", "-h", "--version", "-V"];
+
+/// Saved app whose tokens the `az` shim serves
+const AZ_APP_ENV: &str = "ENTRA_TOKEN_STUDIO_AZ_APP";
+/// `app` (default) or `user`
+const AZ_IDENTITY_ENV: &str = "ENTRA_TOKEN_STUDIO_AZ_IDENTITY";
+/// Cached account to use for user tokens (the first cached account if unset)
+const AZ_ACCOUNT_ENV: &str = "ENTRA_TOKEN_STUDIO_AZ_ACCOUNT";
+/// Reported as `subscription`, which the studio otherwise has no notion of
+const AZ_SUBSCRIPTION_ENV: &str = "ENTRA_TOKEN_STUDIO_AZ_SUBSCRIPTION";
+
+const USAGE: &str = "\
+Usage: entra-token-studio <command> [options]
+
+Commands:
+  token app   --app <app> (--scope <scope>... | --resource <uri>) [--json] [--timeout <seconds>]
+              Acquire an app token using the saved app's Key Vault credential
+  token app   --client-id <id> --tenant-id <id> (--client-secret-env <var> | --cert <pem> [--key <pem>])
+              (--scope <scope>... | --resource <uri>) [--algorithm RS256|PS256] [--json] [--timeout <seconds>]
+              Acquire an app token natively with a local secret or certificate (no Node.js needed)
+              --authority-host <url> sends either form to another token endpoint host (native backend)
+  token user  (--app <app> | --client-id <id> --tenant-id <id>) (--scopes \"<s1> <s2>\" | --scope <scope>...)
+              [--account <username>] [--prompt <prompt>] [--silent] [--json] [--timeout <seconds>]
+              Acquire a user token (opens the browser unless a cached token can be used)
+  token user  (--app <app> | --client-id <id> --tenant-id <id>) (--scopes \"<s1> <s2>\" | --scope <scope>...)
+              --device-code [--json] [--timeout <seconds>]
+              Sign in on any device with a code printed to stderr, then print the token
+  decode      <jwt | ->
+              Decode a token (`-` reads it from stdin) and print its header and claims as JSON
+  apps list   [--json]
+              List apps saved in the desktop app
+  az account get-access-token (--resource <uri> | --scope <scope>...) [--tenant <id>] [--output json|tsv]
+              Azure CLI-compatible token output for the app in ENTRA_TOKEN_STUDIO_AZ_APP
+              (ENTRA_TOKEN_STUDIO_AZ_IDENTITY=user serves the app's cached user sign-in instead)
+  git-credential [--app <app> [--user] [--account <username>]] <get|store|erase>
+              Git credential helper: Azure DevOps tokens for the app matched by the
+              git credential rules in Settings, or the given --app
+
+<app> is a saved app's id, name or client id. Token commands print the raw access
+token unless --json is given.
+
+Exit codes: 0 ok, 1 failure, 2 usage, 3 app not found, 4 sidecar unavailable,
+            5 token request failed, 6 timed out, 7 malformed token";
+
+/// A CLI failure: message for stderr plus the process exit code
+struct CliError {
+    code: i32,
+    message: String,
+}
+
+impl CliError {
+    fn usage(message: impl Into<String>) -> Self {
+        Self {
+            code: EXIT_USAGE,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<CommandError> for CliError {
+    fn from(error: CommandError) -> Self {
+        let code = match &error {
+            CommandError::SidecarUnavailable { .. }
+            | CommandError::NodeMissing { .. }
+            | CommandError::NodeIncompatible { .. }
+            | CommandError::SidecarRestarted { .. }
+            | CommandError::Transport { .. } => EXIT_SIDECAR_UNAVAILABLE,
+            CommandError::Rpc { .. } | CommandError::Network { .. } => EXIT_TOKEN_FAILED,
+            CommandError::Timeout { .. } | CommandError::Cancelled { .. } => EXIT_TIMED_OUT,
+            CommandError::Validation { .. } => EXIT_USAGE,
+            CommandError::Io { .. }
+            | CommandError::Keyring { .. }
+            | CommandError::Random { .. }
+            | CommandError::Contract { .. } => EXIT_FAILURE,
+        };
+        Self {
+            code,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Parsed `--flag value` options and positional arguments
+struct Args {
+    positionals: Vec<String>,
+    values: HashMap<&'static str, Vec<String>>,
+    switches: Vec<&'static str>,
+}
+
+impl Args {
+    /// Parse `args`, accepting only the given value options and switches.
+    fn parse(
+        args: &[String],
+        value_options: &[&'static str],
+        switch_options: &[&'static str],
+    ) -> Result<Self, CliError> {
+        let mut parsed = Args {
+            positionals: Vec::new(),
+            values: HashMap::new(),
+            switches: Vec::new(),
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                parsed.positionals.push(arg.clone());
+                continue;
+            };
+            let (name, inline_value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (flag, None),
+            };
+            if let Some(name) = switch_options.iter().find(|option| **option == name) {
+                parsed.switches.push(name);
+            } else if let Some(name) = value_options.iter().find(|option| **option == name) {
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| CliError::usage(format!("--{} requires a value", name)))?,
+                };
+                parsed.values.entry(name).or_default().push(value);
+            } else {
+                return Err(CliError::usage(format!("Unknown option --{}", name)));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).and_then(|values| values.last()).map(String::as_str)
+    }
+
+    fn all(&self, name: &str) -> &[String] {
+        self.values.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.switches.contains(&name)
+    }
+
+    fn timeout_ms(&self) -> Result<Option<u64>, CliError> {
+        self.value("timeout")
+            .map(|value| {
+                value
+                    .parse::<u64>()
+                    .map(|seconds| seconds * 1000)
+                    .map_err(|_| CliError::usage("--timeout must be a whole number of seconds"))
+            })
+            .transpose()
+    }
+}
+
+/// The process arguments, program name first (if the OS passed one at all).
+///
+/// Unlike `std::env::args`, doesn't panic on arguments that aren't valid UTF-8; they are
+/// converted lossily, so e.g. a path containing one fails as "not found" instead.
+pub(crate) fn process_args() -> Vec<String> {
+    std::env::args_os().map(|arg| arg.to_string_lossy().into_owned()).collect()
+}
+
+/// Run a CLI command if the process was started with one.
+///
+/// Returns `None` when the arguments don't name a command (a normal GUI launch, or a
+/// deep link URL passed by the OS), in which case the caller should start the GUI.
+pub fn run() -> Option<i32> {
+    let argv = process_args();
+    let (arg0, args) = argv.split_first()?;
+    let invoked_as_az = std::path::Path::new(arg0)
+        .file_stem()
+        .is_some_and(|stem| stem.eq_ignore_ascii_case("az"));
+    if invoked_as_az {
+        return Some(run_az(args));
+    }
+
+    let first = args.first()?;
+    if !COMMANDS.contains(&first.as_str()) {
+        return None;
+    }
+
+    #[cfg(windows)]
+    attach_parent_console();
+
+    let code = match dispatch(args) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            eprintln!("error: {}", error.message);
+            if error.code == EXIT_USAGE {
+                eprintln!("\nRun `entra-token-studio help` for usage.");
+            }
+            error.code
+        }
+    };
+    Some(code)
+}
+
+/// Run as the `az` shim, reporting errors the way the Azure CLI does.
+fn run_az(args: &[String]) -> i32 {
+    #[cfg(windows)]
+    attach_parent_console();
+    hide_shim_from_path();
+
+    match tauri::async_runtime::block_on(az(args)) {
+        Ok(()) => EXIT_OK,
+        Err(error) => {
+            eprintln!("ERROR: {}", error.message);
+            // The Azure CLI uses 2 for argument errors and 1 for everything else.
+            if error.code == EXIT_USAGE {
+                EXIT_USAGE
+            } else {
+                EXIT_FAILURE
+            }
+        }
+    }
+}
+
+fn dispatch(args: &[String]) -> Result<(), CliError> {
+    let rest = &args[1..];
+    match args[0].as_str() {
+        "az" => tauri::async_runtime::block_on(az(rest)),
+        "git-credential" => tauri::async_runtime::block_on(git_credential(rest)),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        "--version" | "-V" => {
+            println!("entra-token-studio {}", env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+        "decode" => decode(rest),
+        "apps" => match rest.first().map(String::as_str) {
+            Some("list") => list_apps(&rest[1..]),
+            _ => Err(CliError::usage("Expected `apps list`")),
+        },
+        "token" => match rest.first().map(String::as_str) {
+            Some("app") => tauri::async_runtime::block_on(app_token(&rest[1..])),
+            Some("user") => tauri::async_runtime::block_on(user_token(&rest[1..])),
+            _ => Err(CliError::usage("Expected `token app` or `token user`")),
+        },
+        other => Err(CliError::usage(format!("Unknown command '{}'", other))),
+    }
+}
+
+fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+fn load_apps() -> Result<Vec<SavedApp>, CliError> {
+    let Some(dir) = data_dir() else {
+        return Ok(Vec::new());
+    };
+    Ok(saved_apps::load(&dir)?)
+}
+
+fn find_app(query: &str) -> Result<SavedApp, CliError> {
+    let apps = load_apps()?;
+    if apps.is_empty() {
+        return Err(CliError {
+            code: EXIT_APP_NOT_FOUND,
+            message: "No saved apps found. Add an app in the desktop app first.".to_string(),
+        });
+    }
+    saved_apps::find(&apps, query).cloned().map_err(|message| CliError {
+        code: EXIT_APP_NOT_FOUND,
+        message,
+    })
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| CliError {
+        code: EXIT_FAILURE,
+        message: format!("Failed to serialize output: {}", e),
+    })?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn print_token(token: &TokenResponse, json: bool) -> Result<(), CliError> {
+    if json {
+        print_json(token)
+    } else {
+        println!("{}", token.access_token);
+        Ok(())
+    }
+}
+
+fn decode(args: &[String]) -> Result<(), CliError> {
+    let parsed = Args::parse(args, &[], &[])?;
+    let token = match parsed.positionals.as_slice() {
+        [token] if token == "-" => {
+            let mut input = String::new();
+            std::io::stdin().read_to_string(&mut input).map_err(|e| CliError {
+                code: EXIT_FAILURE,
+                message: format!("Failed to read token from stdin: {}", e),
+            })?;
+            input
+        }
+        [token] => token.clone(),
+        _ => return Err(CliError::usage("Expected exactly one token (or `-` for stdin)")),
+    };
+
+    let decoded = crate::jwt::decode(&token).map_err(|e| CliError {
+        code: EXIT_INVALID_TOKEN,
+        message: e.to_string(),
+    })?;
+    print_json(&decoded)
+}
+
+fn list_apps(args: &[String]) -> Result<(), CliError> {
+    let parsed = Args::parse(args, &[], &["json"])?;
+    let apps = load_apps()?;
+    if parsed.switch("json") {
+        return print_json(&apps);
+    }
+    if apps.is_empty() {
+        eprintln!("No saved apps. Add an app in the desktop app first.");
+        return Ok(());
+    }
+    for app in &apps {
+        println!(
+            "{}\t{}\tclient {}\ttenant {}\t{}",
+            app.id, app.name, app.client_id, app.tenant_id, app.key_vault.credential_type
+        );
+    }
+    Ok(())
+}
+
+fn init_sidecar() {
+    sidecar::init_sidecar_env_for(APP_IDENTIFIER, data_dir());
+}
+
+async fn app_token(args: &[String]) -> Result<(), CliError> {
+    let parsed = Args::parse(
+        args,
+        &[
+            "app",
+            "client-id",
+            "tenant-id",
+            "client-secret-env",
+            "cert",
+            "key",
+            "algorithm",
+            "authority-host",
+            "scope",
+            "resource",
+            "timeout",
+        ],
+        &["json"],
+    )?;
+    let algorithm = parsed
+        .value("algorithm")
+        .map(str::parse::<AssertionAlgorithm>)
+        .transpose()
+        .map_err(CliError::usage)?;
+
+    let mut scopes = parsed.all("scope").to_vec();
+    if let Some(resource) = parsed.value("resource") {
+        // App tokens always use `/.default`.
+        scopes.push(resource_scope(resource));
+    }
+    if scopes.is_empty() {
+        return Err(CliError::usage("--scope or --resource is required"));
+    }
+
+    let Some(query) = parsed.value("app") else {
+        return local_app_token(&parsed, scopes, algorithm).await;
+    };
+    let app = find_app(query)?;
+
+    init_sidecar();
+    let authority_host = parsed.value("authority-host").map(str::to_string);
+    // Only the native backend talks to the token endpoint directly.
+    let backend = if authority_host.is_some() || algorithm.is_some() {
+        Some(BackendKind::Native)
+    } else {
+        app.backend
+    };
+    let config = TokenAppConfig {
+        client_id: app.client_id,
+        tenant_id: app.tenant_id,
+        key_vault: app.key_vault,
+        credential: app.credential,
+        backend,
+        authority_host: authority_host.or(app.authority_host),
+        assertion_algorithm: algorithm.or(app.assertion_algorithm),
+    };
+    let token = crate::acquire_app_token(config, scopes, parsed.timeout_ms()?, None).await?;
+    print_token(&token, parsed.switch("json"))
+}
+
+/// `token app` with an ad-hoc client id and a secret or certificate from the local machine.
+async fn local_app_token(
+    parsed: &Args,
+    scopes: Vec<String>,
+    algorithm: Option<AssertionAlgorithm>,
+) -> Result<(), CliError> {
+    let (Some(client_id), Some(tenant_id)) = (parsed.value("client-id"), parsed.value("tenant-id")) else {
+        return Err(CliError::usage("Use either --app or both --client-id and --tenant-id"));
+    };
+
+    let credential = match (parsed.value("client-secret-env"), parsed.value("cert")) {
+        (Some(var), None) => {
+            // Taken from the environment so the secret never appears in shell history or `ps`.
+            let secret = std::env::var(var)
+                .map_err(|_| CliError::usage(format!("Environment variable {} is not set", var)))?;
+            ClientCredential::Secret(secret)
+        }
+        (None, Some(cert_path)) => {
+            let cert_pem = read_file(cert_path)?;
+            let key_pem = match parsed.value("key") {
+                Some(key_path) => read_file(key_path)?,
+                None => cert_pem.clone(),
+            };
+            ClientCredential::Certificate(CertificateCredential::from_pem(
+                &cert_pem,
+                &key_pem,
+                algorithm.unwrap_or_default(),
+            )?)
+        }
+        _ => return Err(CliError::usage("Use either --client-secret-env or --cert with --client-id")),
+    };
+
+    let timeout_ms = parsed.timeout_ms()?.unwrap_or(60_000);
+    let token = tokio::time::timeout(
+        std::time::Duration::from_millis(timeout_ms),
+        client_credentials::acquire_token(parsed.value("authority-host"), tenant_id, client_id, &scopes, &credential),
+    )
+    .await
+    .map_err(|_| CommandError::timeout("acquire_app_token", timeout_ms))??;
+    print_token(&token, parsed.switch("json"))
+}
+
+fn read_file(path: &str) -> Result<String, CliError> {
+    std::fs::read_to_string(path).map_err(|e| CommandError::io(std::path::Path::new(path), e).into())
+}
+
+async fn user_token(args: &[String]) -> Result<(), CliError> {
+    let parsed = Args::parse(
+        args,
+        &["app", "client-id", "tenant-id", "scope", "scopes", "account", "prompt", "timeout"],
+        &["json", "silent", "device-code"],
+    )?;
+
+    let (client_id, tenant_id, backend) = match (parsed.value("app"), parsed.value("client-id"), parsed.value("tenant-id")) {
+        (Some(query), None, None) => {
+            let app = find_app(query)?;
+            (app.client_id, app.tenant_id, app.backend)
+        }
+        (None, Some(client_id), Some(tenant_id)) => (client_id.to_string(), tenant_id.to_string(), None),
+        _ => return Err(CliError::usage("Use either --app or both --client-id and --tenant-id")),
+    };
+
+    let mut scopes: Vec<String> = parsed.all("scope").to_vec();
+    for value in parsed.all("scopes") {
+        scopes.extend(value.split_whitespace().map(str::to_string));
+    }
+    if scopes.is_empty() {
+        return Err(CliError::usage("--scopes or --scope is required"));
+    }
+
+    init_sidecar();
+    if parsed.switch("device-code") {
+        if parsed.switch("silent") || parsed.value("account").is_some() || parsed.value("prompt").is_some() {
+            return Err(CliError::usage("--device-code can't be combined with --silent, --account or --prompt"));
+        }
+        let kind = backend.unwrap_or_else(backend::default_kind);
+        let token = device_code_token(kind, client_id, tenant_id, scopes, parsed.timeout_ms()?).await?;
+        return print_token(&token, parsed.switch("json"));
+    }
+
+    let account_home_account_id = match parsed.value("account") {
+        Some(username) => {
+            let accounts = crate::get_user_accounts(client_id.clone(), tenant_id.clone(), backend, None).await?;
+            let account = accounts
+                .into_iter()
+                .find(|account| account.username.eq_ignore_ascii_case(username))
+                .ok_or_else(|| CliError {
+                    code: EXIT_TOKEN_FAILED,
+                    message: format!("No cached account '{}' for this app; sign in without --account first", username),
+                })?;
+            Some(account.home_account_id)
+        }
+        None => None,
+    };
+
+    let token = crate::acquire_user_token(
+        client_id,
+        tenant_id,
+        scopes,
+        parsed.value("prompt").map(str::to_string),
+        account_home_account_id,
+        parsed.switch("silent").then_some(true),
+        backend,
+        parsed.timeout_ms()?,
+        None,
+    )
+    .await?;
+    print_token(&token, parsed.switch("json"))
+}
+
+/// Sign in with a device code: print Entra's instructions to stderr and wait for the user.
+/// Without `--timeout` this waits until the code expires.
+async fn device_code_token(
+    kind: BackendKind,
+    client_id: String,
+    tenant_id: String,
+    scopes: Vec<String>,
+    timeout_ms: Option<u64>,
+) -> Result<TokenResponse, CliError> {
+    // Subscribe first so the outcome can't be published before we listen.
+    let mut progress = device_code::subscribe();
+    let info = device_code::start(kind, client_id, tenant_id, scopes, CallOptions::default()).await?;
+    eprintln!("{}", info.message);
+
+    let outcome = async {
+        loop {
+            let update = match progress.recv().await {
+                Ok(update) if update.flow_id == info.flow_id => update,
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Err(CommandError::transport("Device code progress channel closed").into()),
+            };
+            let message = update.error.map(|error| error.message).unwrap_or_default();
+            return match update.status {
+                DeviceCodeStatus::Pending => continue,
+                DeviceCodeStatus::Completed => update.result.ok_or_else(|| CliError {
+                    code: EXIT_FAILURE,
+                    message: "Device code sign-in completed without a token".to_string(),
+                }),
+                DeviceCodeStatus::Cancelled | DeviceCodeStatus::Expired => Err(CliError {
+                    code: EXIT_TIMED_OUT,
+                    message,
+                }),
+                DeviceCodeStatus::Failed => Err(CliError {
+                    code: EXIT_TOKEN_FAILED,
+                    message,
+                }),
+            };
+        }
+    };
+
+    let timeout_ms = timeout_ms.unwrap_or(info.expires_in * 1000);
+    match tokio::time::timeout(Duration::from_millis(timeout_ms), outcome).await {
+        Ok(result) => result,
+        Err(_) => {
+            let _ = device_code::cancel(&info.flow_id).await;
+            Err(CommandError::timeout("start_device_code", timeout_ms).into())
+        }
+    }
+}
+
+/// Silent user token for a saved app's cached account (`username`, or the first one).
+/// Unattended callers must never open a browser.
+async fn cached_user_token(
+    app: &SavedApp,
+    tenant_id: &str,
+    username: Option<&str>,
+    scopes: Vec<String>,
+) -> Result<TokenResponse, CliError> {
+    let accounts = crate::get_user_accounts(app.client_id.clone(), tenant_id.to_string(), app.backend, None).await?;
+    let account = match username {
+        Some(username) => accounts.into_iter().find(|a| a.username.eq_ignore_ascii_case(username)),
+        None => accounts.into_iter().next(),
+    }
+    .ok_or_else(|| CliError {
+        code: EXIT_TOKEN_FAILED,
+        message: format!(
+            "No cached user sign-in for '{}'. Sign in from Entra Token Studio first.",
+            app.name
+        ),
+    })?;
+    Ok(crate::acquire_user_token(
+        app.client_id.clone(),
+        tenant_id.to_string(),
+        scopes,
+        None,
+        Some(account.home_account_id),
+        Some(true),
+        app.backend,
+        None,
+        None,
+    )
+    .await?)
+}
+
+/// Git credential helper. Answers nothing (so git tries its next helper) for hosts other
+/// than Azure DevOps and for URLs no rule covers; failures go to stderr for the same
+/// reason rather than failing the git command.
+async fn git_credential(args: &[String]) -> Result<(), CliError> {
+    let parsed = Args::parse(args, &["app", "account"], &["user"])?;
+    let operation = match parsed.positionals.as_slice() {
+        [operation] => operation.as_str(),
+        _ => return Err(CliError::usage("Expected exactly one of get, store or erase")),
+    };
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input).map_err(|e| CliError {
+        code: EXIT_FAILURE,
+        message: format!("Failed to read the credential request: {}", e),
+    })?;
+    match operation {
+        "get" => {}
+        // Tokens are re-acquired on every `get`; there is nothing to store or forget.
+        "store" | "erase" => return Ok(()),
+        other => return Err(CliError::usage(format!("Unknown git credential operation '{}'", other))),
+    }
+
+    let request = git_credential::parse_request(&input);
+    if request.get("protocol").map(String::as_str) != Some("https") {
+        return Ok(());
+    }
+    let Some(host) = request.get("host") else {
+        return Ok(());
+    };
+    // Even with --app, an Azure DevOps token is never offered to another host.
+    if !git_credential::is_azure_devops_host(host) {
+        return Ok(());
+    }
+
+    let (app_query, identity, account) = match parsed.value("app") {
+        Some(app) => (
+            app.to_string(),
+            if parsed.switch("user") { GitIdentity::User } else { GitIdentity::App },
+            parsed.value("account").map(str::to_string),
+        ),
+        None => {
+            let rules = match data_dir() {
+                Some(dir) => git_credential::load_rules(&dir)?,
+                None => Vec::new(),
+            };
+            match git_credential::find_rule(&rules, host, request.get("path").map(String::as_str)) {
+                Some(rule) => (rule.app.clone(), rule.identity, rule.account.clone()),
+                None => return Ok(()),
+            }
+        }
+    };
+
+    let outcome = async {
+        let app = find_app(&app_query)?;
+        init_sidecar();
+        let scopes = vec![git_credential::AZURE_DEVOPS_SCOPE.to_string()];
+        let (username, token) = match identity {
+            GitIdentity::App => {
+                let token = crate::acquire_app_token(app.token_config(), scopes, None, None).await?;
+                (app.client_id.clone(), token)
+            }
+            GitIdentity::User => {
+                let token = cached_user_token(&app, &app.tenant_id, account.as_deref(), scopes).await?;
+                let username = token.account.as_ref().map(|a| a.username.clone()).unwrap_or_default();
+                (username, token)
+            }
+        };
+        Ok::<_, CliError>((username, token))
+    };
+    match outcome.await {
+        Ok((username, token)) => {
+            // Azure DevOps ignores the username of a bearer-token password, but git needs one.
+            let username = request
+                .get("username")
+                .cloned()
+                .filter(|u| !u.is_empty())
+                .unwrap_or(if username.is_empty() { "entra-token-studio".to_string() } else { username });
+            print!("{}", git_credential::format_response(&username, &token.access_token, token_expiry(&token)));
+        }
+        Err(error) => eprintln!("entra-token-studio: no token for {}: {}", host, error.message),
+    }
+    Ok(())
+}
+
+/// Drop the directories where `az` is this executable from `PATH`, so the Key Vault
+/// client and the sidecar, which run `az` themselves, reach the real Azure CLI instead of
+/// the shim. Runs before any thread or child process exists.
+fn hide_shim_from_path() {
+    let Some(path) = std::env::var_os("PATH") else {
+        return;
+    };
+    let Ok(current_exe) = std::env::current_exe().and_then(std::fs::canonicalize) else {
+        return;
+    };
+    let names: &[&str] = if cfg!(windows) { &["az.exe", "az.cmd", "az"] } else { &["az"] };
+    let is_shim_dir = |dir: &PathBuf| {
+        names
+            .iter()
+            .filter_map(|name| std::fs::canonicalize(dir.join(name)).ok())
+            .any(|candidate| candidate == current_exe)
+    };
+    let dirs: Vec<PathBuf> = std::env::split_paths(&path).filter(|dir| !is_shim_dir(dir)).collect();
+    if let Ok(filtered) = std::env::join_paths(dirs) {
+        std::env::set_var("PATH", filtered);
+    }
+}
+
+/// `az account get-access-token` output, field for field
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AzAccessToken {
+    access_token: String,
+    /// Local time, `YYYY-MM-DD HH:MM:SS.ffffff`
+    expires_on: String,
+    /// Unix seconds; newer Azure SDKs prefer this over `expiresOn`
+    #[serde(rename = "expires_on")]
+    expires_on_unix: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subscription: Option<String>,
+    tenant: String,
+    token_type: String,
+}
+
+impl AzAccessToken {
+    fn new(token: TokenResponse, expires_on_unix: i64, tenant: String, subscription: Option<String>) -> Self {
+        let expires_on = chrono::DateTime::from_timestamp(expires_on_unix, 0)
+            .map(|utc| utc.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S%.6f").to_string())
+            .unwrap_or_default();
+        Self {
+            access_token: token.access_token,
+            expires_on,
+            expires_on_unix,
+            subscription,
+            tenant,
+            token_type: token.token_type,
+        }
+    }
+}
+
+/// Scopes for `--scope` or `--resource`, the way the Azure CLI derives them
+fn az_scopes(parsed: &Args) -> Result<Vec<String>, CliError> {
+    let mut scopes: Vec<String> = parsed
+        .all("scope")
+        .iter()
+        .flat_map(|value| value.split_whitespace().map(str::to_string))
+        .collect();
+    if let Some(resource) = parsed.value("resource") {
+        if !scopes.is_empty() {
+            return Err(CliError::usage("usage error: --resource and --scope are mutually exclusive"));
+        }
+        scopes.push(resource_scope(resource));
+    }
+    if scopes.is_empty() {
+        // Same default as the Azure CLI
+        scopes.push("https://management.core.windows.net//.default".to_string());
+    }
+    Ok(scopes)
+}
+
+/// `az account get-access-token` options the shim understands
+const AZ_VALUE_OPTIONS: &[&str] = &["resource", "scope", "tenant", "subscription", "output", "query", "resource-type"];
+
+/// Azure CLI short flags the credential classes and common scripts use
+fn expand_az_short_flags(args: &[String]) -> Vec<String> {
+    args.iter()
+        .map(|arg| match arg.as_str() {
+            "-o" => "--output".to_string(),
+            "-t" => "--tenant".to_string(),
+            "-s" => "--subscription".to_string(),
+            _ => arg.clone(),
+        })
+        .collect()
+}
+
+fn token_expiry(token: &TokenResponse) -> Option<i64> {
+    crate::jwt::decode(&token.access_token)
+        .ok()
+        .and_then(|decoded| decoded.claims.exp)
+        .or_else(|| {
+            token
+                .expires_on
+                .as_deref()
+                .and_then(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+                .map(|expires_on| expires_on.timestamp())
+        })
+}
+
+async fn az(args: &[String]) -> Result<(), CliError> {
+    let args = expand_az_short_flags(args);
+    match args.iter().take(2).map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["account", "get-access-token"] => {}
+        _ => {
+            return Err(CliError::usage(format!(
+                "'{}' is not supported by the Entra Token Studio az shim; only `az account get-access-token` is",
+                args.join(" ")
+            )))
+        }
+    }
+    let parsed = Args::parse(&args[2..], AZ_VALUE_OPTIONS, &["only-show-errors"])?;
+    if parsed.value("resource-type").is_some() {
+        return Err(CliError::usage("--resource-type is not supported; pass --resource instead"));
+    }
+
+    let scopes = az_scopes(&parsed)?;
+    let app_query = std::env::var(AZ_APP_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| CliError::usage(format!("Set {} to the saved app to serve tokens for", AZ_APP_ENV)))?;
+    let app = find_app(&app_query)?;
+    let tenant_id = parsed.value("tenant").map(str::to_string).unwrap_or_else(|| app.tenant_id.clone());
+
+    init_sidecar();
+    let identity = std::env::var(AZ_IDENTITY_ENV).unwrap_or_default();
+    let token = match identity.trim().to_ascii_lowercase().as_str() {
+        "" | "app" => {
+            let mut config = app.token_config();
+            config.tenant_id = tenant_id.clone();
+            crate::acquire_app_token(config, scopes, None, None).await?
+        }
+        "user" => {
+            let account = std::env::var(AZ_ACCOUNT_ENV).ok();
+            cached_user_token(&app, &tenant_id, account.as_deref(), scopes).await?
+        }
+        other => {
+            return Err(CliError::usage(format!("{} must be 'app' or 'user', not '{}'", AZ_IDENTITY_ENV, other)))
+        }
+    };
+
+    let expires_on_unix = token_expiry(&token).ok_or_else(|| CliError {
+        code: EXIT_FAILURE,
+        message: "The token's expiry could not be determined".to_string(),
+    })?;
+    // Like the Azure CLI, no subscription is reported for an explicit tenant.
+    let subscription = parsed
+        .value("tenant")
+        .is_none()
+        .then(|| std::env::var(AZ_SUBSCRIPTION_ENV).ok())
+        .flatten();
+    let output = AzAccessToken::new(token, expires_on_unix, tenant_id, subscription);
+
+    match parsed.value("output").unwrap_or("json") {
+        "json" | "jsonc" => match parsed.value("query") {
+            None => print_json(&output),
+            Some(query) => print_json(&az_query(&output, query)?),
+        },
+        "tsv" => {
+            let query = parsed
+                .value("query")
+                .ok_or_else(|| CliError::usage("--output tsv needs --query <field>, e.g. --query accessToken"))?;
+            match az_query(&output, query)? {
+                serde_json::Value::String(value) => println!("{}", value),
+                value => println!("{}", value),
+            }
+            Ok(())
+        }
+        other => Err(CliError::usage(format!("--output {} is not supported (use json or tsv)", other))),
+    }
+}
+
+/// `--query` for a single top-level field, the only JMESPath scripts use with this command
+fn az_query(output: &AzAccessToken, query: &str) -> Result<serde_json::Value, CliError> {
+    let value = serde_json::to_value(output).map_err(|e| CliError {
+        code: EXIT_FAILURE,
+        message: format!("Failed to serialize output: {}", e),
+    })?;
+    value
+        .get(query.trim())
+        .cloned()
+        .ok_or_else(|| CliError::usage(format!("--query '{}' is not supported; use a field name such as accessToken", query)))
+}
+
+/// Release builds use the Windows GUI subsystem, which has no console; reattach to the
+/// terminal that started us so output is visible.
+#[cfg(windows)]
+pub(crate) fn attach_parent_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: AttachConsole has no preconditions; failure (e.g. already attached) is harmless.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    const TENANT_ID: &str = "72f988bf-86f1-41af-91ab-2d7cd011db47";
+    const EXPIRES_ON: i64 = 1_700_003_600;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn az_args(args: &[&str]) -> Args {
+        let expanded = expand_az_short_flags(&strings(args));
+        Args::parse(&expanded, AZ_VALUE_OPTIONS, &["only-show-errors"]).unwrap_or_else(|e| panic!("{}", e.message))
+    }
+
+    fn az_output(subscription: Option<&str>) -> AzAccessToken {
+        let token = TokenResponse {
+            access_token: "eyJ0eXAiOiJKV1QifQ.e30.sig".to_string(),
+            token_type: "Bearer".to_string(),
+            expires_on: None,
+            scopes: Vec::new(),
+            account: None,
+        };
+        AzAccessToken::new(token, EXPIRES_ON, TENANT_ID.to_string(), subscription.map(str::to_string))
+    }
+
+    fn parse(args: &[&str]) -> Result<Args, CliError> {
+        Args::parse(&strings(args), &["app", "scope", "timeout"], &["json", "silent"])
+    }
+
+    fn exit_code(error: CommandError) -> i32 {
+        CliError::from(error).code
+    }
+
+    #[test]
+    fn parses_values_switches_and_positionals() {
+        let parsed = parse(&["list", "--app", "Build SP", "--json", "--timeout=30", "extra"]).ok().unwrap();
+        assert_eq!(parsed.positionals, ["list", "extra"]);
+        assert_eq!(parsed.value("app"), Some("Build SP"));
+        assert_eq!(parsed.value("timeout"), Some("30"));
+        assert!(parsed.switch("json"));
+        assert!(!parsed.switch("silent"));
+        assert_eq!(parsed.value("scope"), None);
+        assert!(parsed.all("scope").is_empty());
+    }
+
+    #[test]
+    fn repeated_values_are_all_kept() {
+        let parsed = parse(&["--scope", "api://a/read", "--scope=api://a/write", "--app", "one", "--app", "two"]).ok().unwrap();
+        assert_eq!(parsed.all("scope"), ["api://a/read", "api://a/write"]);
+        // Single-valued options take the last occurrence.
+        assert_eq!(parsed.value("app"), Some("two"));
+    }
+
+    #[test]
+    fn a_value_option_takes_the_next_argument_as_is() {
+        let parsed = parse(&["--app", "--json"]).ok().unwrap();
+        assert_eq!(parsed.value("app"), Some("--json"));
+        assert!(!parsed.switch("json"));
+    }
+
+    #[test]
+    fn missing_values_and_unknown_flags_are_usage_errors() {
+        let missing = parse(&["--json", "--app"]).err().unwrap();
+        assert_eq!(missing.code, EXIT_USAGE);
+        assert_eq!(missing.message, "--app requires a value");
+
+        let unknown = parse(&["--app", "x", "--verbose"]).err().unwrap();
+        assert_eq!(unknown.code, EXIT_USAGE);
+        assert_eq!(unknown.message, "Unknown option --verbose");
+
+        // Options are per command: one another command accepts is still unknown here.
+        let elsewhere = Args::parse(&strings(&["--app", "x"]), &[], &["json"]).err().unwrap();
+        assert_eq!(elsewhere.code, EXIT_USAGE);
+    }
+
+    #[test]
+    fn unknown_commands_are_usage_errors() {
+        assert_eq!(dispatch(&strings(&["frobnicate"])).err().map(|e| e.code), Some(EXIT_USAGE));
+        assert_eq!(dispatch(&strings(&["token", "robot"])).err().map(|e| e.code), Some(EXIT_USAGE));
+        assert_eq!(dispatch(&strings(&["apps"])).err().map(|e| e.code), Some(EXIT_USAGE));
+    }
+
+    #[test]
+    fn timeout_is_whole_seconds() {
+        assert_eq!(parse(&["--timeout", "30"]).ok().unwrap().timeout_ms().ok(), Some(Some(30_000)));
+        assert_eq!(parse(&[]).ok().unwrap().timeout_ms().ok(), Some(None));
+        for invalid in ["1.5", "-1", "soon", ""] {
+            let parsed = parse(&["--timeout", invalid]).ok().unwrap();
+            assert_eq!(parsed.timeout_ms().err().map(|e| e.code), Some(EXIT_USAGE), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn command_errors_map_to_documented_exit_codes() {
+        assert_eq!(exit_code(CommandError::unavailable(crate::error::SIDECAR_NOT_STARTED, "x")), EXIT_SIDECAR_UNAVAILABLE);
+        assert_eq!(exit_code(CommandError::NodeMissing { message: "x".into() }), EXIT_SIDECAR_UNAVAILABLE);
+        assert_eq!(exit_code(CommandError::NodeIncompatible { message: "x".into() }), EXIT_SIDECAR_UNAVAILABLE);
+        assert_eq!(exit_code(CommandError::SidecarRestarted { message: "x".into() }), EXIT_SIDECAR_UNAVAILABLE);
+        assert_eq!(exit_code(CommandError::transport("x")), EXIT_SIDECAR_UNAVAILABLE);
+
+        let rpc = CommandError::Rpc {
+            code: -32000,
+            message: "AADSTS700016".into(),
+            data: None,
+        };
+        assert_eq!(exit_code(rpc), EXIT_TOKEN_FAILED);
+        assert_eq!(exit_code(CommandError::network("https://login.microsoftonline.com", "x")), EXIT_TOKEN_FAILED);
+
+        assert_eq!(exit_code(CommandError::timeout("acquire_app_token", 1000)), EXIT_TIMED_OUT);
+        assert_eq!(exit_code(CommandError::cancelled()), EXIT_TIMED_OUT);
+        assert_eq!(exit_code(CommandError::validation("scopes", "x")), EXIT_USAGE);
+
+        assert_eq!(exit_code(CommandError::keyring("x")), EXIT_FAILURE);
+        assert_eq!(
+            exit_code(CommandError::Contract {
+                method: "acquire_app_token".into(),
+                message: "x".into(),
+            }),
+            EXIT_FAILURE
+        );
+        assert_eq!(
+            exit_code(CommandError::io(std::path::Path::new("apps.json"), std::io::ErrorKind::NotFound.into())),
+            EXIT_FAILURE
+        );
+
+        let error = CliError::from(CommandError::validation("scopes", "At least one scope is required"));
+        assert_eq!(error.message, "At least one scope is required");
+    }
+
+    #[test]
+    fn az_output_matches_the_azure_cli_shape() {
+        let value = serde_json::to_value(az_output(Some("sub-1"))).unwrap();
+        let Value::Object(fields) = &value else {
+            panic!("expected an object, got {}", value);
+        };
+        let mut keys: Vec<&str> = fields.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["accessToken", "expiresOn", "expires_on", "subscription", "tenant", "tokenType"]);
+        assert_eq!(value["accessToken"], "eyJ0eXAiOiJKV1QifQ.e30.sig");
+        assert_eq!(value["expires_on"], EXPIRES_ON);
+        assert_eq!(value["subscription"], "sub-1");
+        assert_eq!(value["tenant"], TENANT_ID);
+        assert_eq!(value["tokenType"], "Bearer");
+
+        // Local time with microseconds, as `AzureCliCredential` parses it
+        let expires_on = value["expiresOn"].as_str().unwrap();
+        let parsed = chrono::NaiveDateTime::parse_from_str(expires_on, "%Y-%m-%d %H:%M:%S%.6f").unwrap();
+        let local = chrono::DateTime::from_timestamp(EXPIRES_ON, 0).unwrap().with_timezone(&chrono::Local);
+        assert_eq!(parsed, local.naive_local());
+        assert!(expires_on.ends_with(".000000"));
+    }
+
+    #[test]
+    fn az_output_omits_a_missing_subscription() {
+        let value = serde_json::to_value(az_output(None)).unwrap();
+        assert!(value.get("subscription").is_none());
+    }
+
+    #[test]
+    fn az_short_flags_are_expanded() {
+        assert_eq!(
+            expand_az_short_flags(&strings(&["account", "get-access-token", "-t", "tid", "-o", "json", "-s", "sub"])),
+            strings(&["account", "get-access-token", "--tenant", "tid", "--output", "json", "--subscription", "sub"])
+        );
+        // Only whole arguments are flags; values are left alone.
+        assert_eq!(
+            expand_az_short_flags(&strings(&["--query", "-o", "--resource", "-t"])),
+            strings(&["--query", "--output", "--resource", "--tenant"])
+        );
+
+        let parsed = az_args(&["-t", TENANT_ID, "-o", "tsv", "-s", "sub-1"]);
+        assert_eq!(parsed.value("tenant"), Some(TENANT_ID));
+        assert_eq!(parsed.value("output"), Some("tsv"));
+        assert_eq!(parsed.value("subscription"), Some("sub-1"));
+    }
+
+    #[test]
+    fn az_resource_becomes_its_default_scope_unchanged() {
+        assert_eq!(
+            az_scopes(&az_args(&["--resource", "https://management.azure.com/"])).ok(),
+            Some(strings(&["https://management.azure.com//.default"]))
+        );
+        assert_eq!(
+            az_scopes(&az_args(&["--resource", " https://vault.azure.net "])).ok(),
+            Some(strings(&["https://vault.azure.net/.default"]))
+        );
+        assert_eq!(
+            az_scopes(&az_args(&["--scope", "api://a/read api://a/write"])).ok(),
+            Some(strings(&["api://a/read", "api://a/write"]))
+        );
+        assert_eq!(
+            az_scopes(&az_args(&[])).ok(),
+            Some(strings(&["https://management.core.windows.net//.default"]))
+        );
+
+        let both = az_scopes(&az_args(&["--resource", "https://vault.azure.net", "--scope", "api://a/read"]));
+        assert_eq!(both.err().map(|e| e.code), Some(EXIT_USAGE));
+    }
+
+    #[test]
+    fn az_query_selects_one_field() {
+        let output = az_output(Some("sub-1"));
+        assert_eq!(az_query(&output, "accessToken").ok(), Some(json!("eyJ0eXAiOiJKV1QifQ.e30.sig")));
+        assert_eq!(az_query(&output, " expires_on ").ok(), Some(json!(EXPIRES_ON)));
+        assert_eq!(az_query(&output, "tenant").ok(), Some(json!(TENANT_ID)));
+
+        assert_eq!(az_query(&output, "accessToken.length").err().map(|e| e.code), Some(EXIT_USAGE));
+        assert_eq!(az_query(&output, "missing").err().map(|e| e.code), Some(EXIT_USAGE));
+    }
+}
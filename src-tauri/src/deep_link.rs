@@ -0,0 +1,410 @@
+//! `entra-token-studio://` deep links.
+//!
+//! Every link is parsed into a [`DeepLinkAction`] against a fixed schema. Unknown actions,
+//! unknown or repeated parameters and malformed values are rejected and never reach the UI.
+//! Accepted actions are queued and announced as `deep-link` events; the UI takes each one
+//! exactly once with `resolve_deep_link`. Actions that issue tokens are marked
+//! `requiresConfirmation`, and the UI only runs them after the user confirms, so a web page
+//! that opens a link can't mint tokens by itself. Sign-in redirects never arrive here: they
+//! go to the loopback listener in [`crate::auth_code`].
+//!
+//! | Link | Action |
+//! |------|--------|
+//! | `issue?favorite=<id>` | Issue a token from a favorite |
+//! | `decode?token=<jwt>` | Show a token's claims |
+//! | `app/import?clientId=<guid>&tenantId=<id>[&keyVaultUri=..][&secretName=..\|&certName=..]` | Open the app form pre-filled |
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{Emitter, Manager};
+
+use crate::client_credentials::random_uuid;
+
+/// Scheme of the app's deep links
+pub const SCHEME: &str = "entra-token-studio";
+
+/// Links waiting for the UI; the oldest are dropped beyond this
+const MAX_PENDING: usize = 10;
+const MAX_TOKEN_LEN: usize = 16 * 1024;
+/// Key Vault DNS suffixes of the public and sovereign clouds
+const VAULT_SUFFIXES: [&str; 3] = [".vault.azure.net", ".vault.azure.cn", ".vault.usgovcloudapi.net"];
+
+/// What a deep link asks the app to do
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum DeepLinkAction {
+    /// Issue a token from the favorite with this id
+    Issue { favorite: String },
+    /// Show the claims of a token; nothing is issued
+    Decode { token: String },
+    /// Open the add-app form with these fields filled in; nothing is saved until the user does
+    #[serde(rename = "importApp", rename_all = "camelCase")]
+    ImportApp {
+        client_id: String,
+        tenant_id: String,
+        key_vault_uri: Option<String>,
+        secret_name: Option<String>,
+        cert_name: Option<String>,
+    },
+}
+
+impl DeepLinkAction {
+    /// Whether the UI must ask the user before acting on it
+    pub fn requires_confirmation(&self) -> bool {
+        matches!(self, Self::Issue { .. })
+    }
+}
+
+/// Payload of the `deep-link` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDeepLink {
+    pub id: String,
+    #[serde(flatten)]
+    pub action: DeepLinkAction,
+    pub requires_confirmation: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref PENDING: Mutex<VecDeque<PendingDeepLink>> = Mutex::new(VecDeque::new());
+}
+
+/// Query parameters of a link, consumed as the schema is checked
+struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn new(url: &url::Url) -> Result<Self, String> {
+        let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        for (i, (name, _)) in pairs.iter().enumerate() {
+            if pairs[..i].iter().any(|(other, _)| other == name) {
+                return Err(format!("parameter '{}' is repeated", name));
+            }
+        }
+        Ok(Self(pairs))
+    }
+
+    fn optional(&mut self, name: &str) -> Option<String> {
+        let index = self.0.iter().position(|(key, _)| key == name)?;
+        Some(self.0.remove(index).1.trim().to_string()).filter(|value| !value.is_empty())
+    }
+
+    fn required(&mut self, name: &str) -> Result<String, String> {
+        self.optional(name).ok_or_else(|| format!("parameter '{}' is required", name))
+    }
+
+    /// Reject whatever the schema didn't ask for.
+    fn finish(self) -> Result<(), String> {
+        match self.0.first() {
+            Some((name, _)) => Err(format!("unknown parameter '{}'", name)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn is_guid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, len)| {
+            group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit())
+        })
+}
+
+/// A tenant GUID or verified domain (`contoso.onmicrosoft.com`)
+fn is_tenant(value: &str) -> bool {
+    is_guid(value)
+        || (value.len() <= 253
+            && value.contains('.')
+            && value
+                .split('.')
+                .all(|label| !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')))
+}
+
+/// Key Vault object names: 1-127 letters, digits and dashes
+fn is_vault_object_name(value: &str) -> bool {
+    (1..=127).contains(&value.len()) && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn check(valid: bool, name: &str, value: &str) -> Result<(), String> {
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a valid {}", value, name))
+    }
+}
+
+/// Parse a deep link against the schema of its action.
+pub fn parse(url: &url::Url) -> Result<DeepLinkAction, String> {
+    let action = format!("{}{}", url.host_str().unwrap_or_default(), url.path());
+    let mut params = Params::new(url)?;
+    let parsed = match action.trim_end_matches('/') {
+        "issue" => {
+            let favorite = params.required("favorite")?;
+            check(
+                favorite.len() <= 128 && favorite.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+                "favorite id",
+                &favorite,
+            )?;
+            DeepLinkAction::Issue { favorite }
+        }
+        "decode" => {
+            let token = params.required("token")?;
+            if token.len() > MAX_TOKEN_LEN || crate::jwt::decode(&token).is_err() {
+                return Err("'token' is not a JWT".to_string());
+            }
+            DeepLinkAction::Decode { token }
+        }
+        "app/import" => {
+            let client_id = params.required("clientId")?;
+            check(is_guid(&client_id), "client id", &client_id)?;
+            let tenant_id = params.required("tenantId")?;
+            check(is_tenant(&tenant_id), "tenant id", &tenant_id)?;
+            let key_vault_uri = params.optional("keyVaultUri");
+            if let Some(uri) = &key_vault_uri {
+                let host = url::Url::parse(uri)
+                    .ok()
+                    .filter(|parsed| parsed.scheme() == "https")
+                    .and_then(|parsed| parsed.host_str().map(str::to_ascii_lowercase));
+                let valid = host.is_some_and(|host| VAULT_SUFFIXES.iter().any(|suffix| host.ends_with(suffix)));
+                check(valid, "Key Vault URI", uri)?;
+            }
+            let secret_name = params.optional("secretName");
+            let cert_name = params.optional("certName");
+            for name in secret_name.iter().chain(&cert_name) {
+                check(is_vault_object_name(name), "Key Vault object name", name)?;
+            }
+            if secret_name.is_some() && cert_name.is_some() {
+                return Err("only one of 'secretName' and 'certName' may be given".to_string());
+            }
+            DeepLinkAction::ImportApp {
+                client_id,
+                tenant_id,
+                key_vault_uri,
+                secret_name,
+                cert_name,
+            }
+        }
+        "" => return Err("the link has no action".to_string()),
+        other => return Err(format!("unknown action '{}'", other)),
+    };
+    params.finish()?;
+    Ok(parsed)
+}
+
+/// Handle a link the OS opened the app with.
+pub fn handle(app: &tauri::AppHandle, url: &url::Url) {
+    if url.scheme() != SCHEME {
+        return;
+    }
+    let mut sanitized = url.clone();
+    sanitized.set_query(None);
+    sanitized.set_fragment(None);
+
+    let action = match parse(url) {
+        Ok(action) => action,
+        Err(reason) => {
+            log::warn!("Rejected deep link {}: {}", sanitized, reason);
+            return;
+        }
+    };
+    let id = match random_uuid() {
+        Ok(id) => id,
+        Err(e) => {
+            log::warn!("Dropped deep link {}: {}", sanitized, e);
+            return;
+        }
+    };
+    log::info!("Deep link received: {}", sanitized);
+    let pending = PendingDeepLink {
+        id,
+        requires_confirmation: action.requires_confirmation(),
+        action,
+    };
+    {
+        let mut queue = PENDING.lock().unwrap();
+        if queue.len() >= MAX_PENDING {
+            queue.pop_front();
+        }
+        queue.push_back(pending.clone());
+    }
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_focus();
+    }
+    if let Err(e) = app.emit("deep-link", &pending) {
+        log::warn!("Failed to emit deep-link event: {}", e);
+    }
+}
+
+/// Links the UI hasn't taken yet, e.g. the one the app was launched with
+pub fn pending() -> Vec<PendingDeepLink> {
+    PENDING.lock().unwrap().iter().cloned().collect()
+}
+
+/// Take a queued link: its action if `accept`, `None` if declined or already taken.
+pub fn resolve(id: &str, accept: bool) -> Option<DeepLinkAction> {
+    let mut queue = PENDING.lock().unwrap();
+    let index = queue.iter().position(|pending| pending.id == id)?;
+    let pending = queue.remove(index)?;
+    accept.then_some(pending.action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT_ID: &str = "7a1c2b3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d";
+    const TENANT_ID: &str = "72f988bf-86f1-41af-91ab-2d7cd011db47";
+    /// `{"alg":"none"}.{"aud":"api://test"}` with an empty signature
+    const TOKEN: &str = "eyJhbGciOiJub25lIn0.eyJhdWQiOiJhcGk6Ly90ZXN0In0.";
+
+    fn parse_link(link: &str) -> Result<DeepLinkAction, String> {
+        parse(&url::Url::parse(link).unwrap())
+    }
+
+    fn rejected(link: &str) -> String {
+        match parse_link(link) {
+            Ok(action) => panic!("{} was accepted as {:?}", link, action),
+            Err(reason) => reason,
+        }
+    }
+
+    #[test]
+    fn accepts_each_action() {
+        let issue = parse_link("entra-token-studio://issue?favorite=fav_01-a").unwrap();
+        assert!(matches!(&issue, DeepLinkAction::Issue { favorite } if favorite == "fav_01-a"));
+        assert!(issue.requires_confirmation());
+
+        let decode = parse_link(&format!("entra-token-studio://decode?token={}", TOKEN)).unwrap();
+        assert!(matches!(&decode, DeepLinkAction::Decode { token } if token == TOKEN));
+        assert!(!decode.requires_confirmation());
+
+        let import = parse_link(&format!(
+            "entra-token-studio://app/import?clientId={}&tenantId={}&keyVaultUri=https://contoso-kv.vault.azure.net/&certName=build-agent",
+            CLIENT_ID, TENANT_ID
+        ))
+        .unwrap();
+        let DeepLinkAction::ImportApp {
+            client_id,
+            tenant_id,
+            key_vault_uri,
+            secret_name,
+            cert_name,
+        } = import
+        else {
+            panic!("expected importApp");
+        };
+        assert_eq!(client_id, CLIENT_ID);
+        assert_eq!(tenant_id, TENANT_ID);
+        assert_eq!(key_vault_uri.as_deref(), Some("https://contoso-kv.vault.azure.net/"));
+        assert_eq!(secret_name, None);
+        assert_eq!(cert_name.as_deref(), Some("build-agent"));
+
+        let by_domain = parse_link(&format!(
+            "entra-token-studio://app/import/?clientId={}&tenantId=contoso.onmicrosoft.com",
+            CLIENT_ID
+        ));
+        assert!(by_domain.is_ok(), "{:?}", by_domain);
+    }
+
+    #[test]
+    fn rejects_unknown_actions() {
+        assert_eq!(rejected("entra-token-studio://mint?favorite=abc"), "unknown action 'mint'");
+        assert_eq!(rejected("entra-token-studio://app/delete?clientId=x"), "unknown action 'app/delete'");
+        assert_eq!(rejected("entra-token-studio:///?favorite=abc"), "the link has no action");
+    }
+
+    #[test]
+    fn rejects_extra_and_repeated_parameters() {
+        assert_eq!(
+            rejected("entra-token-studio://issue?favorite=abc&scope=Directory.ReadWrite.All"),
+            "unknown parameter 'scope'"
+        );
+        assert_eq!(
+            rejected("entra-token-studio://issue?favorite=abc&favorite=def"),
+            "parameter 'favorite' is repeated"
+        );
+        assert_eq!(
+            rejected(&format!(
+                "entra-token-studio://app/import?clientId={}&tenantId={}&clientSecret=hunter2",
+                CLIENT_ID, TENANT_ID
+            )),
+            "unknown parameter 'clientSecret'"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_parameters() {
+        assert_eq!(rejected("entra-token-studio://issue"), "parameter 'favorite' is required");
+        assert_eq!(rejected("entra-token-studio://issue?favorite=%20"), "parameter 'favorite' is required");
+        assert_eq!(
+            rejected(&format!("entra-token-studio://app/import?clientId={}", CLIENT_ID)),
+            "parameter 'tenantId' is required"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        for favorite in ["../settings", "a%20b", "%3Cscript%3E"] {
+            rejected(&format!("entra-token-studio://issue?favorite={}", favorite));
+        }
+        for client_id in [
+            "not-a-guid",
+            "7a1c2b3d4e5f4a6b8c7d9e0f1a2b3c4d",
+            "7a1c2b3d-4e5f-4a6b-8c7d-9e0f1a2b3c4",
+            "za1c2b3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d",
+        ] {
+            let reason = rejected(&format!(
+                "entra-token-studio://app/import?clientId={}&tenantId={}",
+                client_id, TENANT_ID
+            ));
+            assert_eq!(reason, format!("'{}' is not a valid client id", client_id));
+        }
+        for tenant_id in ["contoso", "contoso..com", "contoso.com%2Fevil"] {
+            let reason = rejected(&format!(
+                "entra-token-studio://app/import?clientId={}&tenantId={}",
+                CLIENT_ID, tenant_id
+            ));
+            assert!(reason.ends_with("is not a valid tenant id"), "{}", reason);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_vault_uris_and_names() {
+        for uri in [
+            "http://contoso-kv.vault.azure.net/",
+            "https://contoso-kv.vault.azure.net.evil.example/",
+            "https://evil.example/?contoso-kv.vault.azure.net",
+            "not a uri",
+        ] {
+            let link = format!(
+                "entra-token-studio://app/import?clientId={}&tenantId={}&keyVaultUri={}",
+                CLIENT_ID,
+                TENANT_ID,
+                url::form_urlencoded::byte_serialize(uri.as_bytes()).collect::<String>()
+            );
+            assert_eq!(rejected(&link), format!("'{}' is not a valid Key Vault URI", uri));
+        }
+
+        let base = format!("entra-token-studio://app/import?clientId={}&tenantId={}", CLIENT_ID, TENANT_ID);
+        assert_eq!(
+            rejected(&format!("{}&secretName=my_secret", base)),
+            "'my_secret' is not a valid Key Vault object name"
+        );
+        assert_eq!(
+            rejected(&format!("{}&secretName=a&certName=b", base)),
+            "only one of 'secretName' and 'certName' may be given"
+        );
+    }
+
+    #[test]
+    fn rejects_tokens_that_are_not_jwts() {
+        assert_eq!(rejected("entra-token-studio://decode?token=abc"), "'token' is not a JWT");
+        let oversized = format!("{}{}", TOKEN, "A".repeat(MAX_TOKEN_LEN));
+        assert_eq!(
+            rejected(&format!("entra-token-studio://decode?token={}", oversized)),
+            "'token' is not a JWT"
+        );
+    }
+}
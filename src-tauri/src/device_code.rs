@@ -0,0 +1,189 @@
+//! Device code sign-in for user tokens.
+//!
+//! [`start`] returns the user code as soon as Entra issues it. The backend keeps polling
+//! afterwards and reports `pending` heartbeats and the final outcome as
+//! [`DeviceCodeProgress`] updates, which are emitted to the UI as `device-code-progress`
+//! events and can be awaited in-process with [`subscribe`] (the CLI does this).
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use tokio::sync::broadcast;
+
+use crate::backend::{self, BackendKind};
+use crate::client_credentials::random_uuid;
+use crate::error::CommandError;
+use crate::models::TokenResponse;
+use crate::sidecar::{self, CallOptions};
+
+/// Progress updates buffered per subscriber before the oldest are dropped
+const PROGRESS_CHANNEL_CAPACITY: usize = 64;
+
+lazy_static::lazy_static! {
+    /// Flows that haven't finished yet, with the backend polling them
+    static ref FLOWS: Mutex<HashMap<String, BackendKind>> = Mutex::new(HashMap::new());
+    static ref PROGRESS: broadcast::Sender<DeviceCodeProgress> = broadcast::channel(PROGRESS_CHANNEL_CAPACITY).0;
+}
+
+/// Arguments of a device code sign-in
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCodeRequest {
+    pub flow_id: String,
+    pub client_id: String,
+    pub tenant_id: String,
+    pub scopes: Vec<String>,
+}
+
+/// What the user needs to complete sign-in on another device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCodeInfo {
+    pub flow_id: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Entra's ready-made instructions ("To sign in, use a web browser to open ...")
+    pub message: String,
+    /// Seconds until the user code expires
+    pub expires_in: u64,
+    /// Seconds between token endpoint polls
+    pub interval: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceCodeStatus {
+    /// Still waiting for the user
+    Pending,
+    Completed,
+    Failed,
+    Cancelled,
+    /// The user code expired before sign-in finished
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodeError {
+    pub message: String,
+    /// Entra/MSAL error code, if any
+    pub code: Option<String>,
+}
+
+/// Payload of the `device-code-progress` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceCodeProgress {
+    pub flow_id: String,
+    pub status: DeviceCodeStatus,
+    /// Seconds until the user code expires (`pending` only)
+    pub expires_in: Option<u64>,
+    /// The token (`completed` only)
+    pub result: Option<TokenResponse>,
+    /// Why the flow ended (`failed`, `cancelled` and `expired`)
+    pub error: Option<DeviceCodeError>,
+}
+
+impl DeviceCodeProgress {
+    pub fn completed(flow_id: &str, result: TokenResponse) -> Self {
+        Self {
+            flow_id: flow_id.to_string(),
+            status: DeviceCodeStatus::Completed,
+            expires_in: None,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    fn ended(flow_id: &str, status: DeviceCodeStatus, message: impl Into<String>) -> Self {
+        Self {
+            flow_id: flow_id.to_string(),
+            status,
+            expires_in: None,
+            result: None,
+            error: Some(DeviceCodeError {
+                message: message.into(),
+                code: None,
+            }),
+        }
+    }
+}
+
+/// Start a device code sign-in with the given backend.
+pub async fn start(
+    kind: BackendKind,
+    client_id: String,
+    tenant_id: String,
+    scopes: Vec<String>,
+    options: CallOptions,
+) -> Result<DeviceCodeInfo, CommandError> {
+    let request = DeviceCodeRequest {
+        flow_id: random_uuid()?,
+        client_id,
+        tenant_id,
+        scopes,
+    };
+    // Registered before starting so no progress update can arrive for an unknown flow.
+    FLOWS.lock().unwrap().insert(request.flow_id.clone(), kind);
+    let result = backend::get(Some(kind)).start_device_code(&request, options).await;
+    if result.is_err() {
+        FLOWS.lock().unwrap().remove(&request.flow_id);
+    }
+    result
+}
+
+/// Stop polling a flow. Returns `false` if it already finished.
+pub async fn cancel(flow_id: &str) -> Result<bool, CommandError> {
+    let Some(kind) = FLOWS.lock().unwrap().get(flow_id).copied() else {
+        return Ok(false);
+    };
+    if !backend::get(Some(kind)).cancel_device_code(flow_id).await? {
+        return Ok(false);
+    }
+    // The backend only notices on its next poll; report the cancellation right away; its
+    // own `cancelled` update is dropped by `publish` once the flow is gone.
+    publish(DeviceCodeProgress::ended(flow_id, DeviceCodeStatus::Cancelled, "Sign-in was cancelled"));
+    Ok(true)
+}
+
+/// Report progress of a running flow. Updates for finished or unknown flows are dropped.
+pub fn publish(progress: DeviceCodeProgress) {
+    {
+        let mut flows = FLOWS.lock().unwrap();
+        if !flows.contains_key(&progress.flow_id) {
+            log::debug!("Dropping device code update for finished flow {}", progress.flow_id);
+            return;
+        }
+        if progress.status != DeviceCodeStatus::Pending {
+            flows.remove(&progress.flow_id);
+        }
+    }
+
+    if let Some(handle) = sidecar::app_handle() {
+        if let Err(e) = handle.emit("device-code-progress", &progress) {
+            log::warn!("Failed to emit device-code-progress event: {}", e);
+        }
+    }
+    // No receivers is fine: nobody in-process is waiting.
+    let _ = PROGRESS.send(progress);
+}
+
+/// Receive progress updates published from now on.
+pub fn subscribe() -> broadcast::Receiver<DeviceCodeProgress> {
+    PROGRESS.subscribe()
+}
+
+/// Fail the flows the sidecar was polling when it exited.
+pub fn fail_sidecar_flows(message: &str) {
+    let orphaned: Vec<String> = FLOWS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, kind)| **kind != BackendKind::Demo)
+        .map(|(flow_id, _)| flow_id.clone())
+        .collect();
+    for flow_id in orphaned {
+        publish(DeviceCodeProgress::ended(&flow_id, DeviceCodeStatus::Failed, message));
+    }
+}
@@ -0,0 +1,167 @@
+use serde::Serialize;
+
+/// Error returned by Tauri commands.
+///
+/// Serialized as a tagged object (`{ "kind": "timeout", "message": ..., ... }`) so the
+/// frontend can branch on `kind`/`code` instead of matching on message text.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CommandError {
+    /// The sidecar is not running (script missing, spawn failure, restarting or given up)
+    SidecarUnavailable { code: &'static str, message: String },
+    /// No Node.js runtime was found to run the sidecar
+    NodeMissing { message: String },
+    /// Node.js was found, but no runtime (or the configured override) is a supported version
+    NodeIncompatible { message: String },
+    /// Writing the request or reading the response failed
+    Transport { message: String },
+    /// An HTTP request made by the app itself failed
+    Network { url: String, message: String },
+    /// Reading or writing a local file failed
+    Io { path: String, message: String },
+    /// The OS credential store (Keychain, Credential Manager, Secret Service) failed
+    Keyring { message: String },
+    /// The OS random number generator failed
+    Random { message: String },
+    /// The sidecar process exited while the request was in flight
+    SidecarRestarted { message: String },
+    /// No response arrived before the deadline
+    #[serde(rename_all = "camelCase")]
+    Timeout {
+        method: String,
+        timeout_ms: u64,
+        message: String,
+    },
+    /// The request was cancelled via `cancel_request`
+    Cancelled { message: String },
+    /// The sidecar handler returned a JSON-RPC error; `data` carries any structured
+    /// detail it attached (e.g. a Key Vault error with a suggested action)
+    Rpc {
+        code: i32,
+        message: String,
+        data: Option<serde_json::Value>,
+    },
+    /// The sidecar's result did not match the schema the command expects
+    Contract { method: String, message: String },
+    /// Command arguments were rejected before reaching the sidecar
+    Validation { field: String, message: String },
+}
+
+/// `SidecarUnavailable` codes
+pub const SIDECAR_SCRIPT_NOT_FOUND: &str = "SIDECAR_SCRIPT_NOT_FOUND";
+pub const SIDECAR_SPAWN_FAILED: &str = "SIDECAR_SPAWN_FAILED";
+pub const SIDECAR_NOT_STARTED: &str = "SIDECAR_NOT_STARTED";
+pub const SIDECAR_RESTARTING: &str = "SIDECAR_RESTARTING";
+pub const SIDECAR_CRASH_LOOP: &str = "SIDECAR_CRASH_LOOP";
+pub const SIDECAR_PROTOCOL_MISMATCH: &str = "SIDECAR_PROTOCOL_MISMATCH";
+
+impl CommandError {
+    pub fn unavailable(code: &'static str, message: impl Into<String>) -> Self {
+        Self::SidecarUnavailable {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn transport(message: impl Into<String>) -> Self {
+        Self::Transport {
+            message: message.into(),
+        }
+    }
+
+    pub fn network(url: &str, message: impl Into<String>) -> Self {
+        Self::Network {
+            url: url.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn io(path: &std::path::Path, error: std::io::Error) -> Self {
+        Self::Io {
+            path: path.display().to_string(),
+            message: format!("{}: {}", path.display(), error),
+        }
+    }
+
+    pub fn keyring(message: impl Into<String>) -> Self {
+        Self::Keyring {
+            message: message.into(),
+        }
+    }
+
+    pub fn random(error: getrandom::Error) -> Self {
+        Self::Random {
+            message: format!("Failed to generate random bytes: {}", error),
+        }
+    }
+
+    pub fn timeout(method: &str, timeout_ms: u64) -> Self {
+        Self::Timeout {
+            method: method.to_string(),
+            timeout_ms,
+            message: format!(
+                "Sidecar request '{}' timed out after {} seconds",
+                method,
+                timeout_ms / 1000
+            ),
+        }
+    }
+
+    pub fn cancelled() -> Self {
+        Self::Cancelled {
+            message: "Request was cancelled".to_string(),
+        }
+    }
+
+    pub fn validation(field: &str, message: impl Into<String>) -> Self {
+        Self::Validation {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Stable machine-readable code for startup failures, as reported by `check_sidecar_health`
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            Self::SidecarUnavailable { code, .. } => Some(code),
+            Self::NodeMissing { .. } => Some("NODE_NOT_FOUND"),
+            Self::NodeIncompatible { .. } => Some("NODE_INCOMPATIBLE"),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::SidecarUnavailable { message, .. }
+            | Self::NodeMissing { message }
+            | Self::NodeIncompatible { message }
+            | Self::Transport { message }
+            | Self::Network { message, .. }
+            | Self::Io { message, .. }
+            | Self::Keyring { message }
+            | Self::Random { message }
+            | Self::SidecarRestarted { message }
+            | Self::Timeout { message, .. }
+            | Self::Cancelled { message }
+            | Self::Rpc { message, .. }
+            | Self::Contract { message, .. }
+            | Self::Validation { message, .. } => message,
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Reject an empty (or whitespace-only) required string argument.
+pub fn require_non_empty(field: &str, value: &str) -> Result<(), CommandError> {
+    if value.trim().is_empty() {
+        return Err(CommandError::validation(field, format!("{} is required", field)));
+    }
+    Ok(())
+}
@@ -0,0 +1,242 @@
+//! Git credential helper for Azure DevOps repos.
+//!
+//! Implements git's credential helper protocol (`get`, `store`, `erase` with `key=value`
+//! lines on stdin and stdout). `get` only answers for Azure DevOps hosts, so a helper
+//! configured for every URL never hands an Entra token to another server. The repo URL is
+//! matched against [`GitCredentialRule`]s, and the matching saved app's app token or cached
+//! user sign-in for the Azure DevOps resource is returned as the password. Tokens come from
+//! the same paths (and encrypted MSAL cache) as the rest of the app, so no PATs are
+//! involved. `store` and `erase` are accepted and ignored: nothing is stored on git's behalf.
+//!
+//! Rules live in `git-credential-rules.json` next to the saved apps and are edited from
+//! Settings. Paths are only matched if git sends them (`credential.useHttpPath`).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommandError;
+use crate::store;
+
+/// Azure DevOps resource id; the same scope works for every organization
+pub const AZURE_DEVOPS_SCOPE: &str = "499b84ac-1321-427f-aa17-267ca6975798/.default";
+const RULES_FILE: &str = "git-credential-rules.json";
+
+/// Which identity a rule signs in with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitIdentity {
+    /// App token for the saved app's service principal
+    #[default]
+    App,
+    /// The saved app's cached user sign-in
+    User,
+}
+
+/// Maps repo URLs to a saved app and identity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCredentialRule {
+    /// `host/path` pattern, `*` matching anything (e.g. `dev.azure.com/contoso/*`)
+    pub pattern: String,
+    /// Saved app id, name or client id
+    pub app: String,
+    #[serde(default)]
+    pub identity: GitIdentity,
+    /// Cached account username for user tokens (the first cached account if unset)
+    pub account: Option<String>,
+}
+
+fn rules_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(RULES_FILE)
+}
+
+/// Replace the rules with `rules`.
+pub fn save_rules(data_dir: &Path, rules: &[GitCredentialRule]) -> Result<(), CommandError> {
+    for rule in rules {
+        if rule.pattern.trim().is_empty() || rule.app.trim().is_empty() {
+            return Err(CommandError::validation("rules", "Every rule needs a pattern and an app"));
+        }
+    }
+    store::write_json_atomic(&rules_path(data_dir), rules, "git credential rules")
+}
+
+/// Load the rules; none if the file doesn't exist yet.
+pub fn load_rules(data_dir: &Path) -> Result<Vec<GitCredentialRule>, CommandError> {
+    store::read_json_or_default(&rules_path(data_dir), "git credential rules")
+}
+
+/// Whether `host` (as git sends it, possibly with a port) is Azure DevOps:
+/// `dev.azure.com` or a legacy `<org>.visualstudio.com` host.
+pub fn is_azure_devops_host(host: &str) -> bool {
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    }
+    .to_ascii_lowercase();
+    host == "dev.azure.com"
+        || host
+            .strip_suffix(".visualstudio.com")
+            .is_some_and(|org| !org.is_empty() && !org.contains('.'))
+}
+
+/// Case-insensitive glob match where `*` matches any run of characters, `/` included.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// First rule matching `host` and `path` (as sent by git, without a leading slash).
+pub fn find_rule<'a>(rules: &'a [GitCredentialRule], host: &str, path: Option<&str>) -> Option<&'a GitCredentialRule> {
+    let path = path.unwrap_or_default().trim_start_matches('/').trim_end_matches(".git");
+    let target = if path.is_empty() { host.to_string() } else { format!("{}/{}", host, path) };
+    rules.iter().find(|rule| {
+        let pattern = rule.pattern.trim().trim_start_matches("https://").trim_end_matches('/');
+        glob_match(pattern, &target) || glob_match(pattern, host)
+    })
+}
+
+/// Parse a credential description (`key=value` lines up to a blank line).
+/// Repeated keys such as `wwwauth[]` keep their last value.
+pub fn parse_request(input: &str) -> BTreeMap<String, String> {
+    input
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+/// Format the `get` answer git reads back.
+pub fn format_response(username: &str, password: &str, expires_at: Option<i64>) -> String {
+    let mut response = format!("username={}\npassword={}\n", username, password);
+    // Understood by git 2.41+; older versions ignore unknown keys.
+    if let Some(expires_at) = expires_at {
+        response.push_str(&format!("password_expiry_utc={}\n", expires_at));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, app: &str) -> GitCredentialRule {
+        GitCredentialRule {
+            pattern: pattern.to_string(),
+            app: app.to_string(),
+            identity: GitIdentity::App,
+            account: None,
+        }
+    }
+
+    fn matched<'a>(rules: &'a [GitCredentialRule], host: &str, path: Option<&str>) -> Option<&'a str> {
+        find_rule(rules, host, path).map(|rule| rule.app.as_str())
+    }
+
+    #[test]
+    fn star_matches_across_slashes() {
+        assert!(glob_match("dev.azure.com/contoso/*", "dev.azure.com/contoso/project/_git/repo"));
+        assert!(glob_match("dev.azure.com/*/_git/repo", "dev.azure.com/contoso/project/_git/repo"));
+        assert!(glob_match("*", "dev.azure.com/contoso"));
+        assert!(glob_match("dev.azure.com/contoso/*", "dev.azure.com/contoso/"));
+        assert!(!glob_match("dev.azure.com/contoso/*", "dev.azure.com/fabrikam/project"));
+        assert!(!glob_match("dev.azure.com/contoso", "dev.azure.com/contoso/project"));
+    }
+
+    #[test]
+    fn matching_ignores_case() {
+        assert!(glob_match("DEV.AZURE.COM/Contoso/*", "dev.azure.com/contoso/Project"));
+        let rules = [rule("dev.azure.com/contoso/*", "contoso")];
+        assert_eq!(matched(&rules, "Dev.Azure.com", Some("Contoso/Project/_git/Repo")), Some("contoso"));
+    }
+
+    #[test]
+    fn trailing_dot_git_is_ignored() {
+        let rules = [rule("dev.azure.com/contoso/project/_git/repo", "repo")];
+        assert_eq!(matched(&rules, "dev.azure.com", Some("contoso/project/_git/repo.git")), Some("repo"));
+        assert_eq!(matched(&rules, "dev.azure.com", Some("/contoso/project/_git/repo")), Some("repo"));
+        assert_eq!(matched(&rules, "dev.azure.com", Some("contoso/project/_git/other.git")), None);
+    }
+
+    #[test]
+    fn host_only_request_matches_a_path_rule_by_host() {
+        // Without `credential.useHttpPath` git sends no path; a pattern's host part still applies.
+        let rules = [rule("https://dev.azure.com/", "any-org"), rule("contoso.visualstudio.com", "legacy")];
+        assert_eq!(matched(&rules, "dev.azure.com", None), Some("any-org"));
+        assert_eq!(matched(&rules, "contoso.visualstudio.com", Some("")), Some("legacy"));
+        assert_eq!(matched(&[rule("dev.azure.com/contoso/*", "contoso")], "dev.azure.com", None), None);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = [
+            rule("dev.azure.com/contoso/secret/*", "secret"),
+            rule("dev.azure.com/contoso/*", "contoso"),
+            rule("dev.azure.com/*", "fallback"),
+        ];
+        assert_eq!(matched(&rules, "dev.azure.com", Some("contoso/secret/_git/repo")), Some("secret"));
+        assert_eq!(matched(&rules, "dev.azure.com", Some("contoso/web/_git/repo")), Some("contoso"));
+        assert_eq!(matched(&rules, "dev.azure.com", Some("fabrikam/web/_git/repo")), Some("fallback"));
+        assert_eq!(matched(&rules, "github.com", Some("contoso/repo")), None);
+    }
+
+    #[test]
+    fn parses_a_credential_description() {
+        let request = parse_request("protocol=https\nhost=dev.azure.com\npath=contoso/p/_git/r\nwwwauth[]=Basic\nwwwauth[]=Bearer\n\nignored=1\n");
+        assert_eq!(request.get("protocol").map(String::as_str), Some("https"));
+        assert_eq!(request.get("host").map(String::as_str), Some("dev.azure.com"));
+        assert_eq!(request.get("path").map(String::as_str), Some("contoso/p/_git/r"));
+        assert_eq!(request.get("wwwauth[]").map(String::as_str), Some("Bearer"));
+        assert!(!request.contains_key("ignored"));
+
+        // Values may contain `=`.
+        let request = parse_request("password=a=b\n");
+        assert_eq!(request.get("password").map(String::as_str), Some("a=b"));
+    }
+
+    #[test]
+    fn only_azure_devops_hosts_get_tokens() {
+        for host in ["dev.azure.com", "DEV.AZURE.COM", "dev.azure.com:443", "contoso.visualstudio.com"] {
+            assert!(is_azure_devops_host(host), "{} was rejected", host);
+        }
+        for host in [
+            "github.com",
+            "gitlab.example.com",
+            "visualstudio.com",
+            "evil.contoso.visualstudio.com",
+            "dev.azure.com.attacker.example",
+            "contoso.visualstudio.com.attacker.example",
+        ] {
+            assert!(!is_azure_devops_host(host), "{} was accepted", host);
+        }
+    }
+
+    #[test]
+    fn formats_the_get_response() {
+        assert_eq!(format_response("sp", "tok", None), "username=sp\npassword=tok\n");
+        assert_eq!(
+            format_response("sp", "tok", Some(1_700_000_000)),
+            "username=sp\npassword=tok\npassword_expiry_utc=1700000000\n"
+        );
+    }
+}
@@ -0,0 +1,461 @@
+//! JWT/JWS decoding and standard claim extraction.
+//!
+//! Decoding does not verify signatures; it only parses the compact serialization so the
+//! UI can inspect headers and claims of access and ID tokens.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::error::CommandError;
+
+/// Why a token could not be decoded
+#[derive(Debug, Clone)]
+pub enum JwtError {
+    Empty,
+    /// Wrong number of `.`-separated segments (5 means an encrypted JWE)
+    SegmentCount(usize),
+    /// A segment is not valid base64url
+    Base64 { segment: &'static str, message: String },
+    /// The header or payload is not a JSON object
+    Json { segment: &'static str, message: String },
+    /// A registered claim has the wrong type
+    Claim { name: &'static str, expected: &'static str },
+}
+
+impl std::fmt::Display for JwtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("Token is empty"),
+            Self::SegmentCount(5) => f.write_str(
+                "Token is an encrypted JWE (5 segments); only signed JWS tokens can be decoded",
+            ),
+            Self::SegmentCount(count) => write!(
+                f,
+                "Token must have 3 segments (header.payload.signature), found {}",
+                count
+            ),
+            Self::Base64 { segment, message } => {
+                write!(f, "Token {} is not valid base64url: {}", segment, message)
+            }
+            Self::Json { segment, message } => {
+                write!(f, "Token {} is not a JSON object: {}", segment, message)
+            }
+            Self::Claim { name, expected } => write!(f, "Claim '{}' must be {}", name, expected),
+        }
+    }
+}
+
+impl std::error::Error for JwtError {}
+
+impl From<JwtError> for CommandError {
+    fn from(error: JwtError) -> Self {
+        let field = match &error {
+            JwtError::Empty | JwtError::SegmentCount(_) => "token",
+            JwtError::Base64 { segment, .. } | JwtError::Json { segment, .. } => segment,
+            JwtError::Claim { .. } => "payload",
+        };
+        CommandError::validation(field, error.to_string())
+    }
+}
+
+/// Entra token format, from the `ver` claim or (for tokens without it) the issuer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenVersion {
+    V1,
+    V2,
+    Unknown,
+}
+
+/// JOSE header fields relevant to Entra tokens
+#[derive(Debug, Clone, Serialize)]
+pub struct JwtHeader {
+    pub alg: Option<String>,
+    pub typ: Option<String>,
+    pub kid: Option<String>,
+    pub x5t: Option<String>,
+}
+
+/// Registered and Entra-specific claims, normalized
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StandardClaims {
+    /// `aud` may be a string or an array; always returned as a list
+    pub aud: Vec<String>,
+    pub iss: Option<String>,
+    pub tid: Option<String>,
+    pub oid: Option<String>,
+    /// Delegated scopes; `scp` may be a space-separated string (Entra) or an array (RFC 9068)
+    pub scp: Vec<String>,
+    pub roles: Vec<String>,
+    /// Seconds since the Unix epoch
+    pub exp: Option<i64>,
+    pub nbf: Option<i64>,
+    pub iat: Option<i64>,
+    /// Client id in v1 tokens
+    pub appid: Option<String>,
+    /// Client id in v2 tokens
+    pub azp: Option<String>,
+    pub ver: Option<String>,
+}
+
+/// A decoded (not verified) JWS
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedToken {
+    pub header: JwtHeader,
+    pub claims: StandardClaims,
+    pub version: TokenVersion,
+    /// Full header and payload, including non-standard claims
+    pub raw_header: Map<String, Value>,
+    pub raw_payload: Map<String, Value>,
+    /// Signature segment as it appeared in the token (base64url)
+    pub signature: String,
+    pub signature_len: usize,
+    /// Input that was signed (`header.payload`), for signature verification
+    #[serde(skip)]
+    pub signing_input: String,
+    #[serde(skip)]
+    pub signature_bytes: Vec<u8>,
+}
+
+/// Decode a compact JWS. A leading `Bearer ` prefix and surrounding whitespace are ignored.
+pub fn decode(token: &str) -> Result<DecodedToken, JwtError> {
+    let token = token.trim();
+    let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+    if token.is_empty() {
+        return Err(JwtError::Empty);
+    }
+
+    let segments: Vec<&str> = token.split('.').collect();
+    if segments.len() != 3 {
+        return Err(JwtError::SegmentCount(segments.len()));
+    }
+
+    let raw_header = decode_json_segment(segments[0], "header")?;
+    let raw_payload = decode_json_segment(segments[1], "payload")?;
+    let signature_bytes = decode_segment(segments[2], "signature")?;
+
+    let header = JwtHeader {
+        alg: string_field(&raw_header, "alg"),
+        typ: string_field(&raw_header, "typ"),
+        kid: string_field(&raw_header, "kid"),
+        x5t: string_field(&raw_header, "x5t"),
+    };
+    let claims = standard_claims(&raw_payload)?;
+    let version = token_version(&claims);
+
+    Ok(DecodedToken {
+        header,
+        claims,
+        version,
+        raw_header,
+        raw_payload,
+        signature: segments[2].to_string(),
+        signature_len: signature_bytes.len(),
+        signing_input: format!("{}.{}", segments[0], segments[1]),
+        signature_bytes,
+    })
+}
+
+fn decode_segment(segment: &str, name: &'static str) -> Result<Vec<u8>, JwtError> {
+    // Tolerate padded base64url, which some tools emit.
+    URL_SAFE_NO_PAD
+        .decode(segment.trim_end_matches('='))
+        .map_err(|e| JwtError::Base64 {
+            segment: name,
+            message: e.to_string(),
+        })
+}
+
+fn decode_json_segment(segment: &str, name: &'static str) -> Result<Map<String, Value>, JwtError> {
+    let bytes = decode_segment(segment, name)?;
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(other) => Err(JwtError::Json {
+            segment: name,
+            message: format!("expected an object, found {}", json_type(&other)),
+        }),
+        Err(e) => Err(JwtError::Json {
+            segment: name,
+            message: e.to_string(),
+        }),
+    }
+}
+
+fn standard_claims(payload: &Map<String, Value>) -> Result<StandardClaims, JwtError> {
+    let aud = match payload.get("aud") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::String(aud)) => vec![aud.clone()],
+        Some(Value::Array(_)) => string_list(payload, "aud")?,
+        Some(_) => {
+            return Err(JwtError::Claim {
+                name: "aud",
+                expected: "a string or an array of strings",
+            })
+        }
+    };
+
+    let scp = match payload.get("scp") {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::String(scp)) => scp.split_whitespace().map(str::to_string).collect(),
+        Some(Value::Array(_)) => string_list(payload, "scp")?,
+        Some(_) => {
+            return Err(JwtError::Claim {
+                name: "scp",
+                expected: "a string or an array of strings",
+            })
+        }
+    };
+
+    Ok(StandardClaims {
+        aud,
+        iss: optional_string(payload, "iss")?,
+        tid: optional_string(payload, "tid")?,
+        oid: optional_string(payload, "oid")?,
+        scp,
+        roles: string_list(payload, "roles")?,
+        exp: numeric_date(payload, "exp")?,
+        nbf: numeric_date(payload, "nbf")?,
+        iat: numeric_date(payload, "iat")?,
+        appid: optional_string(payload, "appid")?,
+        azp: optional_string(payload, "azp")?,
+        ver: optional_string(payload, "ver")?,
+    })
+}
+
+fn token_version(claims: &StandardClaims) -> TokenVersion {
+    match claims.ver.as_deref() {
+        Some("1.0") => return TokenVersion::V1,
+        Some("2.0") => return TokenVersion::V2,
+        _ => {}
+    }
+    match claims.iss.as_deref() {
+        Some(iss) if iss.trim_end_matches('/').ends_with("/v2.0") => TokenVersion::V2,
+        Some(iss) if iss.starts_with("https://sts.windows.net/") => TokenVersion::V1,
+        _ => TokenVersion::Unknown,
+    }
+}
+
+fn string_field(map: &Map<String, Value>, name: &str) -> Option<String> {
+    map.get(name).and_then(Value::as_str).map(str::to_string)
+}
+
+fn optional_string(payload: &Map<String, Value>, name: &'static str) -> Result<Option<String>, JwtError> {
+    match payload.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(JwtError::Claim {
+            name,
+            expected: "a string",
+        }),
+    }
+}
+
+fn string_list(payload: &Map<String, Value>, name: &'static str) -> Result<Vec<String>, JwtError> {
+    let invalid = JwtError::Claim {
+        name,
+        expected: "an array of strings",
+    };
+    match payload.get(name) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| item.as_str().map(str::to_string).ok_or_else(|| invalid.clone()))
+            .collect(),
+        Some(_) => Err(invalid),
+    }
+}
+
+/// NumericDate claims are seconds since the epoch; fractional values are truncated.
+fn numeric_date(payload: &Map<String, Value>, name: &'static str) -> Result<Option<i64>, JwtError> {
+    match payload.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(number)) => number
+            .as_i64()
+            .or_else(|| number.as_f64().map(|value| value as i64))
+            .map(Some)
+            .ok_or(JwtError::Claim {
+                name,
+                expected: "a NumericDate",
+            }),
+        Some(_) => Err(JwtError::Claim {
+            name,
+            expected: "a NumericDate",
+        }),
+    }
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn segment(value: &Value) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(value).unwrap())
+    }
+
+    fn token_with(payload: Value) -> String {
+        format!("{}.{}.c2ln", segment(&json!({ "alg": "RS256", "typ": "JWT", "kid": "k1" })), segment(&payload))
+    }
+
+    fn claim_error(payload: Value) -> (&'static str, &'static str) {
+        match decode(&token_with(payload)) {
+            Err(JwtError::Claim { name, expected }) => (name, expected),
+            other => panic!("expected a claim error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn decodes_header_claims_and_signature() {
+        let token = token_with(json!({
+            "aud": "api://my-api",
+            "iss": "https://login.microsoftonline.com/72f988bf-86f1-41af-91ab-2d7cd011db47/v2.0",
+            "scp": "read  write",
+            "roles": ["Admin"],
+            "exp": 1700003600,
+            "custom": true,
+        }));
+        let decoded = decode(&token).unwrap();
+
+        assert_eq!(decoded.header.alg.as_deref(), Some("RS256"));
+        assert_eq!(decoded.header.kid.as_deref(), Some("k1"));
+        assert_eq!(decoded.claims.aud, ["api://my-api"]);
+        assert_eq!(decoded.claims.scp, ["read", "write"]);
+        assert_eq!(decoded.claims.roles, ["Admin"]);
+        assert_eq!(decoded.claims.exp, Some(1700003600));
+        assert_eq!(decoded.raw_payload["custom"], json!(true));
+        assert_eq!(decoded.signature, "c2ln");
+        assert_eq!(decoded.signature_bytes, b"sig");
+        assert_eq!(decoded.signing_input, token.rsplit_once('.').unwrap().0);
+    }
+
+    #[test]
+    fn ignores_a_bearer_prefix_and_whitespace() {
+        let token = token_with(json!({ "aud": "api://my-api" }));
+        for input in [format!("  {}\n", token), format!("Bearer {}", token), format!(" Bearer  {} ", token)] {
+            assert_eq!(decode(&input).unwrap().signing_input, decode(&token).unwrap().signing_input);
+        }
+        assert!(matches!(decode(" \n\t"), Err(JwtError::Empty)));
+    }
+
+    #[test]
+    fn rejects_wrong_segment_counts() {
+        assert!(matches!(decode("a.b"), Err(JwtError::SegmentCount(2))));
+        assert!(matches!(decode("a.b.c.d"), Err(JwtError::SegmentCount(4))));
+
+        let jwe = decode("eyJhbGciOiJSU0EtT0FFUCJ9.a.b.c.d").unwrap_err();
+        assert!(matches!(jwe, JwtError::SegmentCount(5)));
+        assert!(jwe.to_string().contains("encrypted JWE"));
+    }
+
+    #[test]
+    fn reports_the_segment_with_bad_base64() {
+        let header = segment(&json!({ "alg": "none" }));
+        let payload = segment(&json!({}));
+
+        for (token, expected) in [
+            (format!("!!!.{}.", payload), "header"),
+            (format!("{}.!!!.", header), "payload"),
+            (format!("{}.{}.!!!", header, payload), "signature"),
+        ] {
+            match decode(&token) {
+                Err(JwtError::Base64 { segment, .. }) => assert_eq!(segment, expected),
+                other => panic!("{}: expected a base64 error, got {:?}", token, other),
+            }
+        }
+
+        // Padded base64url is tolerated.
+        assert!(decode(&format!("{}=.{}.", header, payload)).is_ok());
+    }
+
+    #[test]
+    fn reports_the_segment_that_is_not_a_json_object() {
+        let object = segment(&json!({}));
+        let not_json = URL_SAFE_NO_PAD.encode("not json");
+
+        for (token, expected, message) in [
+            (format!("{}.{}.", segment(&json!([1, 2])), object), "header", "found an array"),
+            (format!("{}.{}.", object, segment(&json!("claims"))), "payload", "found a string"),
+            (format!("{}.{}.", not_json, object), "header", ""),
+            (format!("{}.{}.", object, not_json), "payload", ""),
+        ] {
+            match decode(&token) {
+                Err(JwtError::Json { segment, message: actual }) => {
+                    assert_eq!(segment, expected);
+                    assert!(actual.contains(message), "{}", actual);
+                }
+                other => panic!("{}: expected a JSON error, got {:?}", token, other),
+            }
+        }
+    }
+
+    #[test]
+    fn aud_and_scp_may_be_a_string_or_an_array() {
+        let single = decode(&token_with(json!({ "aud": "api://a", "scp": "read write" }))).unwrap();
+        assert_eq!(single.claims.aud, ["api://a"]);
+        assert_eq!(single.claims.scp, ["read", "write"]);
+
+        let list = decode(&token_with(json!({ "aud": ["api://a", "api://b"], "scp": ["read", "write"] }))).unwrap();
+        assert_eq!(list.claims.aud, ["api://a", "api://b"]);
+        assert_eq!(list.claims.scp, ["read", "write"]);
+
+        let missing = decode(&token_with(json!({ "aud": null }))).unwrap();
+        assert!(missing.claims.aud.is_empty());
+        assert!(missing.claims.scp.is_empty());
+    }
+
+    #[test]
+    fn rejects_claims_of_the_wrong_type() {
+        assert_eq!(claim_error(json!({ "aud": 1 })), ("aud", "a string or an array of strings"));
+        assert_eq!(claim_error(json!({ "aud": ["api://a", 1] })), ("aud", "an array of strings"));
+        assert_eq!(claim_error(json!({ "scp": { "read": true } })), ("scp", "a string or an array of strings"));
+        assert_eq!(claim_error(json!({ "scp": ["read", null] })), ("scp", "an array of strings"));
+        assert_eq!(claim_error(json!({ "roles": "Admin" })), ("roles", "an array of strings"));
+        assert_eq!(claim_error(json!({ "iss": 42 })), ("iss", "a string"));
+        assert_eq!(claim_error(json!({ "exp": "1700000000" })), ("exp", "a NumericDate"));
+        assert_eq!(claim_error(json!({ "nbf": true })), ("nbf", "a NumericDate"));
+    }
+
+    #[test]
+    fn fractional_numeric_dates_are_truncated() {
+        let decoded = decode(&token_with(json!({ "exp": 1700003600.75, "nbf": 1699999999.2, "iat": 1700000000 }))).unwrap();
+        assert_eq!(decoded.claims.exp, Some(1700003600));
+        assert_eq!(decoded.claims.nbf, Some(1699999999));
+        assert_eq!(decoded.claims.iat, Some(1700000000));
+    }
+
+    #[test]
+    fn detects_the_token_version() {
+        let version = |payload: Value| decode(&token_with(payload)).unwrap().version;
+
+        assert_eq!(version(json!({ "ver": "1.0" })), TokenVersion::V1);
+        assert_eq!(version(json!({ "ver": "2.0" })), TokenVersion::V2);
+        // `ver` wins over the issuer.
+        assert_eq!(
+            version(json!({ "ver": "1.0", "iss": "https://login.microsoftonline.com/tid/v2.0" })),
+            TokenVersion::V1
+        );
+
+        assert_eq!(version(json!({ "iss": "https://sts.windows.net/tid/" })), TokenVersion::V1);
+        assert_eq!(version(json!({ "iss": "https://login.microsoftonline.com/tid/v2.0" })), TokenVersion::V2);
+        assert_eq!(version(json!({ "iss": "https://login.microsoftonline.com/tid/v2.0/" })), TokenVersion::V2);
+
+        assert_eq!(version(json!({ "ver": "3.0" })), TokenVersion::Unknown);
+        assert_eq!(version(json!({ "iss": "https://accounts.google.com" })), TokenVersion::Unknown);
+        assert_eq!(version(json!({})), TokenVersion::Unknown);
+    }
+}
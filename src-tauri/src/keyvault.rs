@@ -0,0 +1,330 @@
+//! Minimal Azure Key Vault REST client used by native token flows.
+//!
+//! Vault requests are authorized with an Azure CLI access token (`az account
+//! get-access-token`), which is what `DefaultAzureCredential` ends up using on a developer
+//! machine in the sidecar. Failures are reported as [`CommandError::Rpc`] carrying the same
+//! structured data as the sidecar's `KeyVaultError`, so the UI renders them identically.
+
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use serde::Deserialize;
+
+use crate::client_credentials::HANDLER_ERROR_CODE;
+use crate::error::CommandError;
+
+const API_VERSION: &str = "7.4";
+const VAULT_RESOURCE: &str = "https://vault.azure.net";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Refresh the cached CLI token this long before it expires.
+const TOKEN_REFRESH_MARGIN_SECS: u64 = 300;
+
+lazy_static::lazy_static! {
+    static ref VAULT_TOKEN: Mutex<Option<(String, u64)>> = Mutex::new(None);
+}
+
+/// Certificate metadata needed to build a client assertion
+#[derive(Debug, Clone)]
+pub struct VaultCertificate {
+    /// DER-encoded X.509 certificate
+    pub cer: Vec<u8>,
+    /// Key identifier used for remote signing
+    pub key_id: String,
+}
+
+#[derive(Deserialize)]
+struct CliAccessToken {
+    #[serde(rename = "accessToken")]
+    access_token: String,
+    /// Unix timestamp; only reported by Azure CLI 2.54+
+    expires_on: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct SecretBundle {
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CertificateBundle {
+    cer: Option<String>,
+    kid: Option<String>,
+    attributes: Option<CertificateAttributes>,
+}
+
+#[derive(Deserialize)]
+struct CertificateAttributes {
+    exp: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct SignResult {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct VaultErrorBody {
+    error: Option<VaultErrorDetail>,
+}
+
+#[derive(Deserialize)]
+struct VaultErrorDetail {
+    code: Option<String>,
+    message: Option<String>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn portal_url(vault_uri: &str) -> String {
+    format!(
+        "https://portal.azure.com/#view/Microsoft_Azure_KeyVault/VaultMenuBlade/~/overview/vaultUri/{}",
+        url::form_urlencoded::byte_serialize(vault_uri.as_bytes()).collect::<String>()
+    )
+}
+
+/// Build a Key Vault error with the sidecar's `KeyVaultErrorData` shape.
+fn vault_error(code: &str, message: String, action: &str, vault_uri: Option<&str>, detail: Option<String>) -> CommandError {
+    let mut data = serde_json::json!({
+        "code": code,
+        "message": message,
+        "action": action,
+        "severity": "error",
+        "detail": detail,
+    });
+    if let Some(vault_uri) = vault_uri {
+        data["actionUrl"] = serde_json::json!(portal_url(vault_uri));
+        data["actionLabel"] = serde_json::json!("Open Key Vault in Azure Portal");
+        data["context"] = serde_json::json!({ "vaultUri": vault_uri });
+    }
+    CommandError::Rpc {
+        code: HANDLER_ERROR_CODE,
+        message,
+        data: Some(data),
+    }
+}
+
+fn credential_unavailable(detail: String) -> CommandError {
+    vault_error(
+        "CREDENTIAL_UNAVAILABLE",
+        "Azure credentials not available.".to_string(),
+        "Run \"az login\" in your terminal to authenticate with Azure CLI.",
+        None,
+        Some(detail),
+    )
+}
+
+/// Get (and cache) an Azure CLI access token for Key Vault.
+async fn vault_access_token() -> Result<String, CommandError> {
+    if let Some((token, expires_on)) = VAULT_TOKEN.lock().unwrap().as_ref() {
+        if now_secs() + TOKEN_REFRESH_MARGIN_SECS < *expires_on {
+            return Ok(token.clone());
+        }
+    }
+
+    let program = if cfg!(windows) { "az.cmd" } else { "az" };
+    let mut command = tokio::process::Command::new(program);
+    command.args(["account", "get-access-token", "--resource", VAULT_RESOURCE, "--output", "json"]);
+    // GUI launches on macOS don't inherit the shell PATH; add the usual install locations.
+    if cfg!(target_os = "macos") {
+        let current_path = std::env::var("PATH").unwrap_or_default();
+        command.env("PATH", format!("/opt/homebrew/bin:/usr/local/bin:{}", current_path));
+    }
+    #[cfg(all(target_os = "windows", not(debug_assertions)))]
+    {
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let output = command
+        .output()
+        .await
+        .map_err(|e| credential_unavailable(format!("Failed to run Azure CLI: {}", e)))?;
+    if !output.status.success() {
+        return Err(credential_unavailable(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let token: CliAccessToken = serde_json::from_slice(&output.stdout)
+        .map_err(|e| credential_unavailable(format!("Unexpected Azure CLI output: {}", e)))?;
+
+    // Older CLIs only report a local-time `expiresOn`; assume the minimum token lifetime.
+    let expires_on = token.expires_on.unwrap_or_else(|| now_secs() + 2 * TOKEN_REFRESH_MARGIN_SECS);
+    *VAULT_TOKEN.lock().unwrap() = Some((token.access_token.clone(), expires_on));
+    Ok(token.access_token)
+}
+
+/// Map a failed vault response to a structured error.
+async fn response_error(
+    response: reqwest::Response,
+    kind: &str,
+    name: &str,
+    vault_uri: &str,
+) -> CommandError {
+    let status = response.status();
+    let body: Option<VaultErrorBody> = response.json().await.ok();
+    let detail = body
+        .and_then(|b| b.error)
+        .map(|e| format!("{}: {}", e.code.unwrap_or_default(), e.message.unwrap_or_default()));
+
+    match status.as_u16() {
+        401 => {
+            // Cached token was revoked or the vault is in another tenant; retry gets a fresh one.
+            *VAULT_TOKEN.lock().unwrap() = None;
+            credential_unavailable(detail.unwrap_or_else(|| "HTTP 401 Unauthorized".to_string()))
+        }
+        403 => vault_error(
+            "ACCESS_DENIED",
+            format!("Access denied to Key Vault {} '{}'.", kind, name),
+            if kind == "certificate" {
+                "Verify you have the 'Key Vault Crypto User' and 'Key Vault Certificates User' role(s) assigned. If using PIM, ensure your elevated access is active."
+            } else {
+                "Verify you have the 'Key Vault Secrets User' role(s) assigned. If using PIM, ensure your elevated access is active."
+            },
+            Some(vault_uri),
+            detail,
+        ),
+        404 => vault_error(
+            if kind == "certificate" { "CERT_NOT_FOUND" } else { "SECRET_NOT_FOUND" },
+            format!(
+                "{} '{}' not found in Key Vault.",
+                if kind == "certificate" { "Certificate" } else { "Secret" },
+                name
+            ),
+            "Verify the name is correct and exists in the Key Vault. Check for typos.",
+            Some(vault_uri),
+            detail,
+        ),
+        _ => vault_error(
+            "UNKNOWN",
+            format!("Failed to access {} '{}' in Key Vault (HTTP {}).", kind, name, status),
+            "Check the technical details below. Verify Key Vault configuration and your Azure identity permissions.",
+            Some(vault_uri),
+            detail,
+        ),
+    }
+}
+
+fn network_error(vault_uri: &str, error: reqwest::Error) -> CommandError {
+    vault_error(
+        "NETWORK_ERROR",
+        "Unable to connect to Key Vault.".to_string(),
+        "Check your network connection. If the Key Vault uses Private Endpoints, ensure your network can reach it.",
+        Some(vault_uri),
+        Some(error.to_string()),
+    )
+}
+
+fn item_url(vault_uri: &str, collection: &str, name: &str) -> String {
+    format!(
+        "{}/{}/{}?api-version={}",
+        vault_uri.trim_end_matches('/'),
+        collection,
+        name,
+        API_VERSION
+    )
+}
+
+/// Read a secret's current value.
+pub async fn get_secret(vault_uri: &str, name: &str) -> Result<String, CommandError> {
+    let token = vault_access_token().await?;
+    let response = reqwest::Client::new()
+        .get(item_url(vault_uri, "secrets", name))
+        .bearer_auth(token)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| network_error(vault_uri, e))?;
+    if !response.status().is_success() {
+        return Err(response_error(response, "secret", name, vault_uri).await);
+    }
+    let bundle: SecretBundle = response.json().await.map_err(|e| network_error(vault_uri, e))?;
+    bundle
+        .value
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| {
+            vault_error(
+                "UNKNOWN",
+                format!("Secret '{}' value is empty", name),
+                "Set a value for the secret in Key Vault.",
+                Some(vault_uri),
+                None,
+            )
+        })
+}
+
+/// Read a certificate's public part and signing key id, rejecting expired certificates.
+pub async fn get_certificate(vault_uri: &str, name: &str) -> Result<VaultCertificate, CommandError> {
+    let token = vault_access_token().await?;
+    let response = reqwest::Client::new()
+        .get(item_url(vault_uri, "certificates", name))
+        .bearer_auth(token)
+        .timeout(REQUEST_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| network_error(vault_uri, e))?;
+    if !response.status().is_success() {
+        return Err(response_error(response, "certificate", name, vault_uri).await);
+    }
+    let bundle: CertificateBundle = response.json().await.map_err(|e| network_error(vault_uri, e))?;
+
+    if let Some(exp) = bundle.attributes.and_then(|a| a.exp) {
+        if exp < now_secs() {
+            return Err(vault_error(
+                "CERT_EXPIRED",
+                format!("Certificate '{}' has expired.", name),
+                "Rotate the certificate in Key Vault and update your Entra app registration.",
+                Some(vault_uri),
+                Some("The certificate has expired and cannot be used for authentication.".to_string()),
+            ));
+        }
+    }
+
+    let cer = bundle
+        .cer
+        .and_then(|cer| STANDARD.decode(cer).ok())
+        .ok_or_else(|| {
+            vault_error(
+                "UNKNOWN",
+                format!("Certificate '{}' public key not accessible", name),
+                "Ensure the certificate has an associated public key.",
+                Some(vault_uri),
+                None,
+            )
+        })?;
+    let key_id = bundle.kid.ok_or_else(|| {
+        vault_error(
+            "UNKNOWN",
+            format!("Certificate '{}' does not have an associated key", name),
+            "Ensure the certificate was created with a key.",
+            Some(vault_uri),
+            None,
+        )
+    })?;
+    Ok(VaultCertificate { cer, key_id })
+}
+
+/// Sign a SHA-256 digest with a vault key (`alg` is a JWS algorithm such as `RS256`).
+pub async fn sign(key_id: &str, alg: &str, digest: &[u8]) -> Result<Vec<u8>, CommandError> {
+    let token = vault_access_token().await?;
+    let url = format!("{}/sign?api-version={}", key_id.trim_end_matches('/'), API_VERSION);
+    let vault_uri = key_id.split("/keys/").next().unwrap_or(key_id);
+    let response = reqwest::Client::new()
+        .post(&url)
+        .bearer_auth(token)
+        .timeout(REQUEST_TIMEOUT)
+        .json(&serde_json::json!({ "alg": alg, "value": URL_SAFE_NO_PAD.encode(digest) }))
+        .send()
+        .await
+        .map_err(|e| network_error(vault_uri, e))?;
+    if !response.status().is_success() {
+        return Err(response_error(response, "certificate", key_id, vault_uri).await);
+    }
+    let result: SignResult = response.json().await.map_err(|e| network_error(vault_uri, e))?;
+    URL_SAFE_NO_PAD
+        .decode(result.value.trim_end_matches('='))
+        .map_err(|e| CommandError::network(&url, format!("Key Vault returned an invalid signature: {}", e)))
+}
@@ -0,0 +1,505 @@
+//! Command-line arguments for the desktop window.
+//!
+//! `entra-token-studio --issue-favorite "Graph as daemon"` or `--decode <file>` from a shell or
+//! desktop shortcut is parsed here into a [`LaunchCommand`] and handed to the UI as a
+//! `launch-command` event. That works for the first launch and for later ones, which the
+//! single-instance plugin forwards to the running window.
+//!
+//! With `--wait`, the launching process doesn't go through the plugin. It sends its
+//! arguments to the running instance over a local socket, prints the result the window
+//! reports and exits with 1 if it failed. The socket is a Unix socket in the user's runtime
+//! directory (a named pipe on Windows) that only the same user can open. If no instance is
+//! running, the app starts normally and runs the command itself.
+//!
+//! Unlike deep links, these commands run without asking: only the user's own processes can
+//! start the binary or open the socket, and those can already use the headless CLI.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::oneshot;
+
+use crate::cli::{process_args, APP_IDENTIFIER, EXIT_FAILURE, EXIT_OK, EXIT_USAGE};
+use crate::client_credentials::random_uuid;
+use crate::error::CommandError;
+
+const ISSUE_FAVORITE_ARG: &str = "--issue-favorite";
+const DECODE_ARG: &str = "--decode";
+const WAIT_ARG: &str = "--wait";
+
+/// Commands waiting for the UI; the oldest are dropped beyond this
+const MAX_PENDING: usize = 10;
+const MAX_TOKEN_FILE_BYTES: u64 = 64 * 1024;
+const MAX_REQUEST_BYTES: u64 = 256 * 1024;
+/// How long a `--wait` caller waits for the window to act on its command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What a launch asks the running window to do
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum LaunchCommand {
+    /// Issue a token from the favorite with this id or name
+    IssueFavorite { favorite: String },
+    /// Show a token in the token dock; nothing is issued
+    Decode { token: String },
+}
+
+/// Payload of the `launch-command` event
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchRequest {
+    pub id: String,
+    #[serde(flatten)]
+    pub command: LaunchCommand,
+}
+
+/// Outcome reported by the window, written back to a `--wait` caller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LaunchResult {
+    pub ok: bool,
+    pub message: String,
+}
+
+impl LaunchResult {
+    fn failed(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+        }
+    }
+}
+
+/// What a `--wait` caller sends over the socket
+#[derive(Debug, Serialize, Deserialize)]
+struct ForwardedLaunch {
+    args: Vec<String>,
+    cwd: PathBuf,
+}
+
+struct Pending {
+    request: LaunchRequest,
+    reply: Option<oneshot::Sender<LaunchResult>>,
+}
+
+lazy_static::lazy_static! {
+    static ref PENDING: Mutex<VecDeque<Pending>> = Mutex::new(VecDeque::new());
+}
+
+/// Parse launch arguments (without the program name). Relative paths are resolved against
+/// `cwd`, the launching process's directory. Returns `None` if there is no launch command,
+/// e.g. a plain launch or one with a deep link URL.
+pub fn parse(args: &[String], cwd: &Path) -> Result<Option<LaunchCommand>, String> {
+    if !args.iter().any(|arg| arg == ISSUE_FAVORITE_ARG || arg == DECODE_ARG) {
+        return Ok(None);
+    }
+
+    let mut command = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let parsed = match arg.as_str() {
+            ISSUE_FAVORITE_ARG => {
+                let favorite = value(arg, iter.next())?;
+                if favorite.len() > 256 {
+                    return Err(format!("{} is too long", ISSUE_FAVORITE_ARG));
+                }
+                LaunchCommand::IssueFavorite { favorite }
+            }
+            DECODE_ARG => LaunchCommand::Decode {
+                token: read_token(&value(arg, iter.next())?, cwd)?,
+            },
+            WAIT_ARG => continue,
+            other => return Err(format!("unexpected argument '{}'", other)),
+        };
+        if command.replace(parsed).is_some() {
+            return Err(format!("give only one of {} and {}", ISSUE_FAVORITE_ARG, DECODE_ARG));
+        }
+    }
+    Ok(command)
+}
+
+fn value(flag: &str, value: Option<&String>) -> Result<String, String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty() && !value.starts_with("--"))
+        .ok_or_else(|| format!("{} requires a value", flag))
+}
+
+/// A token given directly, or the path of a file holding one
+fn read_token(value: &str, cwd: &Path) -> Result<String, String> {
+    if crate::jwt::decode(value).is_ok() {
+        return Ok(value.to_string());
+    }
+    let path = cwd.join(value);
+    let size = std::fs::metadata(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_TOKEN_FILE_BYTES {
+        return Err(format!("{} is too large to hold a token", path.display()));
+    }
+    let token = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .trim()
+        .to_string();
+    crate::jwt::decode(&token).map_err(|e| format!("{} doesn't hold a valid token: {}", path.display(), e))?;
+    Ok(token)
+}
+
+/// Queue a command for the UI and announce it. Returns the command's id.
+fn dispatch(
+    app: &tauri::AppHandle,
+    command: LaunchCommand,
+    reply: Option<oneshot::Sender<LaunchResult>>,
+) -> Result<String, CommandError> {
+    let request = LaunchRequest {
+        id: random_uuid()?,
+        command,
+    };
+    let id = request.id.clone();
+    {
+        let mut queue = PENDING.lock().unwrap();
+        if queue.len() >= MAX_PENDING {
+            queue.pop_front();
+        }
+        queue.push_back(Pending {
+            request: request.clone(),
+            reply,
+        });
+    }
+
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.set_focus();
+    }
+    if let Err(e) = app.emit("launch-command", &request) {
+        log::warn!("Failed to emit launch-command event: {}", e);
+    }
+    Ok(id)
+}
+
+fn handle_args(app: &tauri::AppHandle, args: &[String], cwd: &Path) {
+    match parse(args, cwd) {
+        Ok(Some(command)) => {
+            if let Err(e) = dispatch(app, command, None) {
+                log::warn!("Ignoring launch arguments: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(reason) => log::warn!("Ignoring launch arguments: {}", reason),
+    }
+}
+
+/// Handle the arguments this instance was started with.
+pub fn handle_launch(app: &tauri::AppHandle) {
+    let args = process_args();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    handle_args(app, args.get(1..).unwrap_or_default(), &cwd);
+}
+
+/// Handle the arguments of a second launch forwarded by the single-instance plugin.
+pub fn handle_second_instance(app: &tauri::AppHandle, argv: &[String], cwd: &str) {
+    handle_args(app, argv.get(1..).unwrap_or_default(), Path::new(cwd));
+}
+
+/// Commands the UI hasn't taken yet, e.g. the one the app was launched with
+pub fn pending() -> Vec<LaunchRequest> {
+    PENDING.lock().unwrap().iter().map(|pending| pending.request.clone()).collect()
+}
+
+/// Take a command the UI has acted on and pass its result to a waiting caller.
+/// Returns `false` if the command is unknown or was already completed.
+pub fn complete(id: &str, result: LaunchResult) -> bool {
+    let pending = {
+        let mut queue = PENDING.lock().unwrap();
+        let Some(index) = queue.iter().position(|pending| pending.request.id == id) else {
+            return false;
+        };
+        queue.remove(index)
+    };
+    if let Some(reply) = pending.and_then(|pending| pending.reply) {
+        let _ = reply.send(result);
+    }
+    true
+}
+
+fn forget(id: &str) {
+    PENDING.lock().unwrap().retain(|pending| pending.request.id != id);
+}
+
+// ----------------------------------------------------------------------------
+// Local socket
+// ----------------------------------------------------------------------------
+
+#[cfg(unix)]
+fn socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .or_else(dirs::data_dir)
+        .map(|dir| dir.join(APP_IDENTIFIER).join("launch.sock"))
+}
+
+#[cfg(windows)]
+fn pipe_name() -> String {
+    let user = std::env::var("USERNAME").unwrap_or_default();
+    format!(r"\\.\pipe\{}-launch-{}", APP_IDENTIFIER, user)
+}
+
+/// Listen for `--wait` callers for the lifetime of the app.
+pub fn start_listener(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = listen(app).await {
+            log::warn!("Launch socket unavailable, --wait won't reach this instance: {}", e);
+        }
+    });
+}
+
+#[cfg(unix)]
+async fn listen(app: tauri::AppHandle) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = socket_path()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no runtime or data directory"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    // The single-instance plugin keeps other instances out, so any existing socket is stale.
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tauri::async_runtime::spawn(serve_connection(app.clone(), stream));
+    }
+}
+
+#[cfg(windows)]
+async fn listen(app: tauri::AppHandle) -> std::io::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = pipe_name();
+    // Fail rather than share a pipe another process created first.
+    let mut server = ServerOptions::new().first_pipe_instance(true).create(&name)?;
+    loop {
+        server.connect().await?;
+        let connected = server;
+        server = ServerOptions::new().create(&name)?;
+        tauri::async_runtime::spawn(serve_connection(app.clone(), connected));
+    }
+}
+
+/// Read one forwarded launch, run it and write back its result.
+async fn serve_connection<S>(app: tauri::AppHandle, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = tokio::io::BufReader::new(reader.take(MAX_REQUEST_BYTES));
+    let mut line = String::new();
+    let result = match reader.read_line(&mut line).await {
+        Ok(_) => match serde_json::from_str::<ForwardedLaunch>(&line) {
+            Ok(forwarded) => run_forwarded(&app, forwarded).await,
+            Err(e) => LaunchResult::failed(format!("Malformed launch request: {}", e)),
+        },
+        Err(e) => LaunchResult::failed(format!("Failed to read launch request: {}", e)),
+    };
+
+    let mut body = serde_json::to_string(&result).unwrap_or_default();
+    body.push('\n');
+    if let Err(e) = writer.write_all(body.as_bytes()).await {
+        log::debug!("Launch caller went away before its result was written: {}", e);
+    }
+    let _ = writer.shutdown().await;
+}
+
+async fn run_forwarded(app: &tauri::AppHandle, forwarded: ForwardedLaunch) -> LaunchResult {
+    let command = match parse(&forwarded.args, &forwarded.cwd) {
+        Ok(Some(command)) => command,
+        Ok(None) => return LaunchResult::failed("No launch command given"),
+        Err(reason) => return LaunchResult::failed(reason),
+    };
+    let (reply, result) = oneshot::channel();
+    let id = match dispatch(app, command, Some(reply)) {
+        Ok(id) => id,
+        Err(e) => return LaunchResult::failed(e.to_string()),
+    };
+    match tokio::time::timeout(REPLY_TIMEOUT, result).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => LaunchResult::failed("The command was dropped before the window handled it"),
+        Err(_) => {
+            // Don't act on it after the caller has given up.
+            forget(&id);
+            LaunchResult::failed(format!(
+                "The window didn't handle the command within {} seconds",
+                REPLY_TIMEOUT.as_secs()
+            ))
+        }
+    }
+}
+
+#[cfg(unix)]
+fn connect() -> std::io::Result<std::os::unix::net::UnixStream> {
+    let path = socket_path()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no runtime or data directory"))?;
+    std::os::unix::net::UnixStream::connect(path)
+}
+
+#[cfg(windows)]
+fn connect() -> std::io::Result<std::fs::File> {
+    std::fs::OpenOptions::new().read(true).write(true).open(pipe_name())
+}
+
+/// With `--wait`, hand the launch arguments to the running instance and print its result.
+/// Returns the exit code, or `None` to start the app (no `--wait`, or no instance to reach).
+pub fn forward() -> Option<i32> {
+    let args: Vec<String> = process_args().into_iter().skip(1).collect();
+    if !args.iter().any(|arg| arg == WAIT_ARG) {
+        return None;
+    }
+    let cwd = std::env::current_dir().unwrap_or_default();
+
+    #[cfg(windows)]
+    crate::cli::attach_parent_console();
+
+    match parse(&args, &cwd) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            eprintln!("error: {} needs {} or {}", WAIT_ARG, ISSUE_FAVORITE_ARG, DECODE_ARG);
+            return Some(EXIT_USAGE);
+        }
+        Err(reason) => {
+            eprintln!("error: {}", reason);
+            return Some(EXIT_USAGE);
+        }
+    }
+    let mut stream = connect().ok()?;
+
+    let exchanged = (|| -> std::io::Result<LaunchResult> {
+        let mut request = serde_json::to_string(&ForwardedLaunch { args, cwd })?;
+        request.push('\n');
+        stream.write_all(request.as_bytes())?;
+        stream.flush()?;
+        let mut line = String::new();
+        BufReader::new(&mut stream).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line)?)
+    })();
+
+    Some(match exchanged {
+        Ok(result) if result.ok => {
+            println!("{}", result.message);
+            EXIT_OK
+        }
+        Ok(result) => {
+            eprintln!("error: {}", result.message);
+            EXIT_FAILURE
+        }
+        Err(e) => {
+            eprintln!("error: lost the connection to the running app: {}", e);
+            EXIT_FAILURE
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn token() -> String {
+        use base64::Engine;
+        let encode = |json: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+        format!("{}.{}.c2ln", encode(r#"{"alg":"none"}"#), encode(r#"{"sub":"launch"}"#))
+    }
+
+    /// A fresh directory under the system temp directory, unique to this test process
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entra-token-studio-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn parse_err(values: &[&str]) -> String {
+        parse(&args(values), Path::new("/")).unwrap_err()
+    }
+
+    #[test]
+    fn no_launch_command() {
+        assert!(parse(&[], Path::new("/")).unwrap().is_none());
+        assert!(parse(&args(&["entra-token-studio://issue?favorite=x"]), Path::new("/")).unwrap().is_none());
+        // --wait alone is not a command; `forward` reports it as a usage error.
+        assert!(parse(&args(&["--wait"]), Path::new("/")).unwrap().is_none());
+    }
+
+    #[test]
+    fn issue_favorite() {
+        let parsed = parse(&args(&["--wait", "--issue-favorite", " Graph as daemon "]), Path::new("/")).unwrap();
+        assert!(matches!(parsed, Some(LaunchCommand::IssueFavorite { favorite }) if favorite == "Graph as daemon"));
+        assert_eq!(parse_err(&["--issue-favorite", &"x".repeat(257)]), "--issue-favorite is too long");
+    }
+
+    #[test]
+    fn missing_or_flag_value() {
+        assert_eq!(parse_err(&["--issue-favorite"]), "--issue-favorite requires a value");
+        assert_eq!(parse_err(&["--issue-favorite", "  "]), "--issue-favorite requires a value");
+        assert_eq!(parse_err(&["--issue-favorite", "--wait"]), "--issue-favorite requires a value");
+        assert_eq!(parse_err(&["--decode", "--issue-favorite", "x"]), "--decode requires a value");
+    }
+
+    #[test]
+    fn only_one_command() {
+        let token = token();
+        assert_eq!(
+            parse_err(&["--issue-favorite", "x", "--decode", &token]),
+            "give only one of --issue-favorite and --decode"
+        );
+        assert_eq!(
+            parse_err(&["--issue-favorite", "x", "--issue-favorite", "y"]),
+            "give only one of --issue-favorite and --decode"
+        );
+    }
+
+    #[test]
+    fn unexpected_argument() {
+        assert_eq!(parse_err(&["--issue-favorite", "x", "extra"]), "unexpected argument 'extra'");
+        assert_eq!(parse_err(&["--verbose", "--decode", "x"]), "unexpected argument '--verbose'");
+    }
+
+    #[test]
+    fn decode_inline_token() {
+        let token = token();
+        let parsed = parse(&args(&["--decode", &token]), Path::new("/nonexistent")).unwrap();
+        assert!(matches!(parsed, Some(LaunchCommand::Decode { token: decoded }) if decoded == token));
+    }
+
+    #[test]
+    fn decode_relative_path_uses_cwd() {
+        let dir = temp_dir("launch-relative");
+        std::fs::write(dir.join("token.jwt"), format!("{}\n", token())).unwrap();
+
+        let parsed = parse(&args(&["--decode", "token.jwt"]), &dir).unwrap();
+        assert!(matches!(parsed, Some(LaunchCommand::Decode { token: decoded }) if decoded == token()));
+
+        let error = parse(&args(&["--decode", "token.jwt"]), &dir.join("elsewhere")).unwrap_err();
+        assert!(error.starts_with("Failed to read"), "{}", error);
+    }
+
+    #[test]
+    fn decode_file_must_hold_a_small_token() {
+        let dir = temp_dir("launch-files");
+        std::fs::write(dir.join("large.jwt"), "a".repeat(MAX_TOKEN_FILE_BYTES as usize + 1)).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a token").unwrap();
+
+        let error = parse(&args(&["--decode", "large.jwt"]), &dir).unwrap_err();
+        assert!(error.ends_with("is too large to hold a token"), "{}", error);
+
+        let error = parse(&args(&["--decode", "notes.txt"]), &dir).unwrap_err();
+        assert!(error.contains("doesn't hold a valid token"), "{}", error);
+    }
+}
@@ -1,12 +1,39 @@
 use serde::{Deserialize, Serialize};
-use tauri::{Emitter, Manager};
-
+use tauri::Manager;
+
+mod auth_code;
+mod backend;
+mod cli;
+mod client_credentials;
+mod credentials;
+mod deep_link;
+mod device_code;
+mod error;
+mod git_credential;
+mod jwt;
+mod keyvault;
+mod launch_args;
+mod managed_identity;
+mod models;
+mod native;
+mod node_runtime;
+mod saved_apps;
+mod settings;
 mod sidecar;
-
-use sidecar::get_sidecar;
+mod store;
+mod token_server;
+mod verify;
+
+use error::{require_non_empty, CommandError};
+use models::{
+    AccountInfo, AuthStorageStatus, AzureAppRegistration, AzureCliResult, AzureKeyVault, AzureSubscription,
+    AzureVaultCredential, CredentialStatus, TokenResponse, ValidationResult,
+};
+use backend::{BackendKind, UserTokenRequest};
+use sidecar::{get_sidecar, CallOptions};
 
 /// Key Vault configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct KeyVaultConfig {
     pub uri: String,
@@ -21,33 +48,21 @@ pub struct KeyVaultConfig {
 pub struct TokenAppConfig {
     pub client_id: String,
     pub tenant_id: String,
+    /// Ignored when `credential` is set
+    #[serde(default)]
     pub key_vault: KeyVaultConfig,
-}
-
-/// Token response
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct TokenResponse {
-    pub access_token: String,
-    pub expires_on: String,
-    pub token_type: String,
-    pub scopes: Option<Vec<String>>,
-}
-
-/// Validation result
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ValidationResult {
-    pub valid: bool,
-    pub credential_type: String,
-    pub message: Option<String>,
-}
-
-/// Credential status
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CredentialStatus {
-    pub available: bool,
-    pub message: String,
+    /// Local certificate, keyring secret or federated assertion used instead of Key Vault
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<credentials::CredentialSource>,
+    /// Overrides the default token backend for this app
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<BackendKind>,
+    /// Token endpoint host for the native backend (defaults to `login.microsoftonline.com`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authority_host: Option<String>,
+    /// Certificate assertion algorithm for the native backend (defaults to RS256)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assertion_algorithm: Option<client_credentials::AssertionAlgorithm>,
 }
 
 /// Azure app list filters
@@ -63,105 +78,265 @@ pub struct AzureAppFilters {
     pub all: Option<bool>,
 }
 
-/// Acquire an app token via sidecar
+/// Acquire an app token via the app's token backend
 #[tauri::command]
 async fn acquire_app_token(
     config: TokenAppConfig,
     scopes: Vec<String>,
-) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<TokenResponse, CommandError> {
+    require_non_empty("clientId", &config.client_id)?;
+    require_non_empty("tenantId", &config.tenant_id)?;
+    if scopes.is_empty() {
+        return Err(CommandError::validation("scopes", "At least one scope is required"));
+    }
+
+    backend::for_app(&config)
+        .acquire_app_token(&config, &scopes, CallOptions::new(timeout_ms, request_id))
+        .await
+}
+
+/// Exchange a user token for a downstream API token on behalf of that user (OBO),
+/// authenticating with the app's own Key Vault or local credential
+#[tauri::command(rename_all = "camelCase")]
+async fn acquire_obo_token(
+    config: TokenAppConfig,
+    user_token: String,
+    scopes: Vec<String>,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<TokenResponse, CommandError> {
+    require_non_empty("clientId", &config.client_id)?;
+    require_non_empty("tenantId", &config.tenant_id)?;
+    if scopes.is_empty() {
+        return Err(CommandError::validation("scopes", "At least one downstream scope is required"));
+    }
+
+    // Pasted tokens often come straight from an Authorization header.
+    let user_token = user_token.trim();
+    let user_token = user_token.strip_prefix("Bearer ").unwrap_or(user_token).trim();
+    let decoded = jwt::decode(user_token)
+        .map_err(|e| CommandError::validation("userToken", format!("The user token is not a valid JWT: {}", e)))?;
+    if decoded.claims.scp.is_empty() {
+        return Err(CommandError::validation(
+            "userToken",
+            "On-behalf-of needs a delegated token issued to a user (it has no 'scp' claim)",
+        ));
+    }
 
-    manager
-        .call(
-            "acquire_app_token",
-            serde_json::json!({ "config": config, "scopes": scopes }),
-        )
+    backend::for_app(&config)
+        .acquire_obo_token(&config, user_token, &scopes, CallOptions::new(timeout_ms, request_id))
         .await
 }
 
 /// Validate Key Vault connectivity
 #[tauri::command]
-async fn validate_keyvault(config: KeyVaultConfig) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
+async fn validate_keyvault(
+    config: KeyVaultConfig,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<ValidationResult, CommandError> {
+    require_non_empty("uri", &config.uri)?;
+
+    backend::get(None)
+        .validate_keyvault(&config, CallOptions::new(timeout_ms, request_id))
+        .await
+}
 
-    manager.call("validate_keyvault", serde_json::to_value(config).unwrap()).await
+/// Validate a local PFX credential (file, keyring password, certificate dates)
+#[tauri::command(rename_all = "camelCase")]
+async fn validate_pfx_credential(client_id: String, path: String) -> Result<ValidationResult, CommandError> {
+    require_non_empty("clientId", &client_id)?;
+    require_non_empty("path", &path)?;
+    Ok(credentials::validate(&credentials::CredentialSource::Pfx { path }, &client_id).await)
+}
+
+/// Validate a local PEM certificate and private key
+#[tauri::command(rename_all = "camelCase")]
+async fn validate_pem_credential(cert_path: String, key_path: Option<String>) -> Result<ValidationResult, CommandError> {
+    require_non_empty("certPath", &cert_path)?;
+    let source = credentials::CredentialSource::Pem { cert_path, key_path };
+    // PEM keys are unencrypted, so nothing is read from the keyring.
+    Ok(credentials::validate(&source, "").await)
+}
+
+/// Validate that a client secret is stored in the OS keyring for an app
+#[tauri::command(rename_all = "camelCase")]
+async fn validate_keyring_secret(client_id: String) -> Result<ValidationResult, CommandError> {
+    require_non_empty("clientId", &client_id)?;
+    Ok(credentials::validate(&credentials::CredentialSource::KeyringSecret, &client_id).await)
+}
+
+/// Validate a federated assertion source: the token can be obtained and is an unexpired JWT
+#[tauri::command(rename_all = "camelCase")]
+async fn validate_federated_credential(
+    client_id: String,
+    assertion: credentials::FederatedAssertion,
+) -> Result<ValidationResult, CommandError> {
+    let source = credentials::CredentialSource::Federated { assertion };
+    Ok(credentials::validate(&source, &client_id).await)
+}
+
+/// Store an app's client secret or PFX password in the OS keyring
+#[tauri::command(rename_all = "camelCase")]
+fn store_app_secret(
+    client_id: String,
+    kind: credentials::KeyringSecretKind,
+    value: String,
+) -> Result<(), CommandError> {
+    require_non_empty("clientId", &client_id)?;
+    credentials::store_secret(&client_id, kind, &value)
+}
+
+/// Delete an app's stored client secret or PFX password. Returns `false` if none was stored.
+#[tauri::command(rename_all = "camelCase")]
+fn delete_app_secret(client_id: String, kind: credentials::KeyringSecretKind) -> Result<bool, CommandError> {
+    credentials::delete_secret(&client_id, kind)
 }
 
 /// Get credential status
 #[tauri::command]
-async fn get_credential_status() -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
+async fn get_credential_status(
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<CredentialStatus, CommandError> {
+    backend::get(None)
+        .get_credential_status(CallOptions::new(timeout_ms, request_id))
+        .await
+}
 
-    manager.call("get_credential_status", serde_json::json!({})).await
+/// Cancel an in-flight sidecar request by the `requestId` it was started with.
+/// Returns `false` if the request already finished or was never started.
+#[tauri::command]
+async fn cancel_request(request_id: String) -> bool {
+    sidecar::cancel(&request_id).await || auth_code::cancel(&request_id)
 }
 
-/// Check sidecar health - returns status and any startup errors
+/// Result of `check_sidecar_health`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SidecarHealth {
+    /// `running`, `restarting`, `failed` or `stopped`; `notUsed` when the default backend doesn't need it
+    sidecar: &'static str,
+    running: bool,
+    error: Option<String>,
+    error_code: Option<&'static str>,
+    error_detail: Option<CommandError>,
+    /// Automatic restarts after unexpected exits; `None` when the sidecar isn't used
+    restart_count: Option<u32>,
+    /// Exit code of the most recent unexpected exit (`None` if killed by a signal or not used)
+    last_exit_code: Option<i32>,
+    backend: &'static str,
+    protocol_version: u32,
+    build_hash: Option<String>,
+    capabilities: Option<sidecar::SidecarCapabilities>,
+}
+
+/// Check sidecar health - returns the default backend, the sidecar's state and any startup errors
 #[tauri::command]
-async fn check_sidecar_health() -> serde_json::Value {
+async fn check_sidecar_health() -> SidecarHealth {
+    let default_backend = backend::default_kind();
+    if default_backend == BackendKind::Demo {
+        // Node isn't needed unless an app opts back into the sidecar; don't start it just to check.
+        return SidecarHealth {
+            sidecar: "notUsed",
+            running: false,
+            error: None,
+            error_code: None,
+            error_detail: None,
+            restart_count: None,
+            last_exit_code: None,
+            backend: default_backend.as_str(),
+            protocol_version: sidecar::PROTOCOL_VERSION,
+            build_hash: None,
+            capabilities: None,
+        };
+    }
+
     let sidecar = get_sidecar().await;
     let manager = sidecar.lock().await;
 
-    let running = manager.child.is_some();
-    let error = manager.start_error.clone();
-    
-    // Extract error code from error message if present
-    let error_code = error.as_ref().and_then(|e| {
-        if e.contains("[NODE_NOT_FOUND]") {
-            Some("NODE_NOT_FOUND")
-        } else if e.contains("Could not find sidecar") {
-            Some("SIDECAR_SCRIPT_NOT_FOUND")
-        } else {
-            None
-        }
-    });
-
-    serde_json::json!({
-        "running": running,
-        "error": error,
-        "errorCode": error_code
-    })
+    let error = manager.start_error.as_ref();
+    SidecarHealth {
+        sidecar: manager.state(),
+        running: manager.is_running(),
+        error: error.map(|e| e.message().to_string()),
+        error_code: error.and_then(|e| e.code()),
+        error_detail: error.cloned(),
+        restart_count: Some(manager.restart_count),
+        last_exit_code: manager.last_exit_code,
+        backend: default_backend.as_str(),
+        protocol_version: sidecar::PROTOCOL_VERSION,
+        build_hash: manager.capabilities.as_ref().and_then(|c| c.build_hash.clone()),
+        capabilities: manager.capabilities.clone(),
+    }
 }
 
+/// Every Node.js runtime found for the sidecar, with its version and the one in use marked
+#[tauri::command]
+async fn list_node_runtimes() -> Vec<node_runtime::NodeRuntime> {
+    tauri::async_runtime::spawn_blocking(node_runtime::list)
+        .await
+        .unwrap_or_default()
+}
 
-/// List Azure subscriptions via Azure CLI
+/// Get the desktop settings kept by the Rust side (e.g. the Node.js runtime override)
 #[tauri::command]
-async fn list_azure_subscriptions() -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
+fn get_app_settings(app: tauri::AppHandle) -> Result<settings::AppSettings, CommandError> {
+    settings::load(&app_data_dir(&app)?)
+}
+
+/// Replace the desktop settings; a new Node.js runtime is used from the next sidecar start
+#[tauri::command]
+fn save_app_settings(app: tauri::AppHandle, settings: settings::AppSettings) -> Result<(), CommandError> {
+    settings::save(&app_data_dir(&app)?, &settings)
+}
+
+/// Start the sidecar again after it crashed too often to be restarted automatically
+#[tauri::command]
+async fn restart_sidecar() -> Result<(), CommandError> {
+    sidecar::restart().await
+}
+
+/// Get recent sidecar diagnostics (stderr lines), optionally only those after a cursor
+#[tauri::command]
+fn get_sidecar_logs(since: Option<u64>, limit: Option<usize>) -> Vec<sidecar::SidecarLogLine> {
+    sidecar::recent_logs(since, limit)
+}
 
-    manager
-        .call("list_azure_subscriptions", serde_json::json!({}))
+/// List Azure subscriptions via Azure CLI
+#[tauri::command]
+async fn list_azure_subscriptions(
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<AzureCliResult<Vec<AzureSubscription>>, CommandError> {
+    backend::get(None)
+        .list_azure_subscriptions(CallOptions::new(timeout_ms, request_id))
         .await
 }
 
 /// List Azure app registrations via Azure CLI
 #[tauri::command]
-async fn list_azure_apps(filters: Option<AzureAppFilters>) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
-
-    manager
-        .call("list_azure_apps", match filters {
-            Some(filters) => serde_json::to_value(filters).unwrap_or_else(|_| serde_json::json!({})),
-            None => serde_json::json!({}),
-        })
+async fn list_azure_apps(
+    filters: Option<AzureAppFilters>,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<AzureCliResult<Vec<AzureAppRegistration>>, CommandError> {
+    backend::get(None)
+        .list_azure_apps(filters.as_ref(), CallOptions::new(timeout_ms, request_id))
         .await
 }
 
 /// List Key Vaults via Azure CLI
 #[tauri::command]
-async fn list_keyvaults(subscription_id: Option<String>) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
-
-    manager
-        .call(
-            "list_keyvaults",
-            serde_json::json!({ "subscriptionId": subscription_id }),
-        )
+async fn list_keyvaults(
+    subscription_id: Option<String>,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<AzureCliResult<Vec<AzureKeyVault>>, CommandError> {
+    backend::get(None)
+        .list_keyvaults(subscription_id.as_deref(), CallOptions::new(timeout_ms, request_id))
         .await
 }
 
@@ -170,15 +345,11 @@ async fn list_keyvaults(subscription_id: Option<String>) -> Result<serde_json::V
 async fn list_keyvault_secrets(
     vault_name: String,
     subscription_id: Option<String>,
-) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
-
-    manager
-        .call(
-            "list_keyvault_secrets",
-            serde_json::json!({ "vaultName": vault_name, "subscriptionId": subscription_id }),
-        )
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+    backend::get(None)
+        .list_keyvault_secrets(&vault_name, subscription_id.as_deref(), CallOptions::new(timeout_ms, request_id))
         .await
 }
 
@@ -187,20 +358,20 @@ async fn list_keyvault_secrets(
 async fn list_keyvault_certificates(
     vault_name: String,
     subscription_id: Option<String>,
-) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
-
-    manager
-        .call(
-            "list_keyvault_certificates",
-            serde_json::json!({ "vaultName": vault_name, "subscriptionId": subscription_id }),
-        )
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+    backend::get(None)
+        .list_keyvault_certificates(&vault_name, subscription_id.as_deref(), CallOptions::new(timeout_ms, request_id))
         .await
 }
 
-/// Acquire a user token via sidecar (opens system browser)
+/// Silent-only user token requests never wait on the browser, so they get a short deadline.
+const SILENT_USER_TOKEN_TIMEOUT_MS: u64 = 30_000;
+
+/// Acquire a user token via the app's token backend (the sidecar opens the system browser)
 #[tauri::command(rename_all = "camelCase")]
+#[allow(clippy::too_many_arguments)]
 async fn acquire_user_token(
     client_id: String,
     tenant_id: String,
@@ -208,68 +379,212 @@ async fn acquire_user_token(
     prompt: Option<String>,
     account_home_account_id: Option<String>,
     silent_only: Option<bool>,
-) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
-
-    manager
-        .call(
-            "acquire_user_token",
-            serde_json::json!({
-                "clientId": client_id,
-                "tenantId": tenant_id,
-                "scopes": scopes,
-                "prompt": prompt,
-                "accountHomeAccountId": account_home_account_id,
-                "silentOnly": silent_only
-            }),
-        )
+    backend: Option<BackendKind>,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<TokenResponse, CommandError> {
+    require_non_empty("clientId", &client_id)?;
+    require_non_empty("tenantId", &tenant_id)?;
+    if scopes.is_empty() {
+        return Err(CommandError::validation("scopes", "At least one scope is required"));
+    }
+
+    let timeout_ms = timeout_ms.or(silent_only
+        .unwrap_or(false)
+        .then_some(SILENT_USER_TOKEN_TIMEOUT_MS));
+
+    let request = UserTokenRequest {
+        client_id,
+        tenant_id,
+        scopes,
+        prompt,
+        account_home_account_id,
+        silent_only,
+    };
+    backend::get(backend)
+        .acquire_user_token(&request, CallOptions::new(timeout_ms, request_id))
         .await
 }
 
+/// Start a device code sign-in. Returns the user code right away; polling continues in the
+/// background and the token arrives in a `device-code-progress` event.
+#[tauri::command(rename_all = "camelCase")]
+async fn start_device_code(
+    client_id: String,
+    tenant_id: String,
+    scopes: Vec<String>,
+    backend: Option<BackendKind>,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+) -> Result<device_code::DeviceCodeInfo, CommandError> {
+    require_non_empty("clientId", &client_id)?;
+    require_non_empty("tenantId", &tenant_id)?;
+    if scopes.is_empty() {
+        return Err(CommandError::validation("scopes", "At least one scope is required"));
+    }
+    device_code::start(
+        backend.unwrap_or_else(backend::default_kind),
+        client_id,
+        tenant_id,
+        scopes,
+        CallOptions::new(timeout_ms, request_id),
+    )
+    .await
+}
+
+/// Stop polling a device code sign-in. Returns `false` if it already finished.
+#[tauri::command(rename_all = "camelCase")]
+async fn cancel_device_code(flow_id: String) -> Result<bool, CommandError> {
+    device_code::cancel(&flow_id).await
+}
+
 /// Get cached user accounts for a client (desktop auth restoration)
 #[tauri::command(rename_all = "camelCase")]
-async fn get_user_accounts(client_id: String, tenant_id: String) -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
-
-    manager
-        .call(
-            "get_user_accounts",
-            serde_json::json!({
-                "clientId": client_id,
-                "tenantId": tenant_id,
-            }),
-        )
+async fn get_user_accounts(
+    client_id: String,
+    tenant_id: String,
+    backend: Option<BackendKind>,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<AccountInfo>, CommandError> {
+    backend::get(backend)
+        .get_user_accounts(&client_id, &tenant_id, CallOptions::new(timeout_ms, None))
         .await
 }
 
 /// Clear cached user tokens/accounts for a client (logout)
 #[tauri::command(rename_all = "camelCase")]
-async fn clear_user_cache(client_id: String, tenant_id: String) -> Result<(), String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
+async fn clear_user_cache(
+    client_id: String,
+    tenant_id: String,
+    backend: Option<BackendKind>,
+) -> Result<(), CommandError> {
+    backend::get(backend).clear_user_cache(&client_id, &tenant_id).await
+}
 
-    manager
-        .call(
-            "clear_user_cache",
-            serde_json::json!({
-                "clientId": client_id,
-                "tenantId": tenant_id,
-            }),
-        )
-        .await?;
+/// Get desktop auth storage status (encrypted token cache availability)
+#[tauri::command]
+async fn get_auth_storage_status() -> Result<AuthStorageStatus, CommandError> {
+    backend::get(None).get_auth_storage_status().await
+}
 
-    Ok(())
+/// Decode a JWT (without verifying it) into its header, standard claims and token version
+#[tauri::command]
+fn decode_token(token: String) -> Result<jwt::DecodedToken, CommandError> {
+    Ok(jwt::decode(&token)?)
 }
 
-/// Get desktop auth storage status (encrypted token cache availability)
+/// Verify a token's signature and claims against a JWKS (pasted, file or tenant keys)
 #[tauri::command]
-async fn get_auth_storage_status() -> Result<serde_json::Value, String> {
-    let sidecar = get_sidecar().await;
-    let mut manager = sidecar.lock().await;
+async fn verify_token(
+    app: tauri::AppHandle,
+    token: String,
+    jwks: verify::JwksSource,
+    options: Option<verify::VerifyOptions>,
+) -> Result<verify::VerificationResult, CommandError> {
+    let cache_dir = app.path().app_data_dir().ok();
+    verify::verify(&token, &jwks, &options.unwrap_or_default(), cache_dir.as_deref()).await
+}
+
+fn app_data_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, CommandError> {
+    app.path().app_data_dir().map_err(|e| CommandError::Io {
+        path: String::new(),
+        message: format!("Failed to resolve app data directory: {}", e),
+    })
+}
+
+/// Mirror the app registry to the data directory so the headless CLI can use saved apps
+#[tauri::command]
+fn sync_saved_apps(app: tauri::AppHandle, apps: Vec<saved_apps::SavedApp>) -> Result<(), CommandError> {
+    saved_apps::save(&app_data_dir(&app)?, &apps)
+}
+
+/// Start the loopback token server (returns the running server if already started)
+#[tauri::command]
+async fn start_token_server(
+    app: tauri::AppHandle,
+    port: Option<u16>,
+) -> Result<token_server::TokenServerStatus, CommandError> {
+    token_server::start(&app, port).await
+}
+
+/// Stop the loopback token server. Returns `false` if it wasn't running.
+#[tauri::command]
+fn stop_token_server() -> bool {
+    token_server::stop()
+}
+
+/// Get the loopback token server's port and session secret
+#[tauri::command]
+fn get_token_server_status() -> token_server::TokenServerStatus {
+    token_server::status()
+}
+
+/// Get the rules mapping Azure DevOps repo URLs to saved apps for the git credential helper
+#[tauri::command]
+fn get_git_credential_rules(app: tauri::AppHandle) -> Result<Vec<git_credential::GitCredentialRule>, CommandError> {
+    git_credential::load_rules(&app_data_dir(&app)?)
+}
+
+/// Replace the git credential helper's rules
+#[tauri::command]
+fn save_git_credential_rules(
+    app: tauri::AppHandle,
+    rules: Vec<git_credential::GitCredentialRule>,
+) -> Result<(), CommandError> {
+    git_credential::save_rules(&app_data_dir(&app)?, &rules)
+}
 
-    manager.call("get_auth_storage_status", serde_json::json!({})).await
+/// Start an IMDS or App Service managed identity endpoint backed by a saved app
+#[tauri::command]
+async fn start_managed_identity_endpoint(
+    app: tauri::AppHandle,
+    config: managed_identity::ManagedIdentityEndpointConfig,
+) -> Result<managed_identity::ManagedIdentityEndpointStatus, CommandError> {
+    require_non_empty("app", &config.app)?;
+    managed_identity::start(&app, config).await
+}
+
+/// Stop a managed identity endpoint. Returns `false` if it wasn't running.
+#[tauri::command]
+fn stop_managed_identity_endpoint(kind: managed_identity::EndpointKind) -> bool {
+    managed_identity::stop(kind)
+}
+
+/// Get the running managed identity endpoints and the environment to point SDKs at them
+#[tauri::command]
+fn get_managed_identity_endpoints() -> Vec<managed_identity::ManagedIdentityEndpointStatus> {
+    managed_identity::status()
+}
+
+/// Get the managed identity endpoints' access log, newest first
+#[tauri::command]
+fn get_managed_identity_log() -> Vec<managed_identity::ManagedIdentityAccess> {
+    managed_identity::access_log()
+}
+
+/// Deep links that arrived before the UI was listening for `deep-link` events
+#[tauri::command]
+fn get_pending_deep_links() -> Vec<deep_link::PendingDeepLink> {
+    deep_link::pending()
+}
+
+/// Take a queued deep link. Returns its action if `accept` is set and it hasn't been taken
+/// already; token-issuing actions must only be accepted after the user confirms.
+#[tauri::command]
+fn resolve_deep_link(id: String, accept: bool) -> Option<deep_link::DeepLinkAction> {
+    deep_link::resolve(&id, accept)
+}
+
+/// Launch commands (`--issue-favorite`, `--decode`) the UI hasn't handled yet
+#[tauri::command]
+fn get_pending_launch_commands() -> Vec<launch_args::LaunchRequest> {
+    launch_args::pending()
+}
+
+/// Report the outcome of a launch command; it is passed to a `--wait` caller if there is one.
+#[tauri::command]
+fn complete_launch_command(id: String, ok: bool, message: String) -> bool {
+    launch_args::complete(&id, launch_args::LaunchResult { ok, message })
 }
 
 /// Exit the desktop application.
@@ -278,6 +593,13 @@ fn exit_app(app: tauri::AppHandle) {
     app.exit(0);
 }
 
+/// Run a headless CLI command if one was given on the command line, or hand a `--wait`
+/// launch command to the running window. Returns the process exit code, or `None` to
+/// launch the GUI.
+pub fn run_cli() -> Option<i32> {
+    cli::run().or_else(launch_args::forward)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let mut builder = tauri::Builder::default();
@@ -286,13 +608,15 @@ pub fn run() {
     // This ensures only one app instance runs and deep-links are forwarded to it.
     #[cfg(desktop)]
     {
-        builder = builder.plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
             // Focus the main window when a second instance is launched
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.set_focus();
             }
+            // Run launch commands such as `--issue-favorite <name>` in this window.
             // Note: Deep-link events are automatically emitted by the deep-link plugin
             // when the single-instance plugin has the "deep-link" feature enabled
+            launch_args::handle_second_instance(app, &argv, &cwd);
         }));
     }
 
@@ -305,12 +629,43 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             acquire_app_token,
             acquire_user_token,
+            acquire_obo_token,
+            start_device_code,
+            cancel_device_code,
             get_user_accounts,
             clear_user_cache,
             get_auth_storage_status,
             check_sidecar_health,
+            restart_sidecar,
+            cancel_request,
+            get_sidecar_logs,
+            list_node_runtimes,
+            get_app_settings,
+            save_app_settings,
+            decode_token,
+            verify_token,
+            sync_saved_apps,
+            start_token_server,
+            stop_token_server,
+            get_token_server_status,
+            get_git_credential_rules,
+            save_git_credential_rules,
+            start_managed_identity_endpoint,
+            stop_managed_identity_endpoint,
+            get_managed_identity_endpoints,
+            get_managed_identity_log,
+            get_pending_deep_links,
+            resolve_deep_link,
+            get_pending_launch_commands,
+            complete_launch_command,
             exit_app,
             validate_keyvault,
+            validate_pfx_credential,
+            validate_pem_credential,
+            validate_keyring_secret,
+            validate_federated_credential,
+            store_app_secret,
+            delete_app_secret,
             get_credential_status,
             list_azure_subscriptions,
             list_azure_apps,
@@ -328,29 +683,35 @@ pub fn run() {
                 )?;
             }
 
-            // Register deep link handler for auth callbacks
+            // Route deep links by action (sign-in redirects, favorites, decode, app import)
             #[cfg(desktop)]
             {
                 use tauri_plugin_deep_link::DeepLinkExt;
-                
+
                 let handle = app.handle().clone();
-                app.deep_link().on_open_url(move |event| {
-                    let urls = event.urls();
+                // The link the app was launched with (Windows/Linux pass it on the command line)
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
                     for url in urls {
-                        let mut sanitized = url.clone();
-                        sanitized.set_query(None);
-                        sanitized.set_fragment(None);
-                        log::info!("Deep link received: {}", sanitized);
-                        // Emit event to frontend to handle auth callback
-                        if url.scheme() == "entra-token-studio" {
-                            let _ = handle.emit("auth-callback", url.as_str());
-                        }
+                        deep_link::handle(app.handle(), &url);
+                    }
+                }
+                app.deep_link().on_open_url(move |event| {
+                    for url in event.urls() {
+                        deep_link::handle(&handle, &url);
                     }
                 });
+
+                // Launch commands, and `--wait` callers from later launches
+                launch_args::handle_launch(app.handle());
+                launch_args::start_listener(app.handle());
             }
 
-            // Initialize sidecar on startup
-            sidecar::init_sidecar_env(&app.handle());
+            // Initialize sidecar on startup (environment always, so apps can still opt into it)
+            sidecar::init_sidecar_env(app.handle());
+            if backend::default_kind() == BackendKind::Demo {
+                log::info!("Using the {} token backend", backend::default_kind().as_str());
+                return Ok(());
+            }
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 // Initialize sidecar
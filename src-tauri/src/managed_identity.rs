@@ -0,0 +1,711 @@
+//! Managed identity endpoint emulator for local containers and services.
+//!
+//! Serves app tokens for one saved app in the shape of the two managed identity endpoints
+//! Azure SDKs know, so `ManagedIdentityCredential` works unchanged on a dev machine:
+//!
+//! - IMDS: `GET /metadata/identity/oauth2/token?resource=<uri>` with `Metadata: true`
+//! - App Service: `GET /msi/token?resource=<uri>` with `X-IDENTITY-HEADER: <secret>`,
+//!   where the endpoint and secret are what `IDENTITY_ENDPOINT` and `IDENTITY_HEADER` hold
+//!
+//! Tokens come from the same `acquire_app_token` path as the UI. Listeners bind to
+//! 127.0.0.1 only and, like the token server, reject browser requests (any `Origin`) and
+//! any `Host` but loopback or `host.docker.internal`, which is how containers reach the
+//! host, so web pages can't reach them through DNS rebinding. Inside a container
+//! 127.0.0.1 is the container itself, so each endpoint also advertises its environment
+//! variables with `host.docker.internal` in place of 127.0.0.1. Those only work with
+//! Docker Desktop, which forwards that name to the host's loopback; on Linux Docker
+//! Engine it is the bridge gateway, where nothing listens. Every request is kept in an
+//! in-memory access log and emitted as a `managed-identity-request` event.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::header::ORIGIN;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+use crate::error::CommandError;
+use crate::jwt::StandardClaims;
+use crate::models::TokenResponse;
+use crate::saved_apps::{self, SavedApp};
+use crate::sidecar;
+use crate::token_server::{constant_time_eq, is_allowed_host, json_response, now_millis, resource_scope, status_for};
+
+pub const DEFAULT_IMDS_PORT: u16 = 47811;
+pub const DEFAULT_APP_SERVICE_PORT: u16 = 47812;
+const IMDS_TOKEN_PATH: &str = "/metadata/identity/oauth2/token";
+const APP_SERVICE_TOKEN_PATH: &str = "/msi/token";
+const IDENTITY_HEADER: &str = "x-identity-header";
+const HEADER_SECRET_PURPOSE: &str = "managed-identity-header";
+/// Requests kept in the access log, oldest dropped first
+const ACCESS_LOG_CAPACITY: usize = 200;
+/// Lifetime reported for tokens whose expiry can't be read
+const FALLBACK_LIFETIME_SECS: u64 = 3600;
+/// How local containers reach the host
+const CONTAINER_HOST: &str = "host.docker.internal";
+/// `Host` values accepted: loopback, or the host as seen from a container
+const ALLOWED_HOSTS: &[&str] = &["127.0.0.1", "localhost", "[::1]", CONTAINER_HOST];
+
+/// Which managed identity endpoint to emulate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EndpointKind {
+    Imds,
+    AppService,
+}
+
+impl EndpointKind {
+    fn token_path(self) -> &'static str {
+        match self {
+            Self::Imds => IMDS_TOKEN_PATH,
+            Self::AppService => APP_SERVICE_TOKEN_PATH,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Imds => "IMDS",
+            Self::AppService => "App Service",
+        }
+    }
+}
+
+/// What to start an endpoint with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedIdentityEndpointConfig {
+    pub kind: EndpointKind,
+    /// Saved app (id, name or client id) whose app tokens are served
+    pub app: String,
+    pub port: Option<u16>,
+    /// Scopes to request per resource URI; other resources get `<resource>/.default`
+    #[serde(default)]
+    pub scope_map: HashMap<String, Vec<String>>,
+}
+
+/// A running endpoint, as reported to the UI
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedIdentityEndpointStatus {
+    pub kind: EndpointKind,
+    pub app_id: String,
+    pub app_name: String,
+    pub port: u16,
+    /// Full token endpoint URL
+    pub url: String,
+    /// Environment variables that point Azure SDKs on this machine at this endpoint
+    pub env: BTreeMap<String, String>,
+    /// The same variables for a Docker Desktop container, which reaches the endpoint through
+    /// `host.docker.internal` (not reachable from Linux Docker Engine, see the module docs)
+    pub container_env: BTreeMap<String, String>,
+    pub scope_map: HashMap<String, Vec<String>>,
+}
+
+/// One request to an endpoint; emitted as `managed-identity-request` and kept in the access log
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManagedIdentityAccess {
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    pub kind: EndpointKind,
+    pub app_name: String,
+    pub client: String,
+    pub path: String,
+    pub resource: Option<String>,
+    pub scopes: Vec<String>,
+    pub status: u16,
+    pub error: Option<String>,
+}
+
+struct RunningEndpoint {
+    status: ManagedIdentityEndpointStatus,
+    shutdown: oneshot::Sender<()>,
+}
+
+struct Shared {
+    app_handle: tauri::AppHandle,
+    kind: EndpointKind,
+    app: SavedApp,
+    /// Expected `X-IDENTITY-HEADER` (App Service only)
+    header_secret: Option<String>,
+    scope_map: HashMap<String, Vec<String>>,
+}
+
+lazy_static::lazy_static! {
+    static ref ENDPOINTS: Mutex<HashMap<EndpointKind, RunningEndpoint>> = Mutex::new(HashMap::new());
+    static ref ACCESS_LOG: Mutex<VecDeque<ManagedIdentityAccess>> = Mutex::new(VecDeque::new());
+}
+
+/// Token response in the managed identity wire format (all numbers are strings)
+#[derive(Serialize)]
+struct ManagedIdentityToken<'a> {
+    access_token: &'a str,
+    client_id: &'a str,
+    expires_in: String,
+    expires_on: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ext_expires_in: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_before: Option<String>,
+    resource: &'a str,
+    token_type: &'a str,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    error_description: &'a str,
+}
+
+type HttpResponse = Response<Full<Bytes>>;
+
+/// Running endpoints
+pub fn status() -> Vec<ManagedIdentityEndpointStatus> {
+    ENDPOINTS.lock().unwrap().values().map(|e| e.status.clone()).collect()
+}
+
+/// Requests served so far, newest first
+pub fn access_log() -> Vec<ManagedIdentityAccess> {
+    ACCESS_LOG.lock().unwrap().iter().rev().cloned().collect()
+}
+
+/// Start an endpoint backed by a saved app.
+pub async fn start(
+    app_handle: &tauri::AppHandle,
+    config: ManagedIdentityEndpointConfig,
+) -> Result<ManagedIdentityEndpointStatus, CommandError> {
+    let kind = config.kind;
+    if ENDPOINTS.lock().unwrap().contains_key(&kind) {
+        return Err(CommandError::validation(
+            "kind",
+            format!("The {} endpoint is already running; stop it first", kind.label()),
+        ));
+    }
+
+    let data_dir = app_handle.path().app_data_dir().map_err(|e| CommandError::Io {
+        path: String::new(),
+        message: format!("Failed to resolve app data directory: {}", e),
+    })?;
+    let apps = saved_apps::load(&data_dir)?;
+    let app = saved_apps::find(&apps, &config.app)
+        .map_err(|message| CommandError::validation("app", message))?
+        .clone();
+
+    let port = config.port.unwrap_or(match kind {
+        EndpointKind::Imds => DEFAULT_IMDS_PORT,
+        EndpointKind::AppService => DEFAULT_APP_SERVICE_PORT,
+    });
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let listener = TcpListener::bind(addr).await.map_err(|e| CommandError::Io {
+        path: addr.to_string(),
+        message: format!("Failed to listen on {}: {}", addr, e),
+    })?;
+    let port = listener.local_addr().map(|a| a.port()).unwrap_or(port);
+
+    let header_secret = match kind {
+        EndpointKind::Imds => None,
+        EndpointKind::AppService => Some(sidecar::create_session_secret(
+            &app_handle.config().identifier,
+            HEADER_SECRET_PURPOSE,
+        )?),
+    };
+
+    let status = ManagedIdentityEndpointStatus {
+        kind,
+        app_id: app.id.clone(),
+        app_name: app.name.clone(),
+        port,
+        url: format!("http://127.0.0.1:{}{}", port, kind.token_path()),
+        env: endpoint_env(kind, "127.0.0.1", port, header_secret.as_deref()),
+        container_env: endpoint_env(kind, CONTAINER_HOST, port, header_secret.as_deref()),
+        scope_map: config.scope_map.clone(),
+    };
+    let shared = Arc::new(Shared {
+        app_handle: app_handle.clone(),
+        kind,
+        app,
+        header_secret,
+        scope_map: config.scope_map,
+    });
+    let (shutdown, shutdown_rx) = oneshot::channel();
+
+    {
+        let mut endpoints = ENDPOINTS.lock().unwrap();
+        if let Some(running) = endpoints.get(&kind) {
+            return Ok(running.status.clone());
+        }
+        endpoints.insert(
+            kind,
+            RunningEndpoint {
+                status: status.clone(),
+                shutdown,
+            },
+        );
+    }
+
+    log::info!("{} managed identity endpoint listening on {}", kind.label(), status.url);
+    tauri::async_runtime::spawn(serve(listener, shared, shutdown_rx));
+    Ok(status)
+}
+
+/// Environment variables that point Azure SDKs at an endpoint reached as `host`
+fn endpoint_env(kind: EndpointKind, host: &str, port: u16, header_secret: Option<&str>) -> BTreeMap<String, String> {
+    let base_url = format!("http://{}:{}", host, port);
+    let mut env = BTreeMap::new();
+    match kind {
+        // Honored by the Azure Identity SDKs in place of 169.254.169.254.
+        EndpointKind::Imds => {
+            env.insert("AZURE_POD_IDENTITY_AUTHORITY_HOST".to_string(), base_url);
+        }
+        EndpointKind::AppService => {
+            env.insert("IDENTITY_ENDPOINT".to_string(), format!("{}{}", base_url, APP_SERVICE_TOKEN_PATH));
+            env.insert("IDENTITY_HEADER".to_string(), header_secret.unwrap_or_default().to_string());
+        }
+    }
+    env
+}
+
+/// Stop an endpoint. Returns `false` if it wasn't running.
+pub fn stop(kind: EndpointKind) -> bool {
+    match ENDPOINTS.lock().unwrap().remove(&kind) {
+        Some(endpoint) => {
+            let _ = endpoint.shutdown.send(());
+            log::info!("{} managed identity endpoint on port {} stopped", kind.label(), endpoint.status.port);
+            true
+        }
+        None => false,
+    }
+}
+
+async fn serve(listener: TcpListener, shared: Arc<Shared>, mut shutdown: oneshot::Receiver<()>) {
+    loop {
+        let (stream, peer) = tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::warn!("Managed identity endpoint failed to accept a connection: {}", e);
+                    continue;
+                }
+            },
+        };
+
+        let shared = shared.clone();
+        tauri::async_runtime::spawn(async move {
+            let service = service_fn(move |request| {
+                let shared = shared.clone();
+                async move { Ok::<_, Infallible>(handle(request, peer, &shared).await) }
+            });
+            if let Err(e) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                log::debug!("Managed identity connection from {} ended with error: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle(request: Request<Incoming>, peer: SocketAddr, shared: &Shared) -> HttpResponse {
+    let query: Vec<(String, String)> = request
+        .uri()
+        .query()
+        .map(|q| url::form_urlencoded::parse(q.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    let param = |name: &str| query.iter().rev().find(|(k, _)| k == name).map(|(_, v)| v.trim());
+
+    let mut access = ManagedIdentityAccess {
+        timestamp: now_millis(),
+        kind: shared.kind,
+        app_name: shared.app.name.clone(),
+        client: peer.to_string(),
+        path: request.uri().path().to_string(),
+        resource: param("resource").map(str::to_string),
+        scopes: Vec::new(),
+        status: StatusCode::OK.as_u16(),
+        error: None,
+    };
+
+    let response = match check_request(&request, shared.kind, shared.header_secret.as_deref()) {
+        Ok(()) => match param("resource").filter(|r| !r.is_empty()) {
+            Some(resource) => serve_token(shared, resource, param("client_id"), &mut access).await,
+            None => Err((StatusCode::BAD_REQUEST, "Required query parameter 'resource' is missing".to_string())),
+        },
+        Err(rejection) => Err(rejection),
+    };
+    let response = response.unwrap_or_else(|(status, message)| {
+        access.status = status.as_u16();
+        access.error = Some(message.clone());
+        error_response(status, &message)
+    });
+
+    record(&shared.app_handle, access);
+    response
+}
+
+/// Route and authenticate a request the way the real endpoint would.
+/// `header_secret` is the expected `X-IDENTITY-HEADER` (App Service only).
+fn check_request<B>(
+    request: &Request<B>,
+    kind: EndpointKind,
+    header_secret: Option<&str>,
+) -> Result<(), (StatusCode, String)> {
+    if request.headers().contains_key(ORIGIN) {
+        return Err((StatusCode::FORBIDDEN, "Browser requests are not allowed".to_string()));
+    }
+    if !is_allowed_host(request.headers(), ALLOWED_HOSTS) {
+        return Err((StatusCode::FORBIDDEN, "Only local clients may use this endpoint".to_string()));
+    }
+    if request.uri().path() != kind.token_path() {
+        return Err((StatusCode::NOT_FOUND, format!("Unknown path; use GET {}", kind.token_path())));
+    }
+    if request.method() != Method::GET {
+        return Err((StatusCode::METHOD_NOT_ALLOWED, "Use GET".to_string()));
+    }
+
+    let header = |name: &str| request.headers().get(name).and_then(|h| h.to_str().ok()).map(str::trim);
+    match header_secret {
+        // IMDS requires `Metadata: true` so it can't be reached through a simple redirect.
+        None if !header("metadata").is_some_and(|v| v.eq_ignore_ascii_case("true")) => {
+            Err((StatusCode::BAD_REQUEST, "Required metadata header not specified".to_string()))
+        }
+        Some(secret) if !constant_time_eq(header(IDENTITY_HEADER).unwrap_or_default().as_bytes(), secret.as_bytes()) => {
+            Err((StatusCode::UNAUTHORIZED, "Missing or invalid X-IDENTITY-HEADER".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Scopes for a requested resource: the scope map's entry for it, else `<resource>/.default`.
+/// The resource is matched as given, so `https://management.azure.com/` and
+/// `https://management.azure.com` are different audiences here just as they are to Entra.
+fn scopes_for(scope_map: &HashMap<String, Vec<String>>, resource: &str) -> Vec<String> {
+    scope_map
+        .get(resource)
+        .or_else(|| {
+            scope_map
+                .iter()
+                .find(|(mapped, _)| mapped.eq_ignore_ascii_case(resource))
+                .map(|(_, scopes)| scopes)
+        })
+        .cloned()
+        .unwrap_or_else(|| vec![resource_scope(resource)])
+}
+
+async fn serve_token(
+    shared: &Shared,
+    resource: &str,
+    client_id: Option<&str>,
+    access: &mut ManagedIdentityAccess,
+) -> Result<HttpResponse, (StatusCode, String)> {
+    // A user-assigned identity request for some other client id has no match here.
+    if let Some(client_id) = client_id.filter(|id| !id.is_empty()) {
+        if !client_id.eq_ignore_ascii_case(&shared.app.client_id) {
+            return Err((StatusCode::BAD_REQUEST, "Identity not found".to_string()));
+        }
+    }
+
+    let scopes = scopes_for(&shared.scope_map, resource);
+    access.scopes = scopes.clone();
+    let token = crate::acquire_app_token(shared.app.token_config(), scopes, None, None)
+        .await
+        .map_err(|e| (status_for(&e), e.to_string()))?;
+
+    let claims = crate::jwt::decode(&token.access_token).ok().map(|t| t.claims);
+    Ok(json_response(
+        StatusCode::OK,
+        &token_body(
+            shared.kind,
+            &token,
+            &shared.app.client_id,
+            resource,
+            claims.as_ref(),
+            now_millis() / 1000,
+        ),
+    ))
+}
+
+/// The token response body `kind`'s real endpoint would send at `now` (Unix seconds)
+fn token_body<'a>(
+    kind: EndpointKind,
+    token: &'a TokenResponse,
+    client_id: &'a str,
+    resource: &'a str,
+    claims: Option<&StandardClaims>,
+    now: u64,
+) -> ManagedIdentityToken<'a> {
+    let expires_on = claims
+        .and_then(|c| c.exp)
+        .map(|exp| exp.max(0) as u64)
+        .unwrap_or(now + FALLBACK_LIFETIME_SECS);
+    let expires_in = expires_on.saturating_sub(now).to_string();
+    let imds = kind == EndpointKind::Imds;
+
+    ManagedIdentityToken {
+        access_token: &token.access_token,
+        client_id,
+        ext_expires_in: imds.then(|| expires_in.clone()),
+        expires_in,
+        expires_on: expires_on.to_string(),
+        not_before: imds.then(|| claims.and_then(|c| c.nbf).map(|nbf| nbf.to_string())).flatten(),
+        resource,
+        token_type: &token.token_type,
+    }
+}
+
+fn record(app_handle: &tauri::AppHandle, access: ManagedIdentityAccess) {
+    log::info!(
+        "{} managed identity request from {} for {}: {}",
+        access.kind.label(),
+        access.client,
+        access.resource.as_deref().unwrap_or("-"),
+        access.status
+    );
+    if let Err(e) = app_handle.emit("managed-identity-request", &access) {
+        log::warn!("Failed to emit managed-identity-request event: {}", e);
+    }
+    let mut log = ACCESS_LOG.lock().unwrap();
+    if log.len() == ACCESS_LOG_CAPACITY {
+        log.pop_front();
+    }
+    log.push_back(access);
+}
+
+fn error_response(status: StatusCode, message: &str) -> HttpResponse {
+    let error = if status.is_client_error() { "invalid_request" } else { "service_unavailable" };
+    json_response(
+        status,
+        &ErrorBody {
+            error,
+            error_description: message,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HOST;
+    use serde_json::json;
+
+    use super::*;
+
+    const SECRET: &str = "identity-header-secret";
+    const CLIENT_ID: &str = "7a1c2b3d-4e5f-4a6b-8c7d-9e0f1a2b3c4d";
+    const NOW: u64 = 1_700_000_000;
+
+    fn request(path: &str, headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder().uri(path).header(HOST, "127.0.0.1:47811");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn imds(request: &Request<()>) -> Result<(), (StatusCode, String)> {
+        check_request(request, EndpointKind::Imds, None)
+    }
+
+    fn app_service(request: &Request<()>) -> Result<(), (StatusCode, String)> {
+        check_request(request, EndpointKind::AppService, Some(SECRET))
+    }
+
+    fn status_of(result: Result<(), (StatusCode, String)>) -> StatusCode {
+        result.expect_err("request was accepted").0
+    }
+
+    fn token() -> TokenResponse {
+        TokenResponse {
+            access_token: "eyJ0eXAiOiJKV1QifQ.e30.sig".to_string(),
+            token_type: "Bearer".to_string(),
+            expires_on: None,
+            scopes: Vec::new(),
+            account: None,
+        }
+    }
+
+    #[test]
+    fn imds_requires_the_metadata_header() {
+        assert!(imds(&request(IMDS_TOKEN_PATH, &[("Metadata", "true")])).is_ok());
+        assert!(imds(&request(IMDS_TOKEN_PATH, &[("Metadata", "True")])).is_ok());
+        assert_eq!(status_of(imds(&request(IMDS_TOKEN_PATH, &[]))), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            status_of(imds(&request(IMDS_TOKEN_PATH, &[("Metadata", "false")]))),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn app_service_requires_the_identity_header() {
+        assert!(app_service(&request(APP_SERVICE_TOKEN_PATH, &[("X-IDENTITY-HEADER", SECRET)])).is_ok());
+        assert_eq!(
+            status_of(app_service(&request(APP_SERVICE_TOKEN_PATH, &[]))),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status_of(app_service(&request(APP_SERVICE_TOKEN_PATH, &[("X-IDENTITY-HEADER", "wrong")]))),
+            StatusCode::UNAUTHORIZED
+        );
+        // `Metadata: true` is the IMDS header; it doesn't stand in for the secret.
+        assert_eq!(
+            status_of(app_service(&request(APP_SERVICE_TOKEN_PATH, &[("Metadata", "true")]))),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[test]
+    fn browser_requests_are_rejected() {
+        let request = request(IMDS_TOKEN_PATH, &[("Metadata", "true"), ("Origin", "http://127.0.0.1:47811")]);
+        assert_eq!(status_of(imds(&request)), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn only_local_hosts_are_accepted() {
+        for host in ["127.0.0.1", "localhost:47811", "[::1]:47811", "host.docker.internal:47811"] {
+            let request = Request::builder()
+                .uri(IMDS_TOKEN_PATH)
+                .header(HOST, host)
+                .header("Metadata", "true")
+                .body(())
+                .unwrap();
+            assert!(imds(&request).is_ok(), "{} was rejected", host);
+        }
+
+        for host in ["attacker.example:47811", "attacker.example", "169.254.169.254"] {
+            let request = Request::builder()
+                .uri(IMDS_TOKEN_PATH)
+                .header(HOST, host)
+                .header("Metadata", "true")
+                .body(())
+                .unwrap();
+            assert_eq!(status_of(imds(&request)), StatusCode::FORBIDDEN, "{} was accepted", host);
+        }
+    }
+
+    #[test]
+    fn wrong_path_or_method_is_rejected() {
+        assert_eq!(
+            status_of(imds(&request(APP_SERVICE_TOKEN_PATH, &[("Metadata", "true")]))),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            status_of(app_service(&request(IMDS_TOKEN_PATH, &[("X-IDENTITY-HEADER", SECRET)]))),
+            StatusCode::NOT_FOUND
+        );
+
+        let post = Request::builder()
+            .method(Method::POST)
+            .uri(IMDS_TOKEN_PATH)
+            .header(HOST, "127.0.0.1:47811")
+            .header("Metadata", "true")
+            .body(())
+            .unwrap();
+        assert_eq!(status_of(imds(&post)), StatusCode::METHOD_NOT_ALLOWED);
+    }
+
+    #[test]
+    fn scope_map_matches_the_resource_as_given() {
+        let scope_map = HashMap::from([
+            ("api://my-api".to_string(), vec!["api://my-api/read".to_string()]),
+            (
+                "https://management.azure.com/".to_string(),
+                vec!["https://management.azure.com/user_impersonation".to_string()],
+            ),
+        ]);
+
+        assert_eq!(scopes_for(&scope_map, "api://my-api"), ["api://my-api/read"]);
+        assert_eq!(scopes_for(&scope_map, "API://My-Api"), ["api://my-api/read"]);
+        assert_eq!(
+            scopes_for(&scope_map, "https://management.azure.com/"),
+            ["https://management.azure.com/user_impersonation"]
+        );
+
+        // Unmapped resources keep their trailing slash, as the real endpoints do.
+        assert_eq!(scopes_for(&scope_map, "api://my-api/"), ["api://my-api//.default"]);
+        assert_eq!(
+            scopes_for(&scope_map, "https://management.azure.com"),
+            ["https://management.azure.com/.default"]
+        );
+        assert_eq!(
+            scopes_for(&HashMap::new(), "https://vault.azure.net/"),
+            ["https://vault.azure.net//.default"]
+        );
+    }
+
+    #[test]
+    fn imds_body_has_string_numbers_and_extended_fields() {
+        let token = token();
+        let claims = StandardClaims {
+            exp: Some(NOW as i64 + 3599),
+            nbf: Some(NOW as i64 - 300),
+            ..Default::default()
+        };
+        let body = token_body(
+            EndpointKind::Imds,
+            &token,
+            CLIENT_ID,
+            "https://management.azure.com/",
+            Some(&claims),
+            NOW,
+        );
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            json!({
+                "access_token": token.access_token,
+                "client_id": CLIENT_ID,
+                "expires_in": "3599",
+                "expires_on": (NOW + 3599).to_string(),
+                "ext_expires_in": "3599",
+                "not_before": (NOW - 300).to_string(),
+                "resource": "https://management.azure.com/",
+                "token_type": "Bearer",
+            })
+        );
+    }
+
+    #[test]
+    fn app_service_body_omits_imds_only_fields() {
+        let token = token();
+        let claims = StandardClaims {
+            exp: Some(NOW as i64 + 600),
+            nbf: Some(NOW as i64 - 300),
+            ..Default::default()
+        };
+        let body = token_body(EndpointKind::AppService, &token, CLIENT_ID, "api://my-api", Some(&claims), NOW);
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            json!({
+                "access_token": token.access_token,
+                "client_id": CLIENT_ID,
+                "expires_in": "600",
+                "expires_on": (NOW + 600).to_string(),
+                "resource": "api://my-api",
+                "token_type": "Bearer",
+            })
+        );
+    }
+
+    #[test]
+    fn body_falls_back_to_a_default_lifetime() {
+        let token = token();
+        let body = token_body(EndpointKind::AppService, &token, CLIENT_ID, "api://my-api", None, NOW);
+        assert_eq!(body.expires_in, FALLBACK_LIFETIME_SECS.to_string());
+        assert_eq!(body.expires_on, (NOW + FALLBACK_LIFETIME_SECS).to_string());
+    }
+
+    #[test]
+    fn container_env_points_at_the_docker_host() {
+        let env = endpoint_env(EndpointKind::Imds, CONTAINER_HOST, 47811, None);
+        assert_eq!(env["AZURE_POD_IDENTITY_AUTHORITY_HOST"], "http://host.docker.internal:47811");
+
+        let env = endpoint_env(EndpointKind::AppService, CONTAINER_HOST, 47812, Some(SECRET));
+        assert_eq!(env["IDENTITY_ENDPOINT"], "http://host.docker.internal:47812/msi/token");
+        assert_eq!(env["IDENTITY_HEADER"], SECRET);
+    }
+}
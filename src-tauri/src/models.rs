@@ -0,0 +1,251 @@
+//! Typed results of sidecar-backed commands.
+//!
+//! These mirror the shapes produced by the Node handlers in `sidecar/src/handlers`.
+//! Deserializing into them (rather than passing `serde_json::Value` through) makes schema
+//! drift between Rust and the sidecar fail with a `contract` error instead of surfacing
+//! as `undefined` fields in the UI.
+
+use serde::{Deserialize, Serialize};
+
+/// Account attached to a user token (MSAL `AccountInfo` subset)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfo {
+    pub home_account_id: String,
+    pub environment: String,
+    pub tenant_id: String,
+    pub username: String,
+    pub name: Option<String>,
+}
+
+/// Token response (MSAL `AuthenticationResult` subset)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// ISO 8601 timestamp
+    pub expires_on: Option<String>,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Absent for app (client credentials) tokens
+    pub account: Option<AccountInfo>,
+}
+
+/// Validation result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationResult {
+    pub valid: bool,
+    pub credential_type: String,
+    pub message: Option<String>,
+}
+
+/// Credential status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialStatus {
+    pub available: bool,
+    pub message: String,
+}
+
+/// Encrypted token cache status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthStorageStatus {
+    pub encrypted: bool,
+    pub cache_dir: String,
+    /// `keyring`, `file`, `none` or `unknown`
+    pub key_source: String,
+}
+
+/// Result of an Azure CLI query. CLI failures (not signed in, missing extension, ...)
+/// are reported here rather than as command errors so the UI can show them inline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureCliResult<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+/// Azure subscription (`az account list`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureSubscription {
+    pub id: String,
+    pub name: String,
+    pub tenant_id: String,
+    pub is_default: Option<bool>,
+    pub state: Option<String>,
+}
+
+/// App registration (`az ad app list`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureAppRegistration {
+    pub app_id: String,
+    pub display_name: String,
+}
+
+/// Key Vault (`az keyvault list`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureKeyVault {
+    pub name: String,
+    /// `properties.vaultUri`; the CLI reports null for vaults it can't fully read
+    pub uri: Option<String>,
+    pub location: Option<String>,
+    pub resource_group: Option<String>,
+}
+
+/// Secret or certificate in a Key Vault (`az keyvault secret|certificate list`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureVaultCredential {
+    pub name: String,
+    pub enabled: Option<bool>,
+    /// ISO 8601 timestamp, if the credential expires
+    pub expires: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    //! Contract tests against results of the Node handlers, captured in
+    //! `tests/fixtures/sidecar/<method>.json`. Each fixture must deserialize into the type
+    //! the Rust side expects, and every field that type keeps must serialize back to the
+    //! value the sidecar sent.
+
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::device_code::DeviceCodeInfo;
+    use crate::sidecar::SidecarCapabilities;
+
+    macro_rules! fixture {
+        ($method:literal) => {
+            ($method, include_str!(concat!("../tests/fixtures/sidecar/", $method, ".json")))
+        };
+    }
+
+    /// `typed` holds no field the sidecar didn't send, and no value that differs from it.
+    /// Fields missing from the fixture may only come back as `null`.
+    fn assert_subset(method: &str, path: &str, typed: &Value, fixture: &Value) {
+        match (typed, fixture) {
+            (Value::Object(typed), Value::Object(fixture)) => {
+                for (key, value) in typed {
+                    let field = format!("{}.{}", path, key);
+                    match fixture.get(key) {
+                        Some(expected) => assert_subset(method, &field, value, expected),
+                        None => assert!(value.is_null(), "{}: {} is not in the sidecar result", method, field),
+                    }
+                }
+            }
+            (Value::Array(typed), Value::Array(fixture)) => {
+                assert_eq!(typed.len(), fixture.len(), "{}: {} length", method, path);
+                for (index, (value, expected)) in typed.iter().zip(fixture).enumerate() {
+                    assert_subset(method, &format!("{}[{}]", path, index), value, expected);
+                }
+            }
+            _ => assert_eq!(typed, fixture, "{}: {}", method, path),
+        }
+    }
+
+    fn round_trip<T: DeserializeOwned + Serialize>((method, json): (&str, &str)) -> T {
+        let fixture: Value = serde_json::from_str(json).unwrap_or_else(|e| panic!("{}: invalid fixture: {}", method, e));
+        let typed: T = serde_json::from_value(fixture.clone())
+            .unwrap_or_else(|e| panic!("{}: result doesn't match the Rust type: {}", method, e));
+        let serialized = serde_json::to_value(&typed).unwrap();
+        assert_subset(method, "$", &serialized, &fixture);
+        let again: T = serde_json::from_value(serialized.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&again).unwrap(), serialized, "{}: second round trip", method);
+        typed
+    }
+
+    #[test]
+    fn token_methods() {
+        let app: TokenResponse = round_trip(fixture!("acquire_app_token"));
+        assert!(app.account.is_none());
+        assert_eq!(app.expires_on.as_deref(), Some("2026-10-16T13:04:11.000Z"));
+
+        let obo: TokenResponse = round_trip(fixture!("acquire_obo_token"));
+        assert_eq!(obo.account.unwrap().username, "adele@contoso.example");
+
+        for method in [fixture!("acquire_user_token"), fixture!("redeem_auth_code")] {
+            let user: TokenResponse = round_trip(method);
+            assert_eq!(user.token_type, "Bearer");
+            assert_eq!(user.account.unwrap().name.as_deref(), Some("Adele Vance"));
+        }
+    }
+
+    #[test]
+    fn device_code_methods() {
+        let info: DeviceCodeInfo = round_trip(fixture!("start_device_code"));
+        assert_eq!(info.user_code, "F7K2LQ9XM");
+        assert_eq!((info.expires_in, info.interval), (900, 5));
+
+        let cancelled: bool = round_trip(fixture!("cancel_device_code"));
+        assert!(cancelled);
+    }
+
+    #[test]
+    fn account_and_cache_methods() {
+        round_trip::<()>(fixture!("clear_user_cache"));
+
+        let accounts: Vec<AccountInfo> = round_trip(fixture!("get_user_accounts"));
+        assert_eq!(accounts.len(), 2);
+        assert!(accounts[1].name.is_none());
+
+        let storage: AuthStorageStatus = round_trip(fixture!("get_auth_storage_status"));
+        assert_eq!(storage.key_source, "keyring");
+    }
+
+    #[test]
+    fn credential_methods() {
+        let validation: ValidationResult = round_trip(fixture!("validate_keyvault"));
+        assert!(!validation.valid);
+        assert_eq!(validation.credential_type, "certificate");
+
+        let status: CredentialStatus = round_trip(fixture!("get_credential_status"));
+        assert!(!status.available);
+    }
+
+    #[test]
+    fn azure_cli_methods() {
+        let subscriptions: AzureCliResult<Vec<AzureSubscription>> = round_trip(fixture!("list_azure_subscriptions"));
+        assert_eq!(subscriptions.data.unwrap()[0].is_default, Some(true));
+
+        let apps: AzureCliResult<Vec<AzureAppRegistration>> = round_trip(fixture!("list_azure_apps"));
+        assert_eq!(apps.data.unwrap()[1].display_name, "Contoso Build Agent");
+
+        let vaults: AzureCliResult<Vec<AzureKeyVault>> = round_trip(fixture!("list_keyvaults"));
+        let vaults = vaults.data.unwrap();
+        assert_eq!(vaults[0].uri.as_deref(), Some("https://contoso-prod-kv.vault.azure.net/"));
+        assert!(vaults[1].uri.is_none());
+
+        let secrets: AzureCliResult<Vec<AzureVaultCredential>> = round_trip(fixture!("list_keyvault_secrets"));
+        assert_eq!(secrets.data.unwrap()[1].expires, None);
+
+        let certificates: AzureCliResult<Vec<AzureVaultCredential>> =
+            round_trip(fixture!("list_keyvault_certificates"));
+        assert!(!certificates.success);
+        assert!(certificates.data.is_none());
+    }
+
+    #[test]
+    fn hello() {
+        let capabilities: SidecarCapabilities = round_trip(fixture!("hello"));
+        assert_eq!(capabilities.protocol_version, crate::sidecar::PROTOCOL_VERSION);
+        for method in crate::sidecar::REQUIRED_METHODS {
+            assert!(capabilities.methods.iter().any(|m| m == method), "hello fixture lacks {}", method);
+        }
+    }
+
+    /// Every method the host requires has a fixture above.
+    #[test]
+    fn every_required_method_has_a_fixture() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sidecar");
+        for method in crate::sidecar::REQUIRED_METHODS {
+            assert!(dir.join(format!("{}.json", method)).is_file(), "no fixture for {}", method);
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! Native token backend: app tokens and Key Vault checks in Rust, without Node.
+//!
+//! App tokens use the client credentials flow in [`crate::client_credentials`], with
+//! credentials read from Key Vault over REST or from a local [`crate::credentials`] source;
+//! on-behalf-of exchanges use the same credential. Everything else (interactive user
+//! sign-in, the account cache, Azure CLI listings) is still served by the sidecar.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+
+use crate::backend::{SidecarBackend, TokenBackend, UserTokenRequest};
+use crate::client_credentials::{self, CertificateCredential, ClientCredential};
+use crate::credentials;
+use crate::device_code::{DeviceCodeInfo, DeviceCodeRequest};
+use crate::error::CommandError;
+use crate::keyvault;
+use crate::models::{
+    AccountInfo, AuthStorageStatus, AzureAppRegistration, AzureCliResult, AzureKeyVault, AzureSubscription,
+    AzureVaultCredential, CredentialStatus, TokenResponse, ValidationResult,
+};
+use crate::sidecar::CallOptions;
+use crate::{AzureAppFilters, KeyVaultConfig, TokenAppConfig};
+
+/// Same default deadline the sidecar applies to `acquire_app_token` and `validate_keyvault`.
+const APP_TOKEN_TIMEOUT: Duration = Duration::from_secs(60);
+/// Cached tokens are refreshed this long before they expire.
+const TOKEN_REFRESH_MARGIN_SECS: u64 = 300;
+
+lazy_static::lazy_static! {
+    /// App tokens by app + credential + scopes, with their expiry (Unix seconds)
+    static ref APP_TOKENS: Mutex<HashMap<String, (TokenResponse, u64)>> = Mutex::new(HashMap::new());
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn cache_key(config: &TokenAppConfig, scopes: &[String]) -> String {
+    let credential = match &config.credential {
+        Some(source) => source.cache_id(),
+        None => {
+            let name = if config.key_vault.credential_type == "certificate" {
+                config.key_vault.cert_name.as_deref()
+            } else {
+                config.key_vault.secret_name.as_deref()
+            };
+            format!(
+                "{}:{}:{}",
+                config.key_vault.uri,
+                config.key_vault.credential_type,
+                name.unwrap_or_default()
+            )
+        }
+    };
+    let mut scopes = scopes.to_vec();
+    scopes.sort();
+    format!(
+        "{}|{}|{}|{}|{}",
+        config.authority_host.as_deref().unwrap_or(client_credentials::DEFAULT_AUTHORITY_HOST),
+        config.tenant_id,
+        config.client_id,
+        credential,
+        scopes.join(" ")
+    )
+    .to_lowercase()
+}
+
+/// Read the app's credential from its local source or Key Vault.
+async fn load_credential(config: &TokenAppConfig) -> Result<ClientCredential, CommandError> {
+    if let Some(source) = &config.credential {
+        return credentials::load(source, &config.client_id, config.assertion_algorithm.unwrap_or_default()).await;
+    }
+
+    let key_vault = &config.key_vault;
+    if key_vault.credential_type == "certificate" {
+        let name = key_vault
+            .cert_name
+            .as_deref()
+            .ok_or_else(|| CommandError::validation("certName", "Certificate name is required for certificate authentication"))?;
+        let certificate = keyvault::get_certificate(&key_vault.uri, name).await?;
+        Ok(ClientCredential::Certificate(CertificateCredential::from_key_vault(
+            certificate,
+            config.assertion_algorithm.unwrap_or_default(),
+        )))
+    } else {
+        let name = key_vault
+            .secret_name
+            .as_deref()
+            .ok_or_else(|| CommandError::validation("secretName", "Secret name is required for secret authentication"))?;
+        Ok(ClientCredential::Secret(keyvault::get_secret(&key_vault.uri, name).await?))
+    }
+}
+
+async fn acquire_app_token_uncached(config: &TokenAppConfig, scopes: &[String]) -> Result<TokenResponse, CommandError> {
+    let credential = load_credential(config).await?;
+    client_credentials::acquire_token(
+        config.authority_host.as_deref(),
+        &config.tenant_id,
+        &config.client_id,
+        scopes,
+        &credential,
+    )
+    .await
+}
+
+/// Runs app-token and Key Vault requests in-process; defers the rest to the sidecar
+pub struct NativeBackend;
+
+#[async_trait]
+impl TokenBackend for NativeBackend {
+    async fn acquire_app_token(
+        &self,
+        config: &TokenAppConfig,
+        scopes: &[String],
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        let key = cache_key(config, scopes);
+        if let Some((token, expires_at)) = APP_TOKENS.lock().unwrap().get(&key) {
+            if now_secs() + TOKEN_REFRESH_MARGIN_SECS < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let timeout = options.timeout.unwrap_or(APP_TOKEN_TIMEOUT);
+        let token = tokio::time::timeout(timeout, acquire_app_token_uncached(config, scopes))
+            .await
+            .map_err(|_| CommandError::timeout("acquire_app_token", timeout.as_millis() as u64))??;
+
+        // Access tokens are opaque to clients in general, but Entra's are JWTs; if `exp`
+        // can't be read the token simply isn't cached.
+        if let Some(exp) = crate::jwt::decode(&token.access_token).ok().and_then(|t| t.claims.exp) {
+            APP_TOKENS.lock().unwrap().insert(key, (token.clone(), exp.max(0) as u64));
+        }
+        Ok(token)
+    }
+
+    async fn acquire_user_token(
+        &self,
+        request: &UserTokenRequest,
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        SidecarBackend.acquire_user_token(request, options).await
+    }
+
+    async fn acquire_obo_token(
+        &self,
+        config: &TokenAppConfig,
+        user_assertion: &str,
+        scopes: &[String],
+        options: CallOptions,
+    ) -> Result<TokenResponse, CommandError> {
+        // Not cached: each exchange is for a different incoming token.
+        let exchange = async {
+            let credential = load_credential(config).await?;
+            client_credentials::acquire_token_on_behalf_of(
+                config.authority_host.as_deref(),
+                &config.tenant_id,
+                &config.client_id,
+                user_assertion,
+                scopes,
+                &credential,
+            )
+            .await
+        };
+        let timeout = options.timeout.unwrap_or(APP_TOKEN_TIMEOUT);
+        tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| CommandError::timeout("acquire_obo_token", timeout.as_millis() as u64))?
+    }
+
+    async fn start_device_code(
+        &self,
+        request: &DeviceCodeRequest,
+        options: CallOptions,
+    ) -> Result<DeviceCodeInfo, CommandError> {
+        SidecarBackend.start_device_code(request, options).await
+    }
+
+    async fn cancel_device_code(&self, flow_id: &str) -> Result<bool, CommandError> {
+        SidecarBackend.cancel_device_code(flow_id).await
+    }
+
+    async fn get_user_accounts(
+        &self,
+        client_id: &str,
+        tenant_id: &str,
+        options: CallOptions,
+    ) -> Result<Vec<AccountInfo>, CommandError> {
+        SidecarBackend.get_user_accounts(client_id, tenant_id, options).await
+    }
+
+    async fn clear_user_cache(&self, client_id: &str, tenant_id: &str) -> Result<(), CommandError> {
+        SidecarBackend.clear_user_cache(client_id, tenant_id).await
+    }
+
+    async fn validate_keyvault(
+        &self,
+        config: &KeyVaultConfig,
+        options: CallOptions,
+    ) -> Result<ValidationResult, CommandError> {
+        let credential_type = config.credential_type.clone();
+        let check = async {
+            if config.credential_type == "certificate" {
+                match config.cert_name.as_deref() {
+                    Some(name) => keyvault::get_certificate(&config.uri, name).await.map(|_| ()),
+                    None => Err(CommandError::validation("certName", "Certificate name is required")),
+                }
+            } else {
+                match config.secret_name.as_deref() {
+                    Some(name) => keyvault::get_secret(&config.uri, name).await.map(|_| ()),
+                    None => Err(CommandError::validation("secretName", "Secret name is required")),
+                }
+            }
+        };
+        let timeout = options.timeout.unwrap_or(APP_TOKEN_TIMEOUT);
+        let result = tokio::time::timeout(timeout, check)
+            .await
+            .map_err(|_| CommandError::timeout("validate_keyvault", timeout.as_millis() as u64))?;
+
+        // Like the sidecar, report credential problems as an invalid result rather than an error.
+        Ok(match result {
+            Ok(()) => ValidationResult {
+                valid: true,
+                credential_type,
+                message: None,
+            },
+            Err(error) => ValidationResult {
+                valid: false,
+                credential_type,
+                message: Some(error.message().to_string()),
+            },
+        })
+    }
+
+    async fn get_credential_status(&self, options: CallOptions) -> Result<CredentialStatus, CommandError> {
+        SidecarBackend.get_credential_status(options).await
+    }
+
+    async fn get_auth_storage_status(&self) -> Result<AuthStorageStatus, CommandError> {
+        SidecarBackend.get_auth_storage_status().await
+    }
+
+    async fn list_azure_subscriptions(
+        &self,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureSubscription>>, CommandError> {
+        SidecarBackend.list_azure_subscriptions(options).await
+    }
+
+    async fn list_azure_apps(
+        &self,
+        filters: Option<&AzureAppFilters>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureAppRegistration>>, CommandError> {
+        SidecarBackend.list_azure_apps(filters, options).await
+    }
+
+    async fn list_keyvaults(
+        &self,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureKeyVault>>, CommandError> {
+        SidecarBackend.list_keyvaults(subscription_id, options).await
+    }
+
+    async fn list_keyvault_secrets(
+        &self,
+        vault_name: &str,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+        SidecarBackend.list_keyvault_secrets(vault_name, subscription_id, options).await
+    }
+
+    async fn list_keyvault_certificates(
+        &self,
+        vault_name: &str,
+        subscription_id: Option<&str>,
+        options: CallOptions,
+    ) -> Result<AzureCliResult<Vec<AzureVaultCredential>>, CommandError> {
+        SidecarBackend.list_keyvault_certificates(vault_name, subscription_id, options).await
+    }
+}
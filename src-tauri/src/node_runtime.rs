@@ -0,0 +1,411 @@
+//! Node.js runtime discovery for the sidecar.
+//!
+//! Every candidate is listed with the version it reports: each `node` on `PATH`, the usual
+//! install locations and every version installed by nvm, nvm-windows, fnm, Volta, asdf or
+//! Linuxbrew. The newest compatible runtime (Node.js [`MIN_MAJOR`] or higher) is used, the
+//! first one found winning a tie. If [`AppSettings`](crate::settings::AppSettings) names a
+//! runtime, it is the only candidate: it is used as is or not at all, never silently
+//! replaced by another one.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Serialize;
+
+use crate::error::CommandError;
+use crate::settings;
+
+/// Oldest Node.js major version the sidecar supports
+pub const MIN_MAJOR: u32 = 20;
+
+#[cfg(target_os = "windows")]
+const NODE_EXE: &str = "node.exe";
+#[cfg(not(target_os = "windows"))]
+const NODE_EXE: &str = "node";
+
+/// Major, minor and patch version
+type Version = (u32, u32, u32);
+
+/// A discovered Node.js executable
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeRuntime {
+    pub path: String,
+    /// Where it was found: `override`, `path`, `system`, `nvm`, `fnm`, `volta`, `asdf` or `linuxbrew`
+    pub source: &'static str,
+    /// `node --version` without the leading `v`; `None` if it didn't run
+    pub version: Option<String>,
+    pub compatible: bool,
+    /// Whether the sidecar uses this one
+    pub selected: bool,
+    /// Why it can't be used
+    pub problem: Option<String>,
+    #[serde(skip)]
+    parsed: Option<Version>,
+}
+
+/// Parse `v20.11.1` (or `20.11.1`) into its numeric parts.
+fn parse_version(output: &str) -> Option<Version> {
+    let version = output.trim().trim_start_matches('v');
+    let mut parts = version.split(['.', '-']).map(|part| part.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// Version, parsed version and problem for the output of a successful `node --version`
+fn check_version_output(output: &str) -> (Option<String>, Option<Version>, Option<String>) {
+    let text = output.trim().to_string();
+    match parse_version(&text) {
+        Some(parsed) if parsed.0 >= MIN_MAJOR => (Some(text), Some(parsed), None),
+        Some(parsed) => (Some(text), Some(parsed), Some(format!("Node.js {} or higher is required", MIN_MAJOR))),
+        None => (None, None, Some(format!("Unrecognized version output '{}'", text))),
+    }
+}
+
+fn probe(path: PathBuf, source: &'static str) -> NodeRuntime {
+    let mut command = Command::new(&path);
+    command.arg("--version");
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    let output = command.output();
+    let (version, parsed, problem) = match output {
+        Ok(output) if output.status.success() => check_version_output(&String::from_utf8_lossy(&output.stdout)),
+        Ok(output) => (None, None, Some(format!("`node --version` exited with {}", output.status))),
+        Err(e) => (None, None, Some(format!("Failed to run: {}", e))),
+    };
+    NodeRuntime {
+        path: path.display().to_string(),
+        source,
+        version: version.map(|v| v.trim_start_matches('v').to_string()),
+        compatible: problem.is_none(),
+        selected: false,
+        problem,
+        parsed,
+    }
+}
+
+/// Executables in the subdirectories of a version manager's install directory
+fn versions_in(base: &Path, relative_exe: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(base) else {
+        return Vec::new();
+    };
+    let mut found: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path().join(relative_exe))
+        .filter(|path| path.is_file())
+        .collect();
+    found.sort();
+    found
+}
+
+/// Candidate executables in discovery order; only the override if one is set.
+fn candidates(override_path: Option<&Path>) -> Vec<(PathBuf, &'static str)> {
+    let mut found: Vec<(PathBuf, &'static str)> = Vec::new();
+    if let Some(path) = override_path {
+        found.push((path.to_path_buf(), "override"));
+        return found;
+    }
+
+    if let Some(path_var) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&path_var) {
+            let node = dir.join(NODE_EXE);
+            if node.is_file() {
+                found.push((node, "path"));
+            }
+        }
+    }
+
+    // Platform-specific common installation paths
+    #[cfg(target_os = "macos")]
+    let common_paths: Vec<PathBuf> = vec![
+        PathBuf::from("/usr/local/bin/node"),
+        PathBuf::from("/opt/homebrew/bin/node"),
+        PathBuf::from("/usr/bin/node"),
+    ];
+
+    #[cfg(target_os = "windows")]
+    let common_paths: Vec<PathBuf> = {
+        let mut paths = vec![
+            PathBuf::from("C:\\Program Files\\nodejs\\node.exe"),
+            PathBuf::from("C:\\Program Files (x86)\\nodejs\\node.exe"),
+        ];
+        if let Ok(program_files) = std::env::var("ProgramFiles") {
+            paths.push(PathBuf::from(format!("{}\\nodejs\\node.exe", program_files)));
+        }
+        if let Ok(program_files_x86) = std::env::var("ProgramFiles(x86)") {
+            paths.push(PathBuf::from(format!("{}\\nodejs\\node.exe", program_files_x86)));
+        }
+        paths
+    };
+
+    #[cfg(target_os = "linux")]
+    let common_paths: Vec<PathBuf> = vec![
+        PathBuf::from("/usr/local/bin/node"),
+        PathBuf::from("/usr/bin/node"),
+        PathBuf::from("/snap/bin/node"),
+    ];
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    let common_paths: Vec<PathBuf> = vec![PathBuf::from("/usr/local/bin/node"), PathBuf::from("/usr/bin/node")];
+
+    found.extend(common_paths.into_iter().filter(|path| path.is_file()).map(|path| (path, "system")));
+
+    if let Some(home) = dirs::home_dir() {
+        // nvm: ~/.nvm/versions/node/<version>/bin/node
+        #[cfg(not(target_os = "windows"))]
+        found.extend(versions_in(&home.join(".nvm/versions/node"), "bin/node").into_iter().map(|p| (p, "nvm")));
+
+        // nvm-windows: %APPDATA%\nvm\<version>\node.exe
+        #[cfg(target_os = "windows")]
+        if let Ok(appdata) = std::env::var("APPDATA") {
+            let base = PathBuf::from(appdata).join("nvm");
+            found.extend(versions_in(&base, "node.exe").into_iter().map(|p| (p, "nvm")));
+        }
+
+        // fnm: <version>/installation/bin/node (Unix) or <version>\installation\node.exe (Windows)
+        #[cfg(target_os = "windows")]
+        if let Ok(localappdata) = std::env::var("LOCALAPPDATA") {
+            let base = PathBuf::from(localappdata).join("fnm").join("node-versions");
+            found.extend(versions_in(&base, "installation\\node.exe").into_iter().map(|p| (p, "fnm")));
+        }
+        #[cfg(not(target_os = "windows"))]
+        found.extend(
+            versions_in(&home.join(".local/share/fnm/node-versions"), "installation/bin/node")
+                .into_iter()
+                .map(|p| (p, "fnm")),
+        );
+
+        #[cfg(target_os = "windows")]
+        let volta_node = home.join(".volta\\bin\\node.exe");
+        #[cfg(not(target_os = "windows"))]
+        let volta_node = home.join(".volta/bin/node");
+        if volta_node.is_file() {
+            found.push((volta_node, "volta"));
+        }
+
+        #[cfg(not(target_os = "windows"))]
+        {
+            let asdf_node = home.join(".asdf/shims/node");
+            if asdf_node.is_file() {
+                found.push((asdf_node, "asdf"));
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let linuxbrew = home.join(".linuxbrew/bin/node");
+            if linuxbrew.is_file() {
+                found.push((linuxbrew, "linuxbrew"));
+            }
+        }
+    }
+
+    // The same executable is often reachable through PATH and a known location.
+    let mut seen = std::collections::HashSet::new();
+    found.retain(|(path, _)| seen.insert(std::fs::canonicalize(path).unwrap_or_else(|_| path.clone())));
+    found
+}
+
+fn override_path() -> Option<PathBuf> {
+    let data_dir = crate::sidecar::data_dir()?;
+    match settings::load(&data_dir) {
+        Ok(settings) => settings.node_path.map(PathBuf::from),
+        Err(e) => {
+            log::warn!("Ignoring settings: {}", e);
+            None
+        }
+    }
+}
+
+/// Index of the runtime to use: the override if it works, else the newest compatible one.
+fn choose(runtimes: &[NodeRuntime]) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    for (index, runtime) in runtimes.iter().enumerate() {
+        if !runtime.compatible {
+            continue;
+        }
+        match best {
+            Some(best) if runtime.parsed <= runtimes[best].parsed => {}
+            _ => best = Some(index),
+        }
+    }
+    best
+}
+
+/// Every runtime found, with the one the sidecar would use marked `selected`.
+pub fn list() -> Vec<NodeRuntime> {
+    let mut runtimes: Vec<NodeRuntime> = candidates(override_path().as_deref())
+        .into_iter()
+        .map(|(path, source)| probe(path, source))
+        .collect();
+    if let Some(index) = choose(&runtimes) {
+        runtimes[index].selected = true;
+    }
+    runtimes
+}
+
+fn install_hint() -> &'static str {
+    #[cfg(target_os = "macos")]
+    let hint = "Install via Homebrew: brew install node";
+    #[cfg(target_os = "windows")]
+    let hint = "Download from https://nodejs.org or install via: winget install OpenJS.NodeJS.LTS";
+    #[cfg(target_os = "linux")]
+    let hint = "Install via your package manager (apt install nodejs, dnf install nodejs, pacman -S nodejs, or snap install node --classic)";
+    #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+    let hint = "Download from https://nodejs.org";
+    hint
+}
+
+/// The runtime to start the sidecar with.
+///
+/// Fails with `NODE_NOT_FOUND` if there is no candidate at all, and with
+/// `NODE_INCOMPATIBLE` if none of them (or the override) can be used.
+pub fn select() -> Result<NodeRuntime, CommandError> {
+    select_from(&list())
+}
+
+fn select_from(runtimes: &[NodeRuntime]) -> Result<NodeRuntime, CommandError> {
+    if let Some(runtime) = runtimes.iter().find(|runtime| runtime.selected) {
+        return Ok(runtime.clone());
+    }
+
+    let Some(first) = runtimes.first() else {
+        return Err(CommandError::NodeMissing {
+            message: format!(
+                "Node.js (version {} or higher) is required but was not found. \
+                Please install Node.js and ensure it's in your PATH. {}. \
+                Download: https://nodejs.org",
+                MIN_MAJOR,
+                install_hint()
+            ),
+        });
+    };
+
+    let message = if first.source == "override" {
+        format!(
+            "The Node.js runtime set in Settings ({}) can't be used: {}. \
+            Choose another one or clear the setting.",
+            first.path,
+            first.problem.as_deref().unwrap_or("unknown problem")
+        )
+    } else {
+        let found: Vec<String> = runtimes
+            .iter()
+            .map(|runtime| format!("{} ({})", runtime.path, runtime.version.as_deref().unwrap_or("unknown version")))
+            .collect();
+        format!(
+            "Node.js {} or higher is required, but only incompatible runtimes were found: {}. {}. \
+            Download: https://nodejs.org",
+            MIN_MAJOR,
+            found.join(", "),
+            install_hint()
+        )
+    };
+    Err(CommandError::NodeIncompatible { message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A runtime as `probe` would report it for the given `node --version` output
+    fn runtime(path: &str, source: &'static str, output: &str) -> NodeRuntime {
+        let (version, parsed, problem) = check_version_output(output);
+        NodeRuntime {
+            path: path.to_string(),
+            source,
+            version: version.map(|v| v.trim_start_matches('v').to_string()),
+            compatible: problem.is_none(),
+            selected: false,
+            problem,
+            parsed,
+        }
+    }
+
+    #[test]
+    fn parse_version_formats() {
+        assert_eq!(parse_version("v20.11.1\n"), Some((20, 11, 1)));
+        assert_eq!(parse_version("20.11.1"), Some((20, 11, 1)));
+        assert_eq!(parse_version("v22.0.0-nightly20240425b8e6b5e5c3"), Some((22, 0, 0)));
+        assert_eq!(parse_version("v21.0.0-rc.1"), Some((21, 0, 0)));
+        assert_eq!(parse_version("v20.11"), None);
+        assert_eq!(parse_version("node: command not found"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn version_output_below_minimum_or_garbage_is_incompatible() {
+        let current = runtime("/usr/bin/node", "system", "v20.0.0\n");
+        assert!(current.compatible);
+        assert_eq!(current.version.as_deref(), Some("20.0.0"));
+
+        let old = runtime("/usr/bin/node", "system", "v18.19.0");
+        assert!(!old.compatible);
+        assert_eq!(old.version.as_deref(), Some("18.19.0"));
+        assert_eq!(old.problem.as_deref(), Some("Node.js 20 or higher is required"));
+
+        let garbage = runtime("/usr/bin/node", "system", "Welcome to Node.js");
+        assert!(!garbage.compatible);
+        assert_eq!(garbage.version, None);
+        assert!(garbage.problem.unwrap().starts_with("Unrecognized version output"));
+    }
+
+    #[test]
+    fn choose_prefers_the_highest_compatible_version() {
+        let runtimes = vec![
+            runtime("/a/node", "path", "v20.11.1"),
+            runtime("/b/node", "nvm", "v24.0.0"),
+            runtime("/c/node", "nvm", "v22.3.0"),
+            runtime("/d/node", "nvm", "v16.20.2"),
+        ];
+        assert_eq!(choose(&runtimes), Some(1));
+    }
+
+    #[test]
+    fn choose_keeps_the_first_found_on_a_tie() {
+        let runtimes = vec![
+            runtime("/usr/local/bin/node", "path", "v22.1.0"),
+            runtime("/home/user/.nvm/versions/node/v22.1.0/bin/node", "nvm", "v22.1.0"),
+        ];
+        assert_eq!(choose(&runtimes), Some(0));
+    }
+
+    #[test]
+    fn choose_skips_incompatible_runtimes() {
+        let runtimes = vec![runtime("/a/node", "path", "v18.0.0"), runtime("/b/node", "nvm", "garbage")];
+        assert_eq!(choose(&runtimes), None);
+        assert_eq!(choose(&[]), None);
+    }
+
+    #[test]
+    fn override_is_the_only_candidate() {
+        let path = Path::new("/opt/node-18/bin/node");
+        assert_eq!(candidates(Some(path)), vec![(path.to_path_buf(), "override")]);
+    }
+
+    #[test]
+    fn incompatible_override_is_not_replaced() {
+        let runtimes = vec![runtime("/opt/node-18/bin/node", "override", "v18.19.0")];
+        let error = select_from(&runtimes).unwrap_err();
+        assert_eq!(error.code(), Some("NODE_INCOMPATIBLE"));
+        assert!(error.message().contains("set in Settings (/opt/node-18/bin/node)"), "{}", error);
+        assert!(error.message().contains("Node.js 20 or higher is required"), "{}", error);
+    }
+
+    #[test]
+    fn select_reports_missing_and_incompatible_runtimes() {
+        assert_eq!(select_from(&[]).unwrap_err().code(), Some("NODE_NOT_FOUND"));
+
+        let runtimes = vec![runtime("/usr/bin/node", "system", "v18.19.0")];
+        let error = select_from(&runtimes).unwrap_err();
+        assert_eq!(error.code(), Some("NODE_INCOMPATIBLE"));
+        assert!(error.message().contains("/usr/bin/node (18.19.0)"), "{}", error);
+
+        let mut runtimes = vec![runtime("/usr/bin/node", "system", "v18.19.0"), runtime("/b/node", "nvm", "v22.0.0")];
+        runtimes[1].selected = true;
+        assert_eq!(select_from(&runtimes).unwrap().path, "/b/node");
+    }
+}
@@ -0,0 +1,97 @@
+//! Saved app configurations, mirrored from the frontend so headless callers can use them.
+//!
+//! The frontend keeps the app registry in IndexedDB, which only the webview can read.
+//! Every time it persists the registry it also calls `sync_saved_apps`, which writes a
+//! copy to `saved-apps.json` in the app data directory. The file holds only app
+//! metadata and Key Vault references, never credentials.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::BackendKind;
+use crate::client_credentials::AssertionAlgorithm;
+use crate::credentials::CredentialSource;
+use crate::error::CommandError;
+use crate::store;
+use crate::{KeyVaultConfig, TokenAppConfig};
+
+const SAVED_APPS_FILE: &str = "saved-apps.json";
+
+/// A saved app, as configured in the app registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedApp {
+    pub id: String,
+    pub name: String,
+    pub client_id: String,
+    pub tenant_id: String,
+    #[serde(default)]
+    pub key_vault: KeyVaultConfig,
+    /// Local credential used instead of Key Vault
+    pub credential: Option<CredentialSource>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Token backend chosen for this app (the default backend if unset)
+    pub backend: Option<BackendKind>,
+    pub authority_host: Option<String>,
+    pub assertion_algorithm: Option<AssertionAlgorithm>,
+}
+
+impl SavedApp {
+    /// The token request configuration for this app
+    pub fn token_config(&self) -> TokenAppConfig {
+        TokenAppConfig {
+            client_id: self.client_id.clone(),
+            tenant_id: self.tenant_id.clone(),
+            key_vault: self.key_vault.clone(),
+            credential: self.credential.clone(),
+            backend: self.backend,
+            authority_host: self.authority_host.clone(),
+            assertion_algorithm: self.assertion_algorithm,
+        }
+    }
+}
+
+fn saved_apps_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SAVED_APPS_FILE)
+}
+
+/// Replace the mirrored registry with `apps`.
+pub fn save(data_dir: &Path, apps: &[SavedApp]) -> Result<(), CommandError> {
+    store::write_json_atomic(&saved_apps_path(data_dir), apps, "saved apps")
+}
+
+/// Load the mirrored registry. A missing file means the desktop app hasn't synced yet.
+pub fn load(data_dir: &Path) -> Result<Vec<SavedApp>, CommandError> {
+    store::read_json_or_default(&saved_apps_path(data_dir), "saved apps")
+}
+
+/// Find an app by id, name (case-insensitive) or client id.
+pub fn find<'a>(apps: &'a [SavedApp], query: &str) -> Result<&'a SavedApp, String> {
+    let query = query.trim();
+    if let Some(app) = apps.iter().find(|app| app.id == query) {
+        return Ok(app);
+    }
+
+    let by_name: Vec<&SavedApp> = apps
+        .iter()
+        .filter(|app| app.name.eq_ignore_ascii_case(query))
+        .collect();
+    match by_name.as_slice() {
+        [app] => return Ok(app),
+        [] => {}
+        _ => {
+            return Err(format!(
+                "Several saved apps are named '{}'; use the app id instead ({})",
+                query,
+                by_name.iter().map(|app| app.id.as_str()).collect::<Vec<_>>().join(", ")
+            ))
+        }
+    }
+
+    apps.iter()
+        .find(|app| app.client_id.eq_ignore_ascii_case(query))
+        .ok_or_else(|| format!("No saved app matches '{}'", query))
+}
@@ -0,0 +1,43 @@
+//! Desktop settings kept by the Rust side.
+//!
+//! Stored as `settings.json` next to the saved apps, so the headless CLI uses the same
+//! values as the app window. Fields are optional and default to unset.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CommandError;
+use crate::store;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppSettings {
+    /// Node.js executable for the sidecar, instead of the newest compatible one found
+    pub node_path: Option<String>,
+}
+
+fn settings_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SETTINGS_FILE)
+}
+
+/// Replace the settings with `settings`.
+pub fn save(data_dir: &Path, settings: &AppSettings) -> Result<(), CommandError> {
+    if let Some(node_path) = &settings.node_path {
+        let path = Path::new(node_path);
+        if !path.is_absolute() || !path.is_file() {
+            return Err(CommandError::validation(
+                "nodePath",
+                format!("{} is not the absolute path of an existing file", node_path),
+            ));
+        }
+    }
+    store::write_json_atomic(&settings_path(data_dir), settings, "settings")
+}
+
+/// Load the settings; defaults if the file doesn't exist yet.
+pub fn load(data_dir: &Path) -> Result<AppSettings, CommandError> {
+    store::read_json_or_default(&settings_path(data_dir), "settings")
+}
@@ -1,38 +1,63 @@
-use serde::{Deserialize, Serialize};
-use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, Command};
-use tokio::sync::Mutex;
-use std::sync::Arc;
-use std::sync::OnceLock;
-use tauri::Manager;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, watch, Mutex};
+use zeroize::Zeroizing;
+
+use crate::error::{
+    CommandError, SIDECAR_CRASH_LOOP, SIDECAR_NOT_STARTED, SIDECAR_PROTOCOL_MISMATCH, SIDECAR_RESTARTING,
+    SIDECAR_SCRIPT_NOT_FOUND, SIDECAR_SPAWN_FAILED,
+};
 
 #[derive(Clone)]
 struct SidecarEnv {
+    identifier: String,
     data_dir: Option<String>,
-    cache_key_b64: Option<String>,
+    /// Where the MSAL cache key lives (`keyring`, `file` or `none`); the key itself is
+    /// re-read for each sidecar start rather than kept in memory.
     cache_key_source: Option<String>,
 }
 
 static SIDECAR_ENV: OnceLock<SidecarEnv> = OnceLock::new();
 
-fn get_or_create_cache_key_b64(identifier: &str) -> Result<String, String> {
+/// Used to broadcast sidecar lifecycle events; absent until `init_sidecar_env` runs.
+static APP_HANDLE: OnceLock<tauri::AppHandle> = OnceLock::new();
+
+/// The running app, for emitting events from outside a command; `None` in CLI mode.
+pub fn app_handle() -> Option<&'static tauri::AppHandle> {
+    APP_HANDLE.get()
+}
+
+const KEYRING_SERVICE: &str = "Entra Token Studio";
+
+fn keyring_entry(identifier: &str, purpose: &str) -> Result<keyring::Entry, String> {
+    let account = format!("{}:{}", identifier, purpose);
+    keyring::Entry::new(KEYRING_SERVICE, &account).map_err(|e| format!("Failed to initialize keyring: {e}"))
+}
+
+fn get_or_create_cache_key_b64(identifier: &str) -> Result<Zeroizing<String>, String> {
     use base64::{engine::general_purpose::STANDARD, Engine as _};
-    use keyring::Entry;
 
-    let service = "Entra Token Studio";
-    let account = format!("{}:msal-cache-key", identifier);
-    let entry = Entry::new(service, &account).map_err(|e| format!("Failed to initialize keyring: {e}"))?;
+    let entry = keyring_entry(identifier, "msal-cache-key")?;
 
-    match entry.get_password() {
+    match entry.get_password().map(Zeroizing::new) {
         Ok(existing) if !existing.trim().is_empty() => Ok(existing),
         Ok(_) | Err(keyring::Error::NoEntry) => {
             // Missing or empty entry: generate and store a new key.
             let _ = entry.delete_credential();
-            let mut key = [0u8; 32];
-            getrandom::getrandom(&mut key).map_err(|e| format!("Failed to generate cache key: {e}"))?;
-            let b64 = STANDARD.encode(key);
+            let mut key = Zeroizing::new([0u8; 32]);
+            getrandom::getrandom(key.as_mut()).map_err(|e| format!("Failed to generate cache key: {e}"))?;
+            let b64 = Zeroizing::new(STANDARD.encode(key.as_ref()));
             entry
                 .set_password(&b64)
                 .map_err(|e| format!("Failed to write keyring entry: {e}"))?;
@@ -45,6 +70,62 @@ fn get_or_create_cache_key_b64(identifier: &str) -> Result<String, String> {
     }
 }
 
+/// Generate a fresh random secret for this session and store it in the keyring under
+/// `purpose`, replacing any previous one, so local tools can read it from there.
+/// The secret is still returned (and usable for the session) if the keyring is unavailable.
+pub fn create_session_secret(identifier: &str, purpose: &str) -> Result<String, CommandError> {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(CommandError::random)?;
+    let secret = URL_SAFE_NO_PAD.encode(bytes);
+
+    let stored = keyring_entry(identifier, purpose).and_then(|entry| {
+        entry
+            .set_password(&secret)
+            .map_err(|e| format!("Failed to write keyring entry: {e}"))
+    });
+    if let Err(e) = stored {
+        log::warn!("Session secret '{}' is not persisted to the keyring: {}", purpose, e);
+    }
+    Ok(secret)
+}
+
+/// App identifier keyring entries are scoped to; set by `init_sidecar_env`.
+pub fn keyring_identifier() -> Option<&'static str> {
+    SIDECAR_ENV.get().map(|env| env.identifier.as_str())
+}
+
+/// App data directory (saved apps, file cache key); `None` before init or if unknown.
+pub fn data_dir() -> Option<PathBuf> {
+    SIDECAR_ENV.get().and_then(|env| env.data_dir.as_ref()).map(PathBuf::from)
+}
+
+/// Store a user-supplied credential (client secret, PFX password) in the keyring under `purpose`.
+pub fn store_keyring_secret(identifier: &str, purpose: &str, value: &str) -> Result<(), String> {
+    keyring_entry(identifier, purpose)?
+        .set_password(value)
+        .map_err(|e| format!("Failed to write keyring entry: {e}"))
+}
+
+/// Read a credential stored with [`store_keyring_secret`]; `None` if there is none.
+pub fn read_keyring_secret(identifier: &str, purpose: &str) -> Result<Option<String>, String> {
+    match keyring_entry(identifier, purpose)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read keyring entry: {e}")),
+    }
+}
+
+/// Delete a stored credential. Returns `false` if there was none.
+pub fn delete_keyring_secret(identifier: &str, purpose: &str) -> Result<bool, String> {
+    match keyring_entry(identifier, purpose)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!("Failed to delete keyring entry: {e}")),
+    }
+}
+
 fn cache_key_file_path(data_dir: &Path, identifier: &str) -> PathBuf {
     // Scope the key to the app identifier to avoid collisions across forks/dev builds.
     data_dir.join(format!("msal-cache-key.{identifier}.b64"))
@@ -62,24 +143,24 @@ fn is_valid_cache_key_b64(value: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn get_or_create_cache_key_b64_file(data_dir: &Path, identifier: &str) -> Result<String, String> {
+fn get_or_create_cache_key_b64_file(data_dir: &Path, identifier: &str) -> Result<Zeroizing<String>, String> {
     use base64::{engine::general_purpose::STANDARD, Engine as _};
     use std::fs;
     use std::io::Write;
 
     let path = cache_key_file_path(data_dir, identifier);
 
-    if let Ok(existing) = fs::read_to_string(&path) {
+    if let Ok(existing) = fs::read_to_string(&path).map(Zeroizing::new) {
         if is_valid_cache_key_b64(&existing) {
-            return Ok(existing.trim().to_string());
+            return Ok(Zeroizing::new(existing.trim().to_string()));
         }
     }
 
     fs::create_dir_all(data_dir).map_err(|e| format!("Failed to create cache key directory: {e}"))?;
 
-    let mut key = [0u8; 32];
-    getrandom::getrandom(&mut key).map_err(|e| format!("Failed to generate cache key: {e}"))?;
-    let b64 = STANDARD.encode(key);
+    let mut key = Zeroizing::new([0u8; 32]);
+    getrandom::getrandom(key.as_mut()).map_err(|e| format!("Failed to generate cache key: {e}"))?;
+    let b64 = Zeroizing::new(STANDARD.encode(key.as_ref()));
 
     #[cfg(unix)]
     {
@@ -111,16 +192,24 @@ fn get_or_create_cache_key_b64_file(data_dir: &Path, identifier: &str) -> Result
 }
 
 pub fn init_sidecar_env(app: &tauri::AppHandle) {
-    // Best-effort: if keyring fails (e.g. missing secret service), the sidecar will fall back to
-    // a less secure file cache with strict permissions.
     let identifier = app.config().identifier.clone();
     let data_dir_path = app
         .path()
         .app_data_dir()
         .ok();
+    let _ = APP_HANDLE.set(app.clone());
+    init_sidecar_env_for(&identifier, data_dir_path);
+}
+
+/// Set up the sidecar environment without a running Tauri app (headless CLI mode).
+/// Status and log events are not emitted in this mode.
+pub fn init_sidecar_env_for(identifier: &str, data_dir_path: Option<PathBuf>) {
+    // Best-effort: if keyring fails (e.g. missing secret service), the sidecar will fall back to
+    // a less secure file cache with strict permissions.
     let data_dir = data_dir_path.as_ref().map(|p| p.to_string_lossy().to_string());
 
-    let mut cache_key_b64: Option<String> = None;
+    // Only the source is kept: the key is created here if needed and dropped (zeroized)
+    // until a sidecar start reads it again.
     let mut cache_key_source: Option<String> = None;
 
     // In debug builds, prefer a stable on-disk key so local dev restarts (and rebuilds) keep auth state.
@@ -132,14 +221,11 @@ pub fn init_sidecar_env(app: &tauri::AppHandle) {
             .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
             .unwrap_or(false);
 
-    if prefer_keyring {
-        if let Ok(key) = get_or_create_cache_key_b64(&identifier) {
-            cache_key_b64 = Some(key);
-            cache_key_source = Some("keyring".to_string());
-        }
+    if prefer_keyring && get_or_create_cache_key_b64(identifier).is_ok() {
+        cache_key_source = Some("keyring".to_string());
     }
 
-    if cache_key_b64.is_none() {
+    if cache_key_source.is_none() {
         // File-backed key is used as a dev-friendly fallback (and optionally as a runtime fallback).
         let allow_file_fallback = cfg!(debug_assertions)
             || std::env::var("ENTRA_TOKEN_STUDIO_ALLOW_FILE_CACHE_KEY")
@@ -150,8 +236,7 @@ pub fn init_sidecar_env(app: &tauri::AppHandle) {
 
         if allow_file_fallback {
             if let Some(dir) = data_dir_path.as_ref() {
-                if let Ok(key) = get_or_create_cache_key_b64_file(dir, &identifier) {
-                    cache_key_b64 = Some(key);
+                if get_or_create_cache_key_b64_file(dir, identifier).is_ok() {
                     cache_key_source = Some("file".to_string());
                 }
             }
@@ -159,12 +244,58 @@ pub fn init_sidecar_env(app: &tauri::AppHandle) {
     }
 
     let _ = SIDECAR_ENV.set(SidecarEnv {
+        identifier: identifier.to_string(),
         data_dir,
-        cache_key_b64,
         cache_key_source: cache_key_source.or(Some("none".to_string())),
     });
 }
 
+/// Read the cache key back from wherever `init_sidecar_env_for` found it.
+fn load_cache_key(env: &SidecarEnv) -> Option<Zeroizing<String>> {
+    let result = match env.cache_key_source.as_deref() {
+        Some("keyring") => get_or_create_cache_key_b64(&env.identifier),
+        Some("file") => match env.data_dir.as_deref() {
+            Some(dir) => get_or_create_cache_key_b64_file(Path::new(dir), &env.identifier),
+            None => Err("No data directory for the cache key file".to_string()),
+        },
+        _ => return None,
+    };
+    result
+        .map_err(|e| log::warn!("MSAL cache key is unavailable; the sidecar will not persist sign-ins: {}", e))
+        .ok()
+}
+
+/// The first line written to a new sidecar: the `session/key` notification carrying the
+/// MSAL cache key, which the sidecar keeps in memory instead of reading its environment.
+///
+/// Built by hand into a buffer that is sized up front and zeroized on drop, so no partial
+/// copies of the key are left behind by reallocation or serialization.
+fn session_key_message(env: Option<&SidecarEnv>) -> Zeroizing<String> {
+    let key = env.and_then(load_cache_key);
+    let source = match (&key, env.and_then(|env| env.cache_key_source.as_deref())) {
+        (Some(_), Some(source)) => source,
+        _ => "none",
+    };
+    // Base64 and the fixed source names never need JSON escaping.
+    let key_json_len = key.as_ref().map(|key| key.len() + 2).unwrap_or(4);
+    let mut message = Zeroizing::new(String::with_capacity(128 + key_json_len));
+    message.push_str(r#"{"jsonrpc":"2.0","method":""#);
+    message.push_str(SESSION_KEY_METHOD);
+    message.push_str(r#"","params":{"cacheKey":"#);
+    match &key {
+        Some(key) => {
+            message.push('"');
+            message.push_str(key.trim());
+            message.push('"');
+        }
+        None => message.push_str("null"),
+    }
+    message.push_str(r#","cacheKeySource":""#);
+    message.push_str(source);
+    message.push_str("\"}}\n");
+    message
+}
+
 /// JSON-RPC request structure
 #[derive(Serialize)]
 struct JsonRpcRequest {
@@ -174,7 +305,64 @@ struct JsonRpcRequest {
     params: serde_json::Value,
 }
 
-/// JSON-RPC response structure
+/// JSON-RPC notification structure (no id, no response expected)
+#[derive(Serialize)]
+struct JsonRpcNotification {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: serde_json::Value,
+}
+
+/// Notification delivering the MSAL cache key; always the first line the sidecar reads.
+const SESSION_KEY_METHOD: &str = "session/key";
+/// Notification asking the sidecar to abort an in-flight request.
+const CANCEL_METHOD: &str = "$/cancelRequest";
+/// Notification the sidecar sends while polling a device code sign-in.
+const DEVICE_CODE_PROGRESS_METHOD: &str = "device_code/progress";
+
+/// Version of the JSON-RPC contract with the sidecar. Bump it together with
+/// `PROTOCOL_VERSION` in sidecar/src/protocol.ts when either side changes incompatibly.
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Request exchanging versions and capabilities; always the first request after spawning.
+const HELLO_METHOD: &str = "hello";
+const HELLO_TIMEOUT: Duration = Duration::from_secs(15);
+/// JSON-RPC "method not found", returned by sidecars built before the handshake existed
+const METHOD_NOT_FOUND: i32 = -32601;
+/// Methods this build calls; a sidecar without all of them is a stale build.
+pub(crate) const REQUIRED_METHODS: &[&str] = &[
+    "acquire_app_token",
+    "acquire_obo_token",
+    "acquire_user_token",
+    "redeem_auth_code",
+    "start_device_code",
+    "cancel_device_code",
+    "clear_user_cache",
+    "get_user_accounts",
+    "get_auth_storage_status",
+    "validate_keyvault",
+    "get_credential_status",
+    "list_azure_subscriptions",
+    "list_azure_apps",
+    "list_keyvaults",
+    "list_keyvault_secrets",
+    "list_keyvault_certificates",
+];
+
+/// What the running sidecar reported in the `hello` handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarCapabilities {
+    pub protocol_version: u32,
+    /// Version of the app the sidecar bundle was built for
+    pub app_version: String,
+    /// Short hash of the sidecar bundle, to tell builds of the same version apart
+    pub build_hash: Option<String>,
+    pub node_version: Option<String>,
+    /// Every method the sidecar handles
+    pub methods: Vec<String>,
+}
+
+/// JSON-RPC response structure; notifications from the sidecar have a `method` and no `id`
 #[derive(Deserialize)]
 struct JsonRpcResponse {
     #[allow(dead_code)]
@@ -183,207 +371,195 @@ struct JsonRpcResponse {
     id: Option<u64>,
     result: Option<serde_json::Value>,
     error: Option<JsonRpcError>,
+    method: Option<String>,
+    params: Option<serde_json::Value>,
 }
 
 #[derive(Deserialize)]
 struct JsonRpcError {
-    #[allow(dead_code)]
     code: i32,
     message: String,
-    #[allow(dead_code)]
     data: Option<serde_json::Value>,
 }
 
+type RpcOutcome = Result<serde_json::Value, CommandError>;
+
+/// A caller waiting on a response, optionally tagged with a caller-chosen handle
+/// that `cancel_request` can refer to.
+struct PendingRequest {
+    handle: Option<String>,
+    sender: oneshot::Sender<RpcOutcome>,
+}
+
+/// In-flight requests keyed by JSON-RPC id. Shared between callers (who insert)
+/// and the stdout reader task (which removes and completes them).
+type PendingMap = Arc<std::sync::Mutex<HashMap<u64, PendingRequest>>>;
+
+/// What a caller of [`SidecarManager::start`] does once it has released the manager lock
+enum Startup {
+    /// Nothing to wait for; `send` reports a sidecar that isn't usable
+    Ready,
+    /// Another caller's handshake is in flight; the channel closes when it is done
+    Waiting(watch::Receiver<()>),
+    /// A process was just spawned; finish it with [`complete_handshake`]
+    Handshake(Handshake),
+}
+
+/// The `hello` request sent to a freshly spawned sidecar
+struct Handshake {
+    generation: u64,
+    id: u64,
+    receiver: oneshot::Receiver<RpcOutcome>,
+    script: PathBuf,
+    /// Tells the supervisor to kill the process if the handshake fails
+    stop: oneshot::Sender<()>,
+    /// Dropped when the handshake is done, which wakes the callers waiting for it
+    done: watch::Sender<()>,
+}
+
+/// Per-call options supplied by Tauri commands
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    /// Overrides the method's default deadline
+    pub timeout: Option<Duration>,
+    /// Caller-chosen handle used to cancel the request
+    pub request_id: Option<String>,
+}
+
+impl CallOptions {
+    pub fn new(timeout_ms: Option<u64>, request_id: Option<String>) -> Self {
+        Self {
+            timeout: timeout_ms.map(Duration::from_millis),
+            request_id,
+        }
+    }
+}
+
+/// Default deadline for a sidecar method when the caller does not supply one.
+fn default_timeout(method: &str) -> Duration {
+    match method {
+        // Browser sign-in waits happen in `auth_code`; these are cache lookups and code redemption.
+        "acquire_user_token"
+        | "redeem_auth_code"
+        | "acquire_app_token"
+        | "acquire_obo_token"
+        | "validate_keyvault"
+        | "get_credential_status" => Duration::from_secs(60),
+        // Returns once Entra issues the user code; polling continues after the response.
+        "start_device_code" => Duration::from_secs(60),
+        "list_azure_subscriptions"
+        | "list_azure_apps"
+        | "list_keyvaults"
+        | "list_keyvault_secrets"
+        | "list_keyvault_certificates" => Duration::from_secs(45),
+        _ => Duration::from_secs(30),
+    }
+}
+
+/// First delay before respawning a crashed sidecar; doubles on each consecutive crash.
+const RESTART_BASE_DELAY: Duration = Duration::from_millis(500);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(30);
+/// Consecutive crashes tolerated before automatic restarts stop; after that only
+/// [`restart`] starts the sidecar again.
+const MAX_CONSECUTIVE_RESTARTS: u32 = 5;
+/// A sidecar that stayed up this long is considered healthy again (resets the backoff).
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+/// Payload of the `sidecar-status` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarStatusEvent {
+    /// `running`, `restarting` or `failed`
+    pub state: &'static str,
+    pub pid: Option<u32>,
+    pub restart_count: u32,
+    pub last_exit_code: Option<i32>,
+    pub message: Option<String>,
+}
+
 /// Sidecar process manager
 pub struct SidecarManager {
-    pub child: Option<Child>,
+    /// PID of the running sidecar; the process itself is owned by its supervisor task
+    pid: Option<u32>,
+    stdin: Option<ChildStdin>,
     request_id: u64,
+    pending: PendingMap,
+    /// Incremented on every spawn so a supervisor never acts on a newer process
+    generation: u64,
+    started_at: Option<Instant>,
+    /// A respawn is waiting out its backoff delay
+    restart_scheduled: bool,
+    consecutive_crashes: u32,
+    /// Automatic restarts gave up; calls fail with `SIDECAR_CRASH_LOOP` until [`restart`]
+    crash_loop: bool,
+    /// Number of automatic restarts after unexpected exits
+    pub restart_count: u32,
+    /// Exit code of the most recent unexpected exit (`None` if killed by a signal)
+    pub last_exit_code: Option<i32>,
     /// Stores the last startup error for diagnostics
-    pub start_error: Option<String>,
+    pub start_error: Option<CommandError>,
+    /// Reported by the running sidecar's `hello` handshake
+    pub capabilities: Option<SidecarCapabilities>,
+    /// Set while the `hello` handshake of a fresh process is in flight
+    handshake: Option<watch::Receiver<()>>,
 }
 
 impl SidecarManager {
     pub fn new() -> Self {
         Self {
-            child: None,
+            pid: None,
+            stdin: None,
             request_id: 0,
+            pending: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            generation: 0,
+            started_at: None,
+            restart_scheduled: false,
+            consecutive_crashes: 0,
+            crash_loop: false,
+            restart_count: 0,
+            last_exit_code: None,
             start_error: None,
+            capabilities: None,
+            handshake: None,
         }
     }
 
-    /// Check if Node.js is available in PATH or common locations.
-    /// Supports macOS, Windows, and Linux with common version manager paths.
-    fn find_node_executable() -> Option<PathBuf> {
-        use std::process::Command as StdCommand;
-
-        // Platform-specific node executable name
-        #[cfg(target_os = "windows")]
-        let node_cmd = "node.exe";
-        #[cfg(not(target_os = "windows"))]
-        let node_cmd = "node";
-
-        // First try "node" directly (works if in PATH)
-        if StdCommand::new(node_cmd)
-            .arg("--version")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false)
-        {
-            return Some(PathBuf::from(node_cmd));
-        }
-
-        // Platform-specific common installation paths
-        #[cfg(target_os = "macos")]
-        let common_paths: Vec<PathBuf> = vec![
-            PathBuf::from("/usr/local/bin/node"),
-            PathBuf::from("/opt/homebrew/bin/node"),
-            PathBuf::from("/usr/bin/node"),
-        ];
-
-        #[cfg(target_os = "windows")]
-        let common_paths: Vec<PathBuf> = {
-            let mut paths = vec![
-                PathBuf::from("C:\\Program Files\\nodejs\\node.exe"),
-                PathBuf::from("C:\\Program Files (x86)\\nodejs\\node.exe"),
-            ];
-            // Add paths from environment variables
-            if let Ok(program_files) = std::env::var("ProgramFiles") {
-                paths.push(PathBuf::from(format!("{}\\nodejs\\node.exe", program_files)));
-            }
-            if let Ok(program_files_x86) = std::env::var("ProgramFiles(x86)") {
-                paths.push(PathBuf::from(format!("{}\\nodejs\\node.exe", program_files_x86)));
-            }
-            paths
-        };
-
-        #[cfg(target_os = "linux")]
-        let common_paths: Vec<PathBuf> = vec![
-            PathBuf::from("/usr/local/bin/node"),
-            PathBuf::from("/usr/bin/node"),
-            PathBuf::from("/snap/bin/node"),
-        ];
-
-        // Fallback for other Unix-like systems
-        #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
-        let common_paths: Vec<PathBuf> = vec![
-            PathBuf::from("/usr/local/bin/node"),
-            PathBuf::from("/usr/bin/node"),
-        ];
-
-        for path in common_paths {
-            if path.exists() {
-                return Some(path);
-            }
-        }
-
-        // Check home directory-based version managers
-        if let Some(home) = dirs::home_dir() {
-            // === nvm (Node Version Manager) - macOS/Linux ===
-            #[cfg(not(target_os = "windows"))]
-            {
-                let nvm_base = home.join(".nvm/versions/node");
-                if nvm_base.exists() {
-                    if let Ok(entries) = std::fs::read_dir(&nvm_base) {
-                        for entry in entries.flatten() {
-                            let node_bin = entry.path().join("bin/node");
-                            if node_bin.exists() {
-                                return Some(node_bin);
-                            }
-                        }
-                    }
-                }
-            }
-
-            // === nvm-windows ===
-            #[cfg(target_os = "windows")]
-            {
-                // nvm-windows stores in %APPDATA%\nvm
-                if let Ok(appdata) = std::env::var("APPDATA") {
-                    let nvm_base = PathBuf::from(appdata).join("nvm");
-                    if nvm_base.exists() {
-                        if let Ok(entries) = std::fs::read_dir(&nvm_base) {
-                            for entry in entries.flatten() {
-                                let node_bin = entry.path().join("node.exe");
-                                if node_bin.exists() {
-                                    return Some(node_bin);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            // === fnm (Fast Node Manager) ===
-            #[cfg(target_os = "windows")]
-            {
-                // fnm on Windows: %LOCALAPPDATA%\fnm_multishells or %APPDATA%\fnm\node-versions
-                if let Ok(localappdata) = std::env::var("LOCALAPPDATA") {
-                    let fnm_base = PathBuf::from(localappdata).join("fnm").join("node-versions");
-                    if fnm_base.exists() {
-                        if let Ok(entries) = std::fs::read_dir(&fnm_base) {
-                            for entry in entries.flatten() {
-                                let node_bin = entry.path().join("installation").join("node.exe");
-                                if node_bin.exists() {
-                                    return Some(node_bin);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-
-            #[cfg(not(target_os = "windows"))]
-            {
-                // fnm on Unix: ~/.local/share/fnm/node-versions/<version>/installation/bin/node
-                let fnm_base = home.join(".local/share/fnm/node-versions");
-                if fnm_base.exists() {
-                    if let Ok(entries) = std::fs::read_dir(&fnm_base) {
-                        for entry in entries.flatten() {
-                            let node_bin = entry.path().join("installation/bin/node");
-                            if node_bin.exists() {
-                                return Some(node_bin);
-                            }
-                        }
-                    }
-                }
-            }
-
-            // === Volta ===
-            #[cfg(target_os = "windows")]
-            let volta_node = home.join(".volta\\bin\\node.exe");
-            #[cfg(not(target_os = "windows"))]
-            let volta_node = home.join(".volta/bin/node");
-            if volta_node.exists() {
-                return Some(volta_node);
-            }
-
-            // === asdf (Unix only) ===
-            #[cfg(not(target_os = "windows"))]
-            {
-                let asdf_node = home.join(".asdf/shims/node");
-                if asdf_node.exists() {
-                    return Some(asdf_node);
-                }
-            }
+    pub fn is_running(&self) -> bool {
+        self.pid.is_some()
+    }
 
-            // === Homebrew on non-standard paths (Linux) ===
-            #[cfg(target_os = "linux")]
-            {
-                let linuxbrew = home.join(".linuxbrew/bin/node");
-                if linuxbrew.exists() {
-                    return Some(linuxbrew);
-                }
-            }
+    /// `running`, `restarting`, `failed` (see `start_error`) or `stopped` if never started
+    pub fn state(&self) -> &'static str {
+        if self.pid.is_some() {
+            "running"
+        } else if self.restart_scheduled {
+            "restarting"
+        } else if self.crash_loop || self.start_error.is_some() {
+            "failed"
+        } else {
+            "stopped"
         }
-
-        None
     }
 
+    /// Start the Node.js sidecar process and send its `hello` request
+    ///
+    /// The response is awaited by [`finish_startup`] after the manager lock has been
+    /// released, so a slow handshake doesn't block other callers.
+    ///
+    /// Boxed because the process supervisor spawned here calls `start` again to restart
+    /// it, and the compiler can't infer that the future is `Send` through that cycle.
+    fn start(&mut self) -> Pin<Box<dyn Future<Output = Result<Startup, CommandError>> + Send + '_>> {
+        Box::pin(self.spawn_process())
+    }
 
-    /// Start the Node.js sidecar process
-    pub async fn start(&mut self) -> Result<(), String> {
-        if self.child.is_some() {
-            return Ok(());
+    async fn spawn_process(&mut self) -> Result<Startup, CommandError> {
+        if let Some(done) = &self.handshake {
+            return Ok(Startup::Waiting(done.clone()));
+        }
+        // A crashed sidecar is respawned by its supervisor once the backoff delay passes,
+        // and one that keeps crashing only by an explicit `restart`.
+        if self.pid.is_some() || self.restart_scheduled || self.crash_loop {
+            return Ok(Startup::Ready);
         }
 
         // Clear any previous error
@@ -397,14 +573,17 @@ impl SidecarManager {
         let sidecar_script = {
             let exe_path = std::env::current_exe()
                 .map_err(|e| {
-                    let msg = format!("Failed to get executable path: {}", e);
-                    self.start_error = Some(msg.clone());
-                    msg
+                    let error = CommandError::unavailable(
+                        SIDECAR_SPAWN_FAILED,
+                        format!("Failed to get executable path: {}", e),
+                    );
+                    self.start_error = Some(error.clone());
+                    error
                 })?;
             let exe_dir = exe_path.parent().ok_or_else(|| {
-                let msg = "Failed to get parent directory".to_string();
-                self.start_error = Some(msg.clone());
-                msg
+                let error = CommandError::unavailable(SIDECAR_SPAWN_FAILED, "Failed to get parent directory");
+                self.start_error = Some(error.clone());
+                error
             })?;
 
             // Build list of paths to check
@@ -444,8 +623,9 @@ impl SidecarManager {
                              Make sure the sidecar is built (cd sidecar && npm run build)",
                             checked_paths.join(", ")
                         );
-                        self.start_error = Some(msg.clone());
-                        msg
+                        let error = CommandError::unavailable(SIDECAR_SCRIPT_NOT_FOUND, msg);
+                        self.start_error = Some(error.clone());
+                        error
                     })?;
                 log::info!("Using development sidecar path from workspace: {:?}", workspace_root);
                 workspace_root.join("sidecar").join("dist").join("index.cjs")
@@ -455,30 +635,12 @@ impl SidecarManager {
         log::info!("Starting sidecar from: {:?}", sidecar_script);
 
         // Find Node.js executable
-        let node_path = Self::find_node_executable().ok_or_else(|| {
-            // Platform-specific installation instructions
-            #[cfg(target_os = "macos")]
-            let install_hint = "Install via Homebrew: brew install node";
-            #[cfg(target_os = "windows")]
-            let install_hint = "Download from https://nodejs.org or install via: winget install OpenJS.NodeJS.LTS";
-            #[cfg(target_os = "linux")]
-            let install_hint = "Install via your package manager (apt install nodejs, dnf install nodejs, pacman -S nodejs, or snap install node --classic)";
-            #[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
-            let install_hint = "Download from https://nodejs.org";
-
-            let msg = format!(
-                "[NODE_NOT_FOUND] Node.js (version 20 or higher) is required but was not found. \
-                Please install Node.js and ensure it's in your PATH. {}. \
-                Download: https://nodejs.org",
-                install_hint
-            );
-            log::error!("{}", msg);
-            self.start_error = Some(msg.clone());
-            msg
+        let node = crate::node_runtime::select().inspect_err(|error| {
+            log::error!("{}", error.message());
+            self.start_error = Some(error.clone());
         })?;
-
-
-        log::info!("Using Node.js from: {:?}", node_path);
+        let node_path = PathBuf::from(&node.path);
+        log::info!("Using Node.js {} from: {:?}", node.version.as_deref().unwrap_or("?"), node_path);
 
         let mut command = Command::new(&node_path);
         command.arg(&sidecar_script);
@@ -487,13 +649,11 @@ impl SidecarManager {
             if let Some(dir) = &env.data_dir {
                 command.env("ENTRA_TOKEN_STUDIO_DATA_DIR", dir);
             }
-            if let Some(key) = &env.cache_key_b64 {
-                command.env("ENTRA_TOKEN_STUDIO_CACHE_KEY", key);
-            }
-            if let Some(source) = &env.cache_key_source {
-                command.env("ENTRA_TOKEN_STUDIO_CACHE_KEY_SOURCE", source);
-            }
         }
+        // The cache key is sent over stdin below; make sure an inherited copy can't leak
+        // through the environment (readable in /proc/<pid>/environ and by `az`).
+        command.env_remove("ENTRA_TOKEN_STUDIO_CACHE_KEY");
+        command.env_remove("ENTRA_TOKEN_STUDIO_CACHE_KEY_SOURCE");
 
         // Ensure common CLI paths are available to sidecar for Azure CLI discovery
         let path_additions = match std::env::consts::OS {
@@ -516,86 +676,652 @@ impl SidecarManager {
             command.creation_flags(CREATE_NO_WINDOW);
         }
 
-        let child = command
+        let mut child = command
+            .kill_on_drop(true)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
             .map_err(|e| {
-                let msg = if e.kind() == std::io::ErrorKind::NotFound {
-                    format!(
-                        "Node.js executable not found at {:?}. Please install Node.js and ensure it's in your PATH.",
-                        node_path
-                    )
+                let error = if e.kind() == std::io::ErrorKind::NotFound {
+                    CommandError::NodeMissing {
+                        message: format!(
+                            "Node.js executable not found at {:?}. Please install Node.js and ensure it's in your PATH.",
+                            node_path
+                        ),
+                    }
                 } else {
-                    format!("Failed to spawn sidecar from {:?}: {}", sidecar_script, e)
+                    CommandError::unavailable(
+                        SIDECAR_SPAWN_FAILED,
+                        format!("Failed to spawn sidecar from {:?}: {}", sidecar_script, e),
+                    )
                 };
-                log::error!("{}", msg);
-                self.start_error = Some(msg.clone());
-                msg
+                log::error!("{}", error);
+                self.start_error = Some(error.clone());
+                error
             })?;
 
         log::info!("Sidecar process started successfully (PID: {:?})", child.id());
-        self.child = Some(child);
-        Ok(())
+
+        if let Some(stderr) = child.stderr.take() {
+            tauri::async_runtime::spawn(drain_stderr(stderr, child.id()));
+        }
+
+        let stdout = child.stdout.take().ok_or_else(|| {
+            let error = CommandError::unavailable(SIDECAR_SPAWN_FAILED, "Sidecar stdout not available");
+            self.start_error = Some(error.clone());
+            error
+        })?;
+        let mut stdin = child.stdin.take().ok_or_else(|| {
+            let error = CommandError::unavailable(SIDECAR_SPAWN_FAILED, "Sidecar stdin not available");
+            self.start_error = Some(error.clone());
+            error
+        })?;
+        // Written before stdin is handed out, so it is the first line the sidecar reads and
+        // is handled before any request.
+        let handshake = session_key_message(SIDECAR_ENV.get());
+        let delivered = match stdin.write_all(handshake.as_bytes()).await {
+            Ok(()) => stdin.flush().await,
+            Err(e) => Err(e),
+        };
+        drop(handshake);
+        if let Err(e) = delivered {
+            let error = CommandError::unavailable(
+                SIDECAR_SPAWN_FAILED,
+                format!("Failed to send the session key to the sidecar: {}", e),
+            );
+            log::error!("{}", error);
+            self.start_error = Some(error.clone());
+            return Err(error);
+        }
+
+        let (stop, stop_receiver) = oneshot::channel();
+        self.generation += 1;
+        self.pid = child.id();
+        self.stdin = Some(stdin);
+        self.started_at = Some(Instant::now());
+
+        // The supervisor owns the process: its reader routes every response, `hello` included,
+        // to the caller by id (so any number of requests can be in flight) and it restarts
+        // the sidecar if the process exits.
+        let stdout = BufReader::new(stdout);
+        tauri::async_runtime::spawn(supervise(child, stdout, self.pending.clone(), self.generation, stop_receiver));
+
+        let params = serde_json::json!({ "protocolVersion": PROTOCOL_VERSION });
+        let (id, receiver) = match self.send(HELLO_METHOD, params, None).await {
+            Ok(sent) => sent,
+            Err(error) => {
+                let error = CommandError::unavailable(
+                    SIDECAR_SPAWN_FAILED,
+                    format!("Failed to send the handshake to the sidecar: {}", error.message()),
+                );
+                self.abandon(stop, error.clone());
+                return Err(error);
+            }
+        };
+        let (done, waiting) = watch::channel(());
+        self.handshake = Some(waiting);
+        Ok(Startup::Handshake(Handshake {
+            generation: self.generation,
+            id,
+            receiver,
+            script: sidecar_script,
+            stop,
+            done,
+        }))
+    }
+
+    /// Give up on a process whose handshake failed: kill it without restarting and keep the error.
+    fn abandon(&mut self, stop: oneshot::Sender<()>, error: CommandError) {
+        log::error!("{}", error);
+        // A mismatched build would fail the same way on every restart; bumping the generation
+        // keeps its supervisor from respawning it.
+        self.generation += 1;
+        self.pid = None;
+        self.stdin = None;
+        self.started_at = None;
+        let _ = stop.send(());
+        self.start_error = Some(error);
+    }
+
+    /// Record an unexpected exit, fail in-flight calls and decide whether to respawn.
+    /// Returns the backoff delay if a restart should be attempted.
+    fn handle_exit(&mut self, exit_code: Option<i32>) -> Option<Duration> {
+        let uptime = self.started_at.map(|t| t.elapsed()).unwrap_or_default();
+        self.pid = None;
+        self.stdin = None;
+        self.started_at = None;
+        self.capabilities = None;
+        self.last_exit_code = exit_code;
+
+        let exit_desc = match exit_code {
+            Some(code) => format!("exit code {}", code),
+            None => "terminated by signal".to_string(),
+        };
+
+        if uptime >= STABLE_UPTIME {
+            self.consecutive_crashes = 0;
+        }
+        self.consecutive_crashes += 1;
+
+        let restart = self.consecutive_crashes <= MAX_CONSECUTIVE_RESTARTS;
+        let message = if restart {
+            format!("Sidecar exited unexpectedly ({}) and was restarted; please retry", exit_desc)
+        } else {
+            format!(
+                "Sidecar exited unexpectedly ({}) and has crashed {} times in a row; automatic restarts stopped",
+                exit_desc, self.consecutive_crashes
+            )
+        };
+        log::error!("{}", message);
+
+        // Nothing new can be registered while we hold the manager lock and stdin is gone.
+        let orphaned: Vec<_> = self.pending.lock().unwrap().drain().collect();
+        for (_, request) in orphaned {
+            let _ = request.sender.send(Err(CommandError::SidecarRestarted { message: message.clone() }));
+        }
+        crate::device_code::fail_sidecar_flows(&message);
+
+        if !restart {
+            self.crash_loop = true;
+            self.start_error = Some(CommandError::unavailable(SIDECAR_CRASH_LOOP, message.clone()));
+            self.emit_status("failed", Some(message));
+            return None;
+        }
+
+        let exponent = (self.consecutive_crashes - 1).min(16);
+        let delay = RESTART_BASE_DELAY.saturating_mul(1 << exponent).min(RESTART_MAX_DELAY);
+        self.restart_scheduled = true;
+        self.emit_status("restarting", Some(message));
+        Some(delay)
+    }
+
+    fn emit_status(&self, state: &'static str, message: Option<String>) {
+        let Some(handle) = APP_HANDLE.get() else {
+            return;
+        };
+        let event = SidecarStatusEvent {
+            state,
+            pid: self.pid,
+            restart_count: self.restart_count,
+            last_exit_code: self.last_exit_code,
+            message,
+        };
+        if let Err(e) = handle.emit("sidecar-status", event) {
+            log::warn!("Failed to emit sidecar-status event: {}", e);
+        }
     }
 
-    /// Send a request to the sidecar and get a response
-    pub async fn call(
+    /// Write a request to the sidecar and return its id and a receiver for the response.
+    ///
+    /// Only this write needs exclusive access to the manager; the response is awaited
+    /// after the manager lock has been released (see [`call_with`]).
+    async fn send(
         &mut self,
         method: &str,
         params: serde_json::Value,
-    ) -> Result<serde_json::Value, String> {
+        handle: Option<String>,
+    ) -> Result<(u64, oneshot::Receiver<RpcOutcome>), CommandError> {
         // Provide detailed error when sidecar isn't running
-        if self.child.is_none() {
-            let base_error = "Sidecar not started";
-            if let Some(ref startup_error) = self.start_error {
-                return Err(format!("{}: {}", base_error, startup_error));
+        if self.pid.is_none() {
+            if self.restart_scheduled {
+                return Err(CommandError::unavailable(
+                    SIDECAR_RESTARTING,
+                    "Sidecar is restarting after an unexpected exit; please retry shortly",
+                ));
             }
-            return Err(base_error.to_string());
+            // Surface the startup failure itself so callers see e.g. `nodeMissing`.
+            return Err(self
+                .start_error
+                .clone()
+                .unwrap_or_else(|| CommandError::unavailable(SIDECAR_NOT_STARTED, "Sidecar not started")));
         }
-        
-        let child = self.child.as_mut().unwrap();
 
         self.request_id += 1;
+        let id = self.request_id;
         let request = JsonRpcRequest {
             jsonrpc: "2.0",
-            id: self.request_id,
+            id,
             method: method.to_string(),
             params,
         };
 
-        let request_json =
-            serde_json::to_string(&request).map_err(|e| format!("Failed to serialize: {}", e))?;
+        let request_json = serde_json::to_string(&request).map_err(|e| CommandError::transport(format!("Failed to serialize: {}", e)))?;
+
+        // Register before writing so a fast response can never miss its receiver.
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(id, PendingRequest { handle, sender });
+
+        if let Err(message) = self.write_line(&request_json).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(CommandError::transport(message));
+        }
 
-        // Write request to stdin
-        let stdin = child.stdin.as_mut().ok_or("Sidecar stdin not available")?;
+        Ok((id, receiver))
+    }
+
+    /// Fail the pending caller for `id` with `error` and tell the sidecar to stop working on it.
+    async fn abort(&mut self, id: u64, error: CommandError) {
+        if let Some(request) = self.pending.lock().unwrap().remove(&id) {
+            let _ = request.sender.send(Err(error));
+        }
+
+        let notification = JsonRpcNotification {
+            jsonrpc: "2.0",
+            method: CANCEL_METHOD,
+            params: serde_json::json!({ "id": id }),
+        };
+        if let Ok(json) = serde_json::to_string(&notification) {
+            if let Err(e) = self.write_line(&json).await {
+                log::warn!("Failed to send cancel notification for request {}: {}", id, e);
+            }
+        }
+    }
+
+    /// Cancel the in-flight request registered under a caller-chosen handle.
+    /// Returns `false` if no such request is pending.
+    pub async fn cancel(&mut self, handle: &str) -> bool {
+        let id = self
+            .pending
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, request)| request.handle.as_deref() == Some(handle))
+            .map(|(id, _)| *id);
+
+        match id {
+            Some(id) => {
+                self.abort(id, CommandError::cancelled()).await;
+                true
+            }
+            None => false,
+        }
+    }
+
+    async fn write_line(&mut self, json: &str) -> Result<(), String> {
+        let stdin = self.stdin.as_mut().ok_or("Sidecar stdin not available")?;
         stdin
-            .write_all(format!("{}\n", request_json).as_bytes())
+            .write_all(format!("{}\n", json).as_bytes())
             .await
             .map_err(|e| format!("Failed to write to sidecar: {}", e))?;
-        stdin.flush().await.map_err(|e| format!("Failed to flush: {}", e))?;
-
-        // Read response from stdout
-        let stdout = child.stdout.as_mut().ok_or("Sidecar stdout not available")?;
-        let mut reader = BufReader::new(stdout);
-        let mut line = String::new();
-        reader
-            .read_line(&mut line)
-            .await
-            .map_err(|e| format!("Failed to read from sidecar: {}", e))?;
+        stdin.flush().await.map_err(|e| format!("Failed to flush: {}", e))
+    }
+}
+
+/// Wait for the `hello` response of a freshly spawned sidecar, so a stale `sidecar/dist`
+/// fails at startup with a clear error instead of answering another schema.
+async fn complete_handshake(handshake: Handshake) -> Result<(), CommandError> {
+    let Handshake { generation, id, receiver, script, stop, done } = handshake;
+    let failed = |detail: String| CommandError::unavailable(SIDECAR_SPAWN_FAILED, detail);
+
+    let outcome = tokio::time::timeout(HELLO_TIMEOUT, receiver).await;
+    let mut manager = SIDECAR.lock().await;
+    manager.handshake = None;
+    let result = match outcome {
+        Ok(Ok(outcome)) => handshake_result(outcome, &script),
+        Ok(Err(_)) => Err(failed("The sidecar exited during the handshake; see the sidecar logs".to_string())),
+        Err(_) => {
+            manager.pending.lock().unwrap().remove(&id);
+            Err(failed(format!(
+                "The sidecar didn't answer the handshake within {} seconds",
+                HELLO_TIMEOUT.as_secs()
+            )))
+        }
+    };
+    drop(done);
 
-        let response: JsonRpcResponse =
-            serde_json::from_str(&line).map_err(|e| format!("Failed to parse response: {}", e))?;
+    // The process exited meanwhile and its supervisor has already handled that.
+    if manager.generation != generation || manager.pid.is_none() {
+        return result.map(|_| ());
+    }
+    match result {
+        Ok(capabilities) => {
+            log::info!(
+                "Sidecar speaks protocol version {} (built for app version {}, build {})",
+                capabilities.protocol_version,
+                capabilities.app_version,
+                capabilities.build_hash.as_deref().unwrap_or("unknown")
+            );
+            manager.capabilities = Some(capabilities);
+            manager.emit_status("running", None);
+            Ok(())
+        }
+        Err(error) => {
+            manager.abandon(stop, error.clone());
+            Err(error)
+        }
+    }
+}
 
-        if let Some(error) = response.error {
-            return Err(error.message);
+/// The capabilities in a `hello` response, if this build can use the sidecar that sent it
+fn handshake_result(outcome: RpcOutcome, script: &Path) -> Result<SidecarCapabilities, CommandError> {
+    let mismatch = |detail: String| {
+        CommandError::unavailable(
+            SIDECAR_PROTOCOL_MISMATCH,
+            format!(
+                "The sidecar at {} doesn't match this build of the app: {}. Rebuild it with `pnpm sidecar:build`.",
+                script.display(),
+                detail
+            ),
+        )
+    };
+
+    let value = match outcome {
+        Ok(value) => value,
+        Err(CommandError::Rpc { code, message, .. }) => {
+            return Err(mismatch(if code == METHOD_NOT_FOUND {
+                "it was built before the protocol handshake existed".to_string()
+            } else {
+                format!("the handshake failed ({})", message)
+            }))
         }
+        Err(error) => return Err(error),
+    };
+    let capabilities: SidecarCapabilities = serde_json::from_value(value)
+        .map_err(|e| mismatch(format!("its handshake response is malformed ({})", e)))?;
+    check_capabilities(&capabilities).map_err(mismatch)?;
+    Ok(capabilities)
+}
 
-        // Some sidecar handlers intentionally return `void`/`undefined`.
-        // JSON-RPC permits a `null` result for such methods; treat a missing result field
-        // as null for robustness (avoids false-negative errors on successful calls).
-        Ok(response.result.unwrap_or(serde_json::Value::Null))
+/// Why a sidecar that answered `hello` with `capabilities` can't be used by this build
+fn check_capabilities(capabilities: &SidecarCapabilities) -> Result<(), String> {
+    if capabilities.protocol_version != PROTOCOL_VERSION {
+        return Err(format!(
+            "it speaks protocol version {}, but this app needs version {}",
+            capabilities.protocol_version, PROTOCOL_VERSION
+        ));
+    }
+    if capabilities.app_version != env!("CARGO_PKG_VERSION") {
+        return Err(format!(
+            "it was built for app version {}, but this is version {}",
+            capabilities.app_version,
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+    let missing: Vec<&str> = REQUIRED_METHODS
+        .iter()
+        .copied()
+        .filter(|method| !capabilities.methods.iter().any(|m| m == method))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("it doesn't handle {}", missing.join(", ")));
+    }
+    Ok(())
+}
+
+/// Read newline-delimited JSON-RPC responses from the sidecar and complete the matching
+/// pending request. Other output (e.g. a runtime warning) is logged and skipped. Runs until
+/// the sidecar closes stdout.
+async fn read_responses<R: AsyncBufRead + Unpin>(mut reader: R, pending: PendingMap) {
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Failed to read from sidecar: {}", e);
+                break;
+            }
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let response: JsonRpcResponse = match serde_json::from_str(trimmed) {
+            Ok(response) => response,
+            Err(e) => {
+                log::warn!("Ignoring non-JSON-RPC sidecar output ({}): {}", e, trimmed);
+                continue;
+            }
+        };
+
+        let Some(id) = response.id else {
+            if let Some(method) = response.method {
+                handle_notification(&method, response.params.unwrap_or_default());
+                continue;
+            }
+            if let Some(error) = response.error {
+                log::warn!("Sidecar reported an error without a request id: {}", error.message);
+            }
+            continue;
+        };
+
+        let Some(request) = pending.lock().unwrap().remove(&id) else {
+            // Responses to cancelled or timed-out requests land here.
+            log::debug!("Received sidecar response for unknown request id {}", id);
+            continue;
+        };
+
+        let outcome = match response.error {
+            Some(error) => Err(CommandError::Rpc {
+                code: error.code,
+                message: error.message,
+                data: error.data,
+            }),
+            // Some sidecar handlers intentionally return `void`/`undefined`.
+            // JSON-RPC permits a `null` result for such methods; treat a missing result field
+            // as null for robustness (avoids false-negative errors on successful calls).
+            None => Ok(response.result.unwrap_or(serde_json::Value::Null)),
+        };
+        // The caller may have gone away; nothing to do in that case.
+        let _ = request.sender.send(outcome);
+    }
+}
+
+/// Route a notification from the sidecar.
+fn handle_notification(method: &str, params: serde_json::Value) {
+    match method {
+        DEVICE_CODE_PROGRESS_METHOD => match serde_json::from_value(params) {
+            Ok(progress) => crate::device_code::publish(progress),
+            Err(e) => log::error!("Sidecar sent a malformed '{}' notification: {}", method, e),
+        },
+        _ => log::debug!("Ignoring unknown sidecar notification '{}'", method),
+    }
+}
+
+/// Maximum number of sidecar stderr lines kept in memory for diagnostics
+const LOG_BUFFER_CAPACITY: usize = 1000;
+/// Longer stderr lines are truncated while they are read
+const MAX_LOG_LINE_BYTES: usize = 8 * 1024;
+
+/// A single line the sidecar wrote to stderr
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarLogLine {
+    /// Monotonic sequence number, usable as a cursor for `get_sidecar_logs`
+    pub seq: u64,
+    /// Milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// `info`, `warn` or `error` (inferred from the line content)
+    pub level: &'static str,
+    pub pid: Option<u32>,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct LogBuffer {
+    lines: VecDeque<SidecarLogLine>,
+    next_seq: u64,
+}
+
+lazy_static::lazy_static! {
+    static ref SIDECAR_LOGS: std::sync::Mutex<LogBuffer> = std::sync::Mutex::new(LogBuffer::default());
+}
+
+fn infer_log_level(message: &str) -> &'static str {
+    let lower = message.to_ascii_lowercase();
+    if lower.contains("error") || lower.contains("exception") {
+        "error"
+    } else if lower.contains("warn") {
+        "warn"
+    } else {
+        "info"
+    }
+}
+
+/// Read one line into `buf`, keeping at most `limit` bytes of it. The rest of a longer line
+/// is skipped without being buffered. Returns the number of bytes consumed (0 at EOF).
+async fn read_capped_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    limit: usize,
+) -> std::io::Result<usize> {
+    let mut consumed = (&mut *reader).take(limit as u64).read_until(b'\n', buf).await?;
+    if consumed < limit || buf.last() == Some(&b'\n') {
+        return Ok(consumed);
+    }
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(consumed);
+        }
+        let (skip, end_of_line) = match available.iter().position(|&b| b == b'\n') {
+            Some(index) => (index + 1, true),
+            None => (available.len(), false),
+        };
+        reader.consume(skip);
+        consumed += skip;
+        if end_of_line {
+            return Ok(consumed);
+        }
+    }
+}
+
+/// Continuously read sidecar stderr so the pipe never fills up, keeping the most recent
+/// lines in a ring buffer, forwarding them to `log` and emitting `sidecar-log` events.
+async fn drain_stderr(stderr: ChildStderr, pid: Option<u32>) {
+    let mut reader = BufReader::new(stderr);
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match read_capped_line(&mut reader, &mut buf, MAX_LOG_LINE_BYTES).await {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                log::warn!("Failed to read sidecar stderr: {}", e);
+                break;
+            }
+        }
+
+        let message = String::from_utf8_lossy(&buf).trim_end().to_string();
+        if message.is_empty() {
+            continue;
+        }
+
+        let level = infer_log_level(&message);
+        match level {
+            "error" => log::error!("[sidecar] {}", message),
+            "warn" => log::warn!("[sidecar] {}", message),
+            _ => log::info!("[sidecar] {}", message),
+        }
+
+        let line = {
+            let mut logs = SIDECAR_LOGS.lock().unwrap();
+            let line = SidecarLogLine {
+                seq: logs.next_seq,
+                timestamp: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or_default(),
+                level,
+                pid,
+                message,
+            };
+            logs.next_seq += 1;
+            if logs.lines.len() == LOG_BUFFER_CAPACITY {
+                logs.lines.pop_front();
+            }
+            logs.lines.push_back(line.clone());
+            line
+        };
+
+        if let Some(handle) = APP_HANDLE.get() {
+            let _ = handle.emit("sidecar-log", line);
+        }
+    }
+}
+
+/// Buffered sidecar stderr lines with `seq >= since`, oldest first, at most `limit` of the newest.
+pub fn recent_logs(since: Option<u64>, limit: Option<usize>) -> Vec<SidecarLogLine> {
+    let logs = SIDECAR_LOGS.lock().unwrap();
+    let matching: Vec<_> = logs
+        .lines
+        .iter()
+        .filter(|line| since.map_or(true, |since| line.seq >= since))
+        .cloned()
+        .collect();
+    let skip = limit.map(|limit| matching.len().saturating_sub(limit)).unwrap_or(0);
+    matching.into_iter().skip(skip).collect()
+}
+
+/// Own a sidecar process for its lifetime: route its responses, then on exit fail the
+/// in-flight calls and respawn it with exponential backoff.
+async fn supervise(
+    mut child: Child,
+    stdout: BufReader<ChildStdout>,
+    pending: PendingMap,
+    generation: u64,
+    stop: oneshot::Receiver<()>,
+) {
+    tokio::select! {
+        _ = read_responses(stdout, pending) => {}
+        Ok(()) = stop => {
+            if let Err(e) = child.kill().await {
+                log::warn!("Failed to kill sidecar: {}", e);
+            }
+        }
+    }
+
+    let exit_code = match child.wait().await {
+        Ok(status) => status.code(),
+        Err(e) => {
+            log::error!("Failed to wait for sidecar exit: {}", e);
+            None
+        }
+    };
+
+    let delay = {
+        let mut manager = SIDECAR.lock().await;
+        if manager.generation != generation {
+            return;
+        }
+        manager.handle_exit(exit_code)
+    };
+
+    let Some(delay) = delay else {
+        return;
+    };
+    log::info!("Restarting sidecar in {:?}", delay);
+    tokio::time::sleep(delay).await;
+
+    let startup = {
+        let mut manager = SIDECAR.lock().await;
+        if manager.generation != generation || !manager.restart_scheduled {
+            return;
+        }
+        manager.restart_scheduled = false;
+        manager.restart_count += 1;
+        manager.start().await
+    };
+    let started = match startup {
+        Ok(startup) => finish_startup(startup).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = started {
+        log::error!("Failed to restart sidecar: {}", e);
+        // A sidecar that crashed during the handshake has already been scheduled for another restart.
+        let manager = SIDECAR.lock().await;
+        if manager.state() == "failed" {
+            manager.emit_status("failed", Some(e.to_string()));
+        }
     }
 }
 
@@ -606,12 +1332,210 @@ lazy_static::lazy_static! {
 
 /// Initialize and get the sidecar manager
 pub async fn get_sidecar() -> Arc<Mutex<SidecarManager>> {
-    let sidecar = SIDECAR.clone();
-    {
+    let startup = SIDECAR.lock().await.start().await;
+    let started = match startup {
+        Ok(startup) => finish_startup(startup).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = started {
+        log::error!("Failed to start sidecar: {}", e);
+    }
+    SIDECAR.clone()
+}
+
+/// Wait, without holding the manager lock, until a sidecar started by [`SidecarManager::start`]
+/// has answered its handshake.
+async fn finish_startup(startup: Startup) -> Result<(), CommandError> {
+    match startup {
+        Startup::Ready => Ok(()),
+        // A failed handshake is reported by the next `send` as the startup error.
+        Startup::Waiting(mut done) => {
+            let _ = done.changed().await;
+            Ok(())
+        }
+        Startup::Handshake(handshake) => complete_handshake(handshake).await,
+    }
+}
+
+/// Start the sidecar again after automatic restarts gave up (or a startup failure).
+///
+/// The crash count starts over, so a sidecar that crashes again gets the usual backoff
+/// restarts before giving up once more. Does nothing if it is running or about to restart.
+pub async fn restart() -> Result<(), CommandError> {
+    let startup = {
+        let mut manager = SIDECAR.lock().await;
+        if manager.crash_loop {
+            log::info!("Restarting sidecar on request");
+            manager.crash_loop = false;
+            manager.consecutive_crashes = 0;
+        }
+        manager.start().await?
+    };
+    finish_startup(startup).await
+}
+
+/// Send a request to the sidecar and wait for its response using the method's default deadline.
+pub async fn call(method: &str, params: serde_json::Value) -> Result<serde_json::Value, CommandError> {
+    call_with(method, params, CallOptions::default()).await
+}
+
+/// Send a request to the sidecar and wait for its response.
+///
+/// The manager lock is held only while the request is written, so slow calls (e.g. an
+/// interactive browser login) do not block other commands. If the deadline passes, the
+/// sidecar is told to cancel the request and the caller gets [`CommandError::Timeout`].
+pub async fn call_with(
+    method: &str,
+    params: serde_json::Value,
+    options: CallOptions,
+) -> Result<serde_json::Value, CommandError> {
+    let timeout = options.timeout.unwrap_or_else(|| default_timeout(method));
+    let sidecar = get_sidecar().await;
+    let (id, receiver) = {
         let mut manager = sidecar.lock().await;
-        if let Err(e) = manager.start().await {
-            log::error!("Failed to start sidecar: {}", e);
+        manager.send(method, params, options.request_id).await?
+    };
+
+    match tokio::time::timeout(timeout, receiver).await {
+        Ok(Ok(outcome)) => outcome,
+        Ok(Err(_)) => Err(CommandError::transport("Sidecar exited before responding")),
+        Err(_) => {
+            let error = CommandError::timeout(method, timeout.as_millis() as u64);
+            sidecar.lock().await.abort(id, error.clone()).await;
+            Err(error)
+        }
+    }
+}
+
+/// Like [`call_with`], but deserializes the result into `T`.
+///
+/// A result that doesn't match `T` means the Rust and Node sides disagree about the
+/// method's schema; it is logged and returned as [`CommandError::Contract`].
+pub async fn call_typed<T: DeserializeOwned>(
+    method: &str,
+    params: serde_json::Value,
+    options: CallOptions,
+) -> Result<T, CommandError> {
+    let value = call_with(method, params, options).await?;
+    serde_json::from_value(value).map_err(|e| {
+        log::error!("Sidecar result for '{}' does not match the expected schema: {}", method, e);
+        CommandError::Contract {
+            method: method.to_string(),
+            message: format!("Unexpected response from sidecar method '{}': {}", method, e),
+        }
+    })
+}
+
+/// Cancel an in-flight request by the handle its caller supplied.
+pub async fn cancel(request_id: &str) -> bool {
+    SIDECAR.lock().await.cancel(request_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn crash_loop_blocks_respawn_until_restart() {
+        let mut manager = SidecarManager::new();
+        assert_eq!(manager.state(), "stopped");
+        for _ in 0..MAX_CONSECUTIVE_RESTARTS {
+            assert!(manager.handle_exit(Some(1)).is_some());
+            assert_eq!(manager.state(), "restarting");
+            manager.restart_scheduled = false;
         }
+        assert_eq!(manager.handle_exit(Some(1)), None);
+        assert!(manager.crash_loop);
+        assert_eq!(manager.state(), "failed");
+
+        // A plain start (what every call does) must not bring it back.
+        assert!(matches!(manager.start().await.unwrap(), Startup::Ready));
+        assert!(!manager.is_running());
+        assert_eq!(manager.start_error.as_ref().and_then(CommandError::code), Some(SIDECAR_CRASH_LOOP));
+        let error = manager.send("get_credential_status", serde_json::json!({}), None).await.unwrap_err();
+        assert_eq!(error.code(), Some(SIDECAR_CRASH_LOOP));
+    }
+
+    fn current_capabilities() -> SidecarCapabilities {
+        SidecarCapabilities {
+            protocol_version: PROTOCOL_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            build_hash: Some("4f1c9a0be27d".to_string()),
+            node_version: Some("22.11.0".to_string()),
+            methods: REQUIRED_METHODS.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn handshake_accepts_a_matching_build() {
+        assert_eq!(check_capabilities(&current_capabilities()), Ok(()));
+    }
+
+    #[test]
+    fn handshake_rejects_mismatched_builds() {
+        let mut capabilities = current_capabilities();
+        capabilities.protocol_version = PROTOCOL_VERSION + 1;
+        assert!(check_capabilities(&capabilities).unwrap_err().contains("protocol version"));
+
+        let mut capabilities = current_capabilities();
+        capabilities.app_version = "0.0.1".to_string();
+        assert!(check_capabilities(&capabilities).unwrap_err().contains("app version 0.0.1"));
+
+        let mut capabilities = current_capabilities();
+        capabilities.methods.retain(|m| m != "redeem_auth_code");
+        assert_eq!(check_capabilities(&capabilities), Err("it doesn't handle redeem_auth_code".to_string()));
+    }
+
+    #[test]
+    fn handshake_reports_the_build_hash() {
+        let capabilities: SidecarCapabilities =
+            serde_json::from_str(include_str!("../tests/fixtures/sidecar/hello.json")).unwrap();
+        assert_eq!(capabilities.build_hash.as_deref(), Some("4f1c9a0be27d"));
+
+        // A bundle that can't read itself reports no hash; that alone doesn't make it unusable.
+        let mut capabilities = current_capabilities();
+        capabilities.build_hash = None;
+        assert_eq!(check_capabilities(&capabilities), Ok(()));
+
+        let mut without_hash = serde_json::to_value(current_capabilities()).unwrap();
+        without_hash.as_object_mut().unwrap().remove("buildHash");
+        let capabilities: SidecarCapabilities = serde_json::from_value(without_hash).unwrap();
+        assert_eq!(capabilities.build_hash, None);
+    }
+
+    #[tokio::test]
+    async fn responses_skip_stray_stdout_lines() {
+        let pending: PendingMap = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let (sender, receiver) = oneshot::channel();
+        pending.lock().unwrap().insert(7, PendingRequest { handle: None, sender });
+
+        let stdout = concat!(
+            "(node:4242) [DEP0040] DeprecationWarning: The `punycode` module is deprecated.\n",
+            "{\"notice\":\"not a JSON-RPC message\"}\n",
+            "\n",
+            "{\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"protocolVersion\":1}}\n",
+        );
+        read_responses(stdout.as_bytes(), pending.clone()).await;
+
+        assert_eq!(receiver.await.unwrap().unwrap(), serde_json::json!({ "protocolVersion": 1 }));
+        assert!(pending.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn long_stderr_lines_are_capped_while_reading() {
+        let input = format!("{}\nshort\n{}", "x".repeat(100), "y".repeat(20));
+        let mut reader = input.as_bytes();
+        let mut buf = Vec::new();
+
+        assert_eq!(read_capped_line(&mut reader, &mut buf, 10).await.unwrap(), 101);
+        assert_eq!(buf, b"xxxxxxxxxx");
+        buf.clear();
+        assert_eq!(read_capped_line(&mut reader, &mut buf, 10).await.unwrap(), 6);
+        assert_eq!(buf, b"short\n");
+        buf.clear();
+        assert_eq!(read_capped_line(&mut reader, &mut buf, 10).await.unwrap(), 20);
+        assert_eq!(buf, b"yyyyyyyyyy");
+        buf.clear();
+        assert_eq!(read_capped_line(&mut reader, &mut buf, 10).await.unwrap(), 0);
     }
-    sidecar
 }
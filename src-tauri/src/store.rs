@@ -0,0 +1,64 @@
+//! JSON files in the app data directory (saved apps, settings, git credential rules).
+//!
+//! Files are written to a temporary sibling and renamed into place, so a concurrent
+//! reader (the CLI, the git credential helper) never sees a truncated file.
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::CommandError;
+
+/// Replace the file at `path` with `value`, creating its directory if needed.
+/// `what` names the contents in error messages, e.g. `saved apps`.
+pub fn write_json_atomic<T: Serialize + ?Sized>(path: &Path, value: &T, what: &str) -> Result<(), CommandError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| CommandError::io(dir, e))?;
+    }
+    let json = serde_json::to_string_pretty(value).map_err(|e| CommandError::Io {
+        path: path.display().to_string(),
+        message: format!("Failed to serialize {}: {}", what, e),
+    })?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json).map_err(|e| CommandError::io(&tmp_path, e))?;
+    std::fs::rename(&tmp_path, path).map_err(|e| CommandError::io(path, e))
+}
+
+/// Read the file at `path`; the default value if it doesn't exist yet.
+pub fn read_json_or_default<T: DeserializeOwned + Default>(path: &Path, what: &str) -> Result<T, CommandError> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => return Err(CommandError::io(path, e)),
+    };
+    serde_json::from_str(&json).map_err(|e| CommandError::Io {
+        path: path.display().to_string(),
+        message: format!("{} is not a valid {} file: {}", path.display(), what, e),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_and_missing_file() {
+        let dir = std::env::temp_dir().join(format!("entra-token-studio-{}-store", std::process::id()));
+        let path = dir.join("nested").join("values.json");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let missing: Vec<String> = read_json_or_default(&path, "values").unwrap();
+        assert!(missing.is_empty());
+
+        write_json_atomic(&path, &["a", "b"], "values").unwrap();
+        let values: Vec<String> = read_json_or_default(&path, "values").unwrap();
+        assert_eq!(values, ["a", "b"]);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        std::fs::write(&path, "{").unwrap();
+        let error = read_json_or_default::<Vec<String>>(&path, "values").unwrap_err();
+        assert!(error.message().contains("is not a valid values file"), "{}", error);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -25,6 +25,18 @@ export const historyService = {
     }
   },
 
+  async updateHistoryItem(timestamp: number, changes: Partial<HistoryItem>): Promise<HistoryItem[]> {
+    if (typeof window === 'undefined') return [];
+    try {
+      const currentHistory = (await get<HistoryItem[]>(HISTORY_KEY)) || [];
+      const newHistory = currentHistory.map((item) => (item.timestamp === timestamp ? { ...item, ...changes } : item));
+      await set(HISTORY_KEY, newHistory);
+      return newHistory;
+    } catch (error) {
+      return [];
+    }
+  },
+
   async clearHistory(): Promise<void> {
     if (typeof window === 'undefined') return;
     try {
@@ -6,7 +6,7 @@
  */
 
 import { isTauriMode as isTauriRuntime } from '$lib/utils/runtime';
-import type { KeyVaultConfig } from '$lib/types';
+import type { AppConfig, CredentialSource, KeyVaultConfig, TokenBackendKind } from '$lib/types';
 
 // Types for token responses
 interface TokenResponse {
@@ -30,6 +30,10 @@ interface TokenAppConfig {
   clientId: string;
   tenantId: string;
   keyVault: KeyVaultConfig;
+  credential?: CredentialSource;
+  backend?: TokenBackendKind;
+  authorityHost?: string;
+  assertionAlgorithm?: 'RS256' | 'PS256';
 }
 
 interface ValidationResult {
@@ -56,9 +60,55 @@ interface AzureCliResult<T> {
 }
 
 interface SidecarHealth {
+  /** The sidecar process; `notUsed` when the default backend doesn't need it */
+  sidecar?: 'running' | 'restarting' | 'failed' | 'stopped' | 'notUsed';
+  /** Whether the sidecar process is up */
   running: boolean;
   error: string | null;
-  errorCode?: 'NODE_NOT_FOUND' | 'SIDECAR_SCRIPT_NOT_FOUND' | null;
+  errorCode?:
+    | 'NODE_NOT_FOUND'
+    | 'NODE_INCOMPATIBLE'
+    | 'SIDECAR_SCRIPT_NOT_FOUND'
+    | 'SIDECAR_SPAWN_FAILED'
+    | 'SIDECAR_CRASH_LOOP'
+    | 'SIDECAR_PROTOCOL_MISMATCH'
+    | null;
+  /** Full structured startup error, if any */
+  errorDetail?: CommandErrorPayload | null;
+  /** Automatic restarts after unexpected sidecar exits; null when the sidecar isn't used */
+  restartCount?: number | null;
+  /** Exit code of the most recent unexpected exit (null if killed by a signal or the sidecar isn't used) */
+  lastExitCode?: number | null;
+  /** Default token backend; the sidecar isn't started when this is `demo` */
+  backend?: TokenBackendKind;
+  /** Sidecar protocol version this build of the app speaks */
+  protocolVersion?: number;
+  /** Hash of the running sidecar bundle, from its handshake; null when unknown or not running */
+  buildHash?: string | null;
+  /** Reported by the running sidecar's handshake; null when it isn't running */
+  capabilities?: SidecarCapabilities | null;
+}
+
+/** What the sidecar reported in its `hello` handshake */
+export interface SidecarCapabilities {
+  protocolVersion: number;
+  /** Version of the app the sidecar bundle was built for */
+  appVersion: string;
+  /** Short hash of the sidecar bundle, to tell builds of the same version apart */
+  buildHash: string | null;
+  nodeVersion: string | null;
+  methods: string[];
+}
+
+/**
+ * Payload of the `sidecar-status` event emitted when the sidecar starts, crashes or gives up.
+ */
+export interface SidecarStatusEvent {
+  state: 'running' | 'restarting' | 'failed';
+  pid: number | null;
+  restartCount: number;
+  lastExitCode: number | null;
+  message: string | null;
 }
 
 
@@ -99,14 +149,103 @@ interface AzureVaultCredential {
 }
 
 /**
- * Dynamically import Tauri invoke function only when in Tauri environment
+ * Per-call options for sidecar-backed commands (desktop only).
+ */
+export interface SidecarCallOptions {
+  /** Override the command's default deadline, in milliseconds. */
+  timeoutMs?: number;
+  /** Caller-chosen handle that can later be passed to `cancelRequest`. */
+  requestId?: string;
+  /** Token backend override for user token requests (app tokens take it from the config). */
+  backend?: TokenBackendKind;
+}
+
+/**
+ * Failure categories reported by desktop commands.
+ */
+export type CommandErrorKind =
+  | 'sidecarUnavailable'
+  | 'nodeMissing'
+  | 'nodeIncompatible'
+  | 'transport'
+  | 'network'
+  | 'io'
+  | 'keyring'
+  | 'random'
+  | 'sidecarRestarted'
+  | 'timeout'
+  | 'cancelled'
+  | 'rpc'
+  | 'contract'
+  | 'validation';
+
+/**
+ * Serialized form of the Rust `CommandError`.
+ */
+export interface CommandErrorPayload {
+  kind: CommandErrorKind;
+  message: string;
+  /** Startup failure code (`sidecarUnavailable`) or JSON-RPC error code (`rpc`) */
+  code?: string | number;
+  /** Structured detail attached by the sidecar (`rpc`), e.g. a Key Vault error */
+  data?: unknown;
+  /** Method and deadline (`timeout`) */
+  method?: string;
+  timeoutMs?: number;
+  /** Rejected argument (`validation`) */
+  field?: string;
+}
+
+/**
+ * Error raised by a Tauri command. `kind` identifies the failure
+ * (e.g. `timeout`, `cancelled`, `rpc`) so callers don't need to match on messages.
+ */
+export class TauriCommandError extends Error {
+  readonly kind: CommandErrorKind;
+  readonly payload: CommandErrorPayload;
+
+  constructor(payload: CommandErrorPayload) {
+    super(payload.message);
+    this.name = 'TauriCommandError';
+    this.kind = payload.kind;
+    this.payload = payload;
+  }
+
+  /** Structured detail from the sidecar, if any */
+  get data(): unknown {
+    return this.payload.data;
+  }
+}
+
+function toCommandError(err: unknown): Error {
+  if (err instanceof Error) return err;
+  if (typeof err === 'string') return new Error(err);
+  if (err && typeof err === 'object' && 'kind' in err) {
+    const payload = err as CommandErrorPayload;
+    return new TauriCommandError({
+      ...payload,
+      message: typeof payload.message === 'string' ? payload.message : 'Desktop command failed',
+    });
+  }
+  return new Error('Desktop command failed');
+}
+
+/**
+ * Dynamically import Tauri invoke function only when in Tauri environment.
+ * Rejections are normalized to `Error` instances.
  */
 async function getTauriInvoke() {
   if (!isTauriRuntime()) {
     throw new Error('Not running in Tauri environment');
   }
   const { invoke } = await import('@tauri-apps/api/core');
-  return invoke;
+  return async <T>(command: string, args?: Record<string, unknown>): Promise<T> => {
+    try {
+      return await invoke<T>(command, args);
+    } catch (err) {
+      throw toCommandError(err);
+    }
+  };
 }
 
 /**
@@ -115,13 +254,14 @@ async function getTauriInvoke() {
  */
 export async function acquireAppToken(
   config: TokenAppConfig,
-  resource: string
+  resource: string,
+  options?: SidecarCallOptions
 ): Promise<TokenResponse> {
   if (isTauriRuntime()) {
     const invoke = await getTauriInvoke();
     const normalized = resource.trim().replace(/\/+$/, '');
     const scope = normalized.endsWith('/.default') ? normalized : `${normalized}/.default`;
-    return invoke('acquire_app_token', { config, scopes: [scope] });
+    return invoke('acquire_app_token', { config, scopes: [scope], ...options });
   }
 
   // Web mode: use HTTP API
@@ -155,10 +295,13 @@ export async function acquireAppToken(
 /**
  * Validate Key Vault connectivity and credentials
  */
-export async function validateKeyVault(config: KeyVaultConfig): Promise<ValidationResult> {
+export async function validateKeyVault(
+  config: KeyVaultConfig,
+  options?: SidecarCallOptions
+): Promise<ValidationResult> {
   if (isTauriRuntime()) {
     const invoke = await getTauriInvoke();
-    return invoke('validate_keyvault', { config });
+    return invoke('validate_keyvault', { config, ...options });
   }
 
   // Web mode: use HTTP API
@@ -185,6 +328,46 @@ export async function validateKeyVault(config: KeyVaultConfig): Promise<Validati
   };
 }
 
+/**
+ * Validate a local credential: the file can be read (PFX password from the keyring) and the
+ * certificate is within its validity period, a client secret is stored, or a federated
+ * assertion can be obtained and hasn't expired (desktop only).
+ */
+export async function validateLocalCredential(
+  clientId: string,
+  source: CredentialSource
+): Promise<ValidationResult> {
+  const invoke = await getTauriInvoke();
+  switch (source.kind) {
+    case 'pfx':
+      return invoke('validate_pfx_credential', { clientId, path: source.path });
+    case 'pem':
+      return invoke('validate_pem_credential', { certPath: source.certPath, keyPath: source.keyPath });
+    case 'keyringSecret':
+      return invoke('validate_keyring_secret', { clientId });
+    case 'federated':
+      return invoke('validate_federated_credential', { clientId, assertion: source.assertion });
+  }
+}
+
+export type KeyringSecretKind = 'clientSecret' | 'pfxPassword';
+
+/**
+ * Store an app's client secret or PFX password in the OS keyring (desktop only).
+ */
+export async function storeAppSecret(clientId: string, kind: KeyringSecretKind, value: string): Promise<void> {
+  const invoke = await getTauriInvoke();
+  await invoke('store_app_secret', { clientId, kind, value });
+}
+
+/**
+ * Remove an app's stored client secret or PFX password; resolves false if none was stored.
+ */
+export async function deleteAppSecret(clientId: string, kind: KeyringSecretKind): Promise<boolean> {
+  const invoke = await getTauriInvoke();
+  return invoke('delete_app_secret', { clientId, kind });
+}
+
 /**
  * Check if Azure credentials are available
  */
@@ -206,10 +389,12 @@ export async function getCredentialStatus(): Promise<CredentialStatus> {
   }
 }
 
-export async function listAzureSubscriptions(): Promise<AzureCliResult<AzureSubscription[]>> {
+export async function listAzureSubscriptions(
+  options?: SidecarCallOptions
+): Promise<AzureCliResult<AzureSubscription[]>> {
   if (isTauriRuntime()) {
     const invoke = await getTauriInvoke();
-    return invoke('list_azure_subscriptions');
+    return invoke('list_azure_subscriptions', { ...options });
   }
 
   const response = await fetch('/api/azure-cli/subscriptions');
@@ -218,12 +403,12 @@ export async function listAzureSubscriptions(): Promise<AzureCliResult<AzureSubs
 
 /**
  * Check sidecar health status
- * Returns whether the sidecar is running and any startup error
+ * Returns the default backend, the sidecar's state and any startup error
  */
 export async function checkSidecarHealth(): Promise<SidecarHealth> {
   if (!isTauriRuntime()) {
     // In web mode, there's no sidecar - return healthy
-    return { running: true, error: null };
+    return { sidecar: 'notUsed', running: false, error: null };
   }
 
   const invoke = await getTauriInvoke();
@@ -231,6 +416,457 @@ export async function checkSidecarHealth(): Promise<SidecarHealth> {
 }
 
 
+/**
+ * Whether tokens can be acquired: the sidecar is running, or the backend doesn't use it
+ */
+export function isSidecarUsable(health: SidecarHealth): boolean {
+  return health.running || health.sidecar === 'notUsed';
+}
+
+
+/**
+ * A line the sidecar wrote to stderr, as captured by the desktop shell.
+ */
+export interface SidecarLogLine {
+  /** Monotonic sequence number; pass `seq + 1` as `since` to fetch only newer lines. */
+  seq: number;
+  /** Milliseconds since the Unix epoch */
+  timestamp: number;
+  level: 'info' | 'warn' | 'error';
+  pid: number | null;
+  message: string;
+}
+
+/**
+ * Get buffered sidecar diagnostics (desktop only).
+ */
+/** A Node.js executable found for the sidecar */
+export interface NodeRuntime {
+  path: string;
+  source: 'override' | 'path' | 'system' | 'nvm' | 'fnm' | 'volta' | 'asdf' | 'linuxbrew';
+  /** `node --version` without the leading `v`; null if it didn't run */
+  version: string | null;
+  compatible: boolean;
+  /** The one the sidecar uses */
+  selected: boolean;
+  problem: string | null;
+}
+
+/** Every Node.js runtime found, newest compatible (or the override) marked `selected` (desktop only) */
+export async function listNodeRuntimes(): Promise<NodeRuntime[]> {
+  if (!isTauriRuntime()) return [];
+  const invoke = await getTauriInvoke();
+  return invoke('list_node_runtimes');
+}
+
+/** Desktop settings kept by the Rust side, shared with the CLI */
+export interface AppSettings {
+  /** Node.js executable for the sidecar instead of the newest compatible one */
+  nodePath?: string | null;
+}
+
+export async function getAppSettings(): Promise<AppSettings> {
+  if (!isTauriRuntime()) return {};
+  const invoke = await getTauriInvoke();
+  return invoke('get_app_settings');
+}
+
+export async function saveAppSettings(settings: AppSettings): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error('saveAppSettings is only available in Tauri mode.');
+  }
+  const invoke = await getTauriInvoke();
+  await invoke('save_app_settings', { settings });
+}
+
+export async function getSidecarLogs(since?: number, limit?: number): Promise<SidecarLogLine[]> {
+  if (!isTauriRuntime()) return [];
+  const invoke = await getTauriInvoke();
+  return invoke('get_sidecar_logs', { since, limit });
+}
+
+/**
+ * Subscribe to live sidecar diagnostics (desktop only).
+ * Returns an unsubscribe function.
+ */
+export async function onSidecarLog(handler: (line: SidecarLogLine) => void): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen<SidecarLogLine>('sidecar-log', (event) => handler(event.payload));
+}
+
+/**
+ * Subscribe to sidecar lifecycle changes (desktop only).
+ * Returns an unsubscribe function.
+ */
+export async function onSidecarStatus(handler: (status: SidecarStatusEvent) => void): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen<SidecarStatusEvent>('sidecar-status', (event) => handler(event.payload));
+}
+
+/**
+ * Start the sidecar again after it crashed too often to be restarted automatically
+ * (`SIDECAR_CRASH_LOOP`). Until then every sidecar call fails with that error.
+ */
+export async function restartSidecar(): Promise<void> {
+  if (!isTauriRuntime()) return;
+  const invoke = await getTauriInvoke();
+  await invoke('restart_sidecar');
+}
+
+/**
+ * Cancel an in-flight sidecar request started with `options.requestId`.
+ * Returns false if the request already finished.
+ */
+export async function cancelRequest(requestId: string): Promise<boolean> {
+  if (!isTauriRuntime()) return false;
+  const invoke = await getTauriInvoke();
+  return invoke('cancel_request', { requestId });
+}
+
+export interface DecodedToken {
+  header: { alg: string | null; typ: string | null; kid: string | null; x5t: string | null };
+  claims: {
+    aud: string[];
+    iss: string | null;
+    tid: string | null;
+    oid: string | null;
+    scp: string[];
+    roles: string[];
+    exp: number | null;
+    nbf: number | null;
+    iat: number | null;
+    appid: string | null;
+    azp: string | null;
+    ver: string | null;
+  };
+  version: 'v1' | 'v2' | 'unknown';
+  rawHeader: Record<string, unknown>;
+  rawPayload: Record<string, unknown>;
+  signature: string;
+  signatureLen: number;
+}
+
+/**
+ * Decode a JWT with the native decoder (desktop only; does not verify the signature).
+ * Malformed tokens reject with a `validation` error naming the bad segment.
+ */
+export async function decodeToken(token: string): Promise<DecodedToken> {
+  if (!isTauriRuntime()) {
+    throw new Error('Not running in Tauri environment');
+  }
+  const invoke = await getTauriInvoke();
+  return invoke('decode_token', { token });
+}
+
+export type JwksSource =
+  | { kind: 'document'; json: string }
+  | { kind: 'file'; path: string }
+  | { kind: 'tenant'; tenantId: string; authorityHost?: string; refresh?: boolean };
+
+export interface VerifyOptions {
+  /** Defaults to the Entra issuer for the token's tenant and version */
+  issuer?: string;
+  /** Audience check is skipped when omitted */
+  audience?: string;
+  /** Defaults to the JWKS tenant when verifying against tenant keys */
+  allowedTenants?: string[];
+  clockSkewSeconds?: number;
+}
+
+export interface VerificationCheck {
+  name: 'signature' | 'issuer' | 'audience' | 'notBefore' | 'expiry' | 'tenant';
+  status: 'pass' | 'fail' | 'skipped';
+  detail: string;
+}
+
+export interface VerificationResult {
+  valid: boolean;
+  checks: VerificationCheck[];
+  algorithm: string | null;
+  keyId: string | null;
+  jwksOrigin: string;
+}
+
+/**
+ * Verify a token's RS256/PS256 signature and claims against a JWKS (desktop only).
+ */
+export async function verifyToken(
+  token: string,
+  jwks: JwksSource,
+  options?: VerifyOptions
+): Promise<VerificationResult> {
+  if (!isTauriRuntime()) {
+    throw new Error('Not running in Tauri environment');
+  }
+  const invoke = await getTauriInvoke();
+  return invoke('verify_token', { token, jwks, options });
+}
+
+/**
+ * Mirror saved apps to the desktop data directory for the headless CLI (desktop only).
+ * Only app metadata, Key Vault references and local credential paths are written, never secrets.
+ */
+export async function syncSavedApps(apps: AppConfig[]): Promise<void> {
+  if (!isTauriRuntime()) return;
+  const invoke = await getTauriInvoke();
+  await invoke('sync_saved_apps', {
+    apps: apps.map((app) => ({
+      id: app.id,
+      name: app.name,
+      clientId: app.clientId,
+      tenantId: app.tenantId,
+      keyVault: app.keyVault,
+      credential: app.credential,
+      description: app.description,
+      tags: app.tags ?? [],
+      backend: app.backend,
+      authorityHost: app.authorityHost,
+      assertionAlgorithm: app.assertionAlgorithm,
+    })),
+  });
+}
+
+export interface TokenServerStatus {
+  running: boolean;
+  port: number | null;
+  url: string | null;
+  /** Bearer secret callers must send; regenerated each time the server starts */
+  secret: string | null;
+}
+
+export interface TokenServerEvent {
+  timestamp: number;
+  appId: string | null;
+  appName: string | null;
+  tokenType: 'app' | 'user' | string;
+  scopes: string[];
+  fromCache: boolean;
+  client: string;
+  status: number;
+  error: string | null;
+}
+
+/**
+ * Start the loopback token server so local tools can fetch tokens for saved apps.
+ */
+export async function startTokenServer(port?: number): Promise<TokenServerStatus> {
+  if (!isTauriRuntime()) {
+    throw new Error('startTokenServer is only available in Tauri mode.');
+  }
+  const invoke = await getTauriInvoke();
+  return invoke('start_token_server', { port: port ?? null });
+}
+
+export async function stopTokenServer(): Promise<boolean> {
+  if (!isTauriRuntime()) return false;
+  const invoke = await getTauriInvoke();
+  return invoke('stop_token_server');
+}
+
+export async function getTokenServerStatus(): Promise<TokenServerStatus> {
+  if (!isTauriRuntime()) {
+    return { running: false, port: null, url: null, secret: null };
+  }
+  const invoke = await getTauriInvoke();
+  return invoke('get_token_server_status');
+}
+
+/**
+ * Subscribe to requests served by the loopback token server (desktop only).
+ * Returns an unsubscribe function.
+ */
+export async function onTokenServerRequest(handler: (event: TokenServerEvent) => void): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen<TokenServerEvent>('token-server-request', (event) => handler(event.payload));
+}
+
+/** Maps Azure DevOps repo URLs to a saved app for the git credential helper */
+export interface GitCredentialRule {
+  /** `host/path` pattern where `*` matches anything, e.g. `dev.azure.com/contoso/*` */
+  pattern: string;
+  /** Saved app id, name or client id */
+  app: string;
+  identity: 'app' | 'user';
+  /** Cached account for user tokens (the first cached account if unset) */
+  account?: string | null;
+}
+
+export async function getGitCredentialRules(): Promise<GitCredentialRule[]> {
+  if (!isTauriRuntime()) return [];
+  const invoke = await getTauriInvoke();
+  return invoke('get_git_credential_rules');
+}
+
+export async function saveGitCredentialRules(rules: GitCredentialRule[]): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error('saveGitCredentialRules is only available in Tauri mode.');
+  }
+  const invoke = await getTauriInvoke();
+  await invoke('save_git_credential_rules', { rules });
+}
+
+export type ManagedIdentityEndpointKind = 'imds' | 'appService';
+
+export interface ManagedIdentityEndpointConfig {
+  kind: ManagedIdentityEndpointKind;
+  /** Saved app id, name or client id whose app tokens are served */
+  app: string;
+  port?: number;
+  /** Scopes to request per resource URI; other resources get `<resource>/.default` */
+  scopeMap?: Record<string, string[]>;
+}
+
+export interface ManagedIdentityEndpointStatus {
+  kind: ManagedIdentityEndpointKind;
+  appId: string;
+  appName: string;
+  port: number;
+  url: string;
+  /** Environment variables that point Azure SDKs on this machine at this endpoint */
+  env: Record<string, string>;
+  /** The same variables for a local container, which reaches the endpoint through `host.docker.internal` */
+  containerEnv: Record<string, string>;
+  scopeMap: Record<string, string[]>;
+}
+
+export interface ManagedIdentityAccess {
+  timestamp: number;
+  kind: ManagedIdentityEndpointKind;
+  appName: string;
+  client: string;
+  path: string;
+  resource: string | null;
+  scopes: string[];
+  status: number;
+  error: string | null;
+}
+
+/**
+ * Start an IMDS or App Service managed identity endpoint backed by a saved app (desktop only).
+ */
+export async function startManagedIdentityEndpoint(
+  config: ManagedIdentityEndpointConfig
+): Promise<ManagedIdentityEndpointStatus> {
+  if (!isTauriRuntime()) {
+    throw new Error('startManagedIdentityEndpoint is only available in Tauri mode.');
+  }
+  const invoke = await getTauriInvoke();
+  return invoke('start_managed_identity_endpoint', { config: { ...config, scopeMap: config.scopeMap ?? {} } });
+}
+
+export async function stopManagedIdentityEndpoint(kind: ManagedIdentityEndpointKind): Promise<boolean> {
+  if (!isTauriRuntime()) return false;
+  const invoke = await getTauriInvoke();
+  return invoke('stop_managed_identity_endpoint', { kind });
+}
+
+export async function getManagedIdentityEndpoints(): Promise<ManagedIdentityEndpointStatus[]> {
+  if (!isTauriRuntime()) return [];
+  const invoke = await getTauriInvoke();
+  return invoke('get_managed_identity_endpoints');
+}
+
+/** Requests served by the managed identity endpoints, newest first. */
+export async function getManagedIdentityLog(): Promise<ManagedIdentityAccess[]> {
+  if (!isTauriRuntime()) return [];
+  const invoke = await getTauriInvoke();
+  return invoke('get_managed_identity_log');
+}
+
+/**
+ * Subscribe to requests served by the managed identity endpoints (desktop only).
+ * Returns an unsubscribe function.
+ */
+export async function onManagedIdentityRequest(
+  handler: (event: ManagedIdentityAccess) => void
+): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen<ManagedIdentityAccess>('managed-identity-request', (event) => handler(event.payload));
+}
+
+/** What an `entra-token-studio://` link asks for, after validation in Rust */
+export type DeepLinkAction =
+  | { action: 'issue'; favorite: string }
+  | { action: 'decode'; token: string }
+  | {
+      action: 'importApp';
+      clientId: string;
+      tenantId: string;
+      keyVaultUri?: string | null;
+      secretName?: string | null;
+      certName?: string | null;
+    };
+
+/** A deep link waiting for the UI; take it with `resolveDeepLink` */
+export type PendingDeepLink = DeepLinkAction & {
+  id: string;
+  /** Issues a token: only accept after the user confirms */
+  requiresConfirmation: boolean;
+};
+
+export async function getPendingDeepLinks(): Promise<PendingDeepLink[]> {
+  if (!isTauriRuntime()) return [];
+  const invoke = await getTauriInvoke();
+  return invoke('get_pending_deep_links');
+}
+
+/**
+ * Take a queued deep link. Resolves to its action if `accept` is set, or `null` if it was
+ * declined or already taken.
+ */
+export async function resolveDeepLink(id: string, accept: boolean): Promise<DeepLinkAction | null> {
+  if (!isTauriRuntime()) return null;
+  const invoke = await getTauriInvoke();
+  return invoke('resolve_deep_link', { id, accept });
+}
+
+/**
+ * Subscribe to validated deep links (desktop only).
+ * Returns an unsubscribe function.
+ */
+export async function onDeepLink(handler: (link: PendingDeepLink) => void): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen<PendingDeepLink>('deep-link', (event) => handler(event.payload));
+}
+
+/** A command from the command line (`--issue-favorite <id or name>`, `--decode <file>`) */
+export type LaunchCommand =
+  | { command: 'issueFavorite'; favorite: string }
+  | { command: 'decode'; token: string };
+
+export type LaunchRequest = LaunchCommand & { id: string };
+
+export async function getPendingLaunchCommands(): Promise<LaunchRequest[]> {
+  if (!isTauriRuntime()) return [];
+  const invoke = await getTauriInvoke();
+  return invoke('get_pending_launch_commands');
+}
+
+/**
+ * Report what became of a launch command. The message is printed by a caller that
+ * launched with `--wait`.
+ */
+export async function completeLaunchCommand(id: string, ok: boolean, message: string): Promise<boolean> {
+  if (!isTauriRuntime()) return false;
+  const invoke = await getTauriInvoke();
+  return invoke('complete_launch_command', { id, ok, message });
+}
+
+/**
+ * Subscribe to launch commands (desktop only).
+ * Returns an unsubscribe function.
+ */
+export async function onLaunchCommand(handler: (request: LaunchRequest) => void): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen<LaunchRequest>('launch-command', (event) => handler(event.payload));
+}
+
 export async function exitApp(): Promise<void> {
   if (!isTauriRuntime()) {
     throw new Error('Not running in Tauri environment');
@@ -239,10 +875,13 @@ export async function exitApp(): Promise<void> {
   await invoke('exit_app');
 }
 
-export async function listAzureApps(filters?: AzureAppFilters): Promise<AzureCliResult<AzureAppRegistration[]>> {
+export async function listAzureApps(
+  filters?: AzureAppFilters,
+  options?: SidecarCallOptions
+): Promise<AzureCliResult<AzureAppRegistration[]>> {
   if (isTauriRuntime()) {
     const invoke = await getTauriInvoke();
-    return invoke('list_azure_apps', filters ? { filters } : {});
+    return invoke('list_azure_apps', filters ? { filters, ...options } : { ...options });
   }
 
   const params = new URLSearchParams();
@@ -258,10 +897,13 @@ export async function listAzureApps(filters?: AzureAppFilters): Promise<AzureCli
   return response.json();
 }
 
-export async function listKeyVaults(subscriptionId?: string): Promise<AzureCliResult<AzureKeyVault[]>> {
+export async function listKeyVaults(
+  subscriptionId?: string,
+  options?: SidecarCallOptions
+): Promise<AzureCliResult<AzureKeyVault[]>> {
   if (isTauriRuntime()) {
     const invoke = await getTauriInvoke();
-    return invoke('list_keyvaults', { subscriptionId });
+    return invoke('list_keyvaults', { subscriptionId, ...options });
   }
 
   const params = new URLSearchParams();
@@ -272,11 +914,12 @@ export async function listKeyVaults(subscriptionId?: string): Promise<AzureCliRe
 
 export async function listKeyVaultSecrets(
   vaultName: string,
-  subscriptionId?: string
+  subscriptionId?: string,
+  options?: SidecarCallOptions
 ): Promise<AzureCliResult<AzureVaultCredential[]>> {
   if (isTauriRuntime()) {
     const invoke = await getTauriInvoke();
-    return invoke('list_keyvault_secrets', { vaultName, subscriptionId });
+    return invoke('list_keyvault_secrets', { vaultName, subscriptionId, ...options });
   }
 
   const params = new URLSearchParams({ vaultName });
@@ -287,11 +930,12 @@ export async function listKeyVaultSecrets(
 
 export async function listKeyVaultCertificates(
   vaultName: string,
-  subscriptionId?: string
+  subscriptionId?: string,
+  options?: SidecarCallOptions
 ): Promise<AzureCliResult<AzureVaultCredential[]>> {
   if (isTauriRuntime()) {
     const invoke = await getTauriInvoke();
-    return invoke('list_keyvault_certificates', { vaultName, subscriptionId });
+    return invoke('list_keyvault_certificates', { vaultName, subscriptionId, ...options });
   }
 
   const params = new URLSearchParams({ vaultName });
@@ -312,7 +956,8 @@ export async function acquireUserToken(
   scopes: string[],
   prompt?: 'select_account' | 'login' | 'consent',
   accountHomeAccountId?: string,
-  silentOnly?: boolean
+  silentOnly?: boolean,
+  options?: SidecarCallOptions
 ): Promise<TokenResponse> {
   if (!isTauriRuntime()) {
     throw new Error('acquireUserToken is only available in Tauri mode. Use AuthService for web.');
@@ -326,7 +971,129 @@ export async function acquireUserToken(
     prompt,
     accountHomeAccountId,
     silentOnly,
+    ...options,
+  });
+}
+
+/**
+ * Exchange a user token for a downstream API token on behalf of that user (OBO).
+ * The app authenticates with its own Key Vault or local credential.
+ */
+export async function acquireOboToken(
+  config: TokenAppConfig,
+  userToken: string,
+  scopes: string[],
+  options?: SidecarCallOptions
+): Promise<TokenResponse> {
+  if (!isTauriRuntime()) {
+    throw new Error('acquireOboToken is only available in Tauri mode.');
+  }
+
+  const invoke = await getTauriInvoke();
+  return invoke('acquire_obo_token', { config, userToken, scopes, ...options });
+}
+
+export interface DeviceCodeInfo {
+  flowId: string;
+  userCode: string;
+  verificationUri: string;
+  /** Entra's ready-made sign-in instructions */
+  message: string;
+  /** Seconds until the code expires */
+  expiresIn: number;
+  /** Seconds between polls */
+  interval: number;
+}
+
+export type DeviceCodeStatus = 'pending' | 'completed' | 'failed' | 'cancelled' | 'expired';
+
+export interface DeviceCodeProgress {
+  flowId: string;
+  status: DeviceCodeStatus;
+  /** Seconds until the code expires (`pending` only) */
+  expiresIn: number | null;
+  /** The token (`completed` only) */
+  result: TokenResponse | null;
+  error: { message: string; code: string | null } | null;
+}
+
+/**
+ * Start a device code sign-in (desktop only). Resolves with the code to show the user;
+ * the outcome arrives as a `device-code-progress` event.
+ */
+export async function startDeviceCode(
+  clientId: string,
+  tenantId: string,
+  scopes: string[],
+  options?: SidecarCallOptions
+): Promise<DeviceCodeInfo> {
+  const invoke = await getTauriInvoke();
+  return invoke('start_device_code', { clientId, tenantId, scopes, ...options });
+}
+
+/**
+ * Stop polling a device code sign-in; resolves false if it already finished.
+ */
+export async function cancelDeviceCode(flowId: string): Promise<boolean> {
+  const invoke = await getTauriInvoke();
+  return invoke('cancel_device_code', { flowId });
+}
+
+/**
+ * Subscribe to device code progress (pending heartbeats and the final outcome).
+ * Returns an unsubscribe function.
+ */
+export async function onDeviceCodeProgress(handler: (event: DeviceCodeProgress) => void): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import('@tauri-apps/api/event');
+  return listen<DeviceCodeProgress>('device-code-progress', (event) => handler(event.payload));
+}
+
+/**
+ * Acquire a user token with the device code flow (desktop only).
+ * `onCode` receives the code to show the user. Resolves once they finish signing in, or
+ * rejects with "Sign-in was cancelled" after `cancelDeviceCode`.
+ */
+export async function acquireUserTokenByDeviceCode(
+  clientId: string,
+  tenantId: string,
+  scopes: string[],
+  onCode: (info: DeviceCodeInfo) => void,
+  options?: SidecarCallOptions
+): Promise<TokenResponse> {
+  // Listen before starting so an update can't slip in between; buffer until the flow id is known.
+  const buffered: DeviceCodeProgress[] = [];
+  let settle: ((update: DeviceCodeProgress) => void) | null = null;
+  const unlisten = await onDeviceCodeProgress((update) => {
+    if (settle) settle(update);
+    else buffered.push(update);
   });
+
+  try {
+    const info = await startDeviceCode(clientId, tenantId, scopes, options);
+    onCode(info);
+    return await new Promise<TokenResponse>((resolve, reject) => {
+      settle = (update) => {
+        if (update.flowId !== info.flowId) return;
+        switch (update.status) {
+          case 'pending':
+            return;
+          case 'completed':
+            if (update.result) resolve(update.result);
+            else reject(new Error('Device code sign-in completed without a token'));
+            return;
+          case 'cancelled':
+            reject(new Error('Sign-in was cancelled'));
+            return;
+          default:
+            reject(new Error(update.error?.message || 'Device code sign-in failed'));
+        }
+      };
+      buffered.splice(0).forEach(settle);
+    });
+  } finally {
+    unlisten();
+  }
 }
 
 /**
@@ -336,22 +1103,27 @@ export async function acquireUserToken(
 export async function getUserAccounts(
   clientId: string,
   tenantId: string,
+  backend?: TokenBackendKind,
 ): Promise<NonNullable<TokenResponse['account']>[]> {
   if (!isTauriRuntime()) {
     throw new Error('getUserAccounts is only available in Tauri mode.');
   }
 
   const invoke = await getTauriInvoke();
-  return invoke('get_user_accounts', { clientId, tenantId });
+  return invoke('get_user_accounts', { clientId, tenantId, backend });
 }
 
 /**
  * Clear cached user tokens/accounts for a client (logout).
  */
-export async function clearUserCache(clientId: string, tenantId: string): Promise<void> {
+export async function clearUserCache(
+  clientId: string,
+  tenantId: string,
+  backend?: TokenBackendKind,
+): Promise<void> {
   if (!isTauriRuntime()) return;
   const invoke = await getTauriInvoke();
-  await invoke('clear_user_cache', { clientId, tenantId });
+  await invoke('clear_user_cache', { clientId, tenantId, backend });
 }
 
 export async function getAuthStorageStatus(): Promise<AuthStorageStatus> {
@@ -68,6 +68,8 @@ class AppRegistryState {
       ]);
       this.apps = apps ?? [];
       this.activeAppId = activeId;
+      // Registries saved before the CLI existed have never been mirrored.
+      void this.syncToDesktop(this.apps);
     } catch (error) {
       // Silently ignore
     } finally {
@@ -212,6 +214,19 @@ class AppRegistryState {
       tags: app.tags ? [...app.tags] : undefined,
     }));
     await clientStorage.set(CLIENT_STORAGE_KEYS.appRegistry, payload);
+    await this.syncToDesktop(payload);
+  }
+
+  /**
+   * Mirror the registry for the desktop CLI. Best-effort: the UI keeps working if it fails.
+   */
+  private async syncToDesktop(apps: AppConfig[]): Promise<void> {
+    try {
+      const { syncSavedApps } = await import('$lib/services/tauri-api');
+      await syncSavedApps(apps);
+    } catch (error) {
+      console.warn('[app-registry] Failed to sync saved apps for the CLI:', error);
+    }
   }
 
   /**
@@ -227,6 +242,7 @@ class AppRegistryState {
     await Promise.all([
       clientStorage.set(CLIENT_STORAGE_KEYS.appRegistry, []),
       clientStorage.set(CLIENT_STORAGE_KEYS.activeAppId, null),
+      this.syncToDesktop([]),
     ]);
 
     // Cascade delete associated data
@@ -22,6 +22,10 @@ export class HistoryState {
         this.items = await historyService.addHistoryItem(item);
     }
 
+    async update(item: HistoryItem, changes: Partial<HistoryItem>) {
+        this.items = await historyService.updateHistoryItem(item.timestamp, changes);
+    }
+
     async clear() {
         await historyService.clearHistory();
         this.items = [];
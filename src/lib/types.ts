@@ -7,6 +7,11 @@ export interface HistoryItem {
     appId?: string;
     appName?: string;
     appColor?: string;
+    // On-behalf-of exchange: the incoming user token and the downstream token share a pairId
+    obo?: {
+        pairId: string;
+        role: 'incoming' | 'downstream';
+    };
 }
 
 export interface TokenData {
@@ -104,6 +109,39 @@ export type AppCredentialStatus = 'valid' | 'error' | 'unknown' | 'validating';
  * Configuration for an Entra app registration with Azure Key Vault credentials.
  * Supports multi-app scenarios where users can switch between different app configurations.
  */
+/**
+ * Desktop token backend: the Node sidecar (default), native Rust app tokens, or the offline demo backend
+ */
+export type TokenBackendKind = 'sidecar' | 'native' | 'demo';
+
+/**
+ * Where a federated credential's client assertion comes from: a token file, a command's
+ * stdout, or a token minted by another saved app for api://AzureADTokenExchange.
+ */
+export type FederatedAssertion =
+    | { from: 'file'; path: string }
+    | { from: 'command'; program: string; args?: string[] }
+    | { from: 'app'; appId: string };
+
+/**
+ * Desktop-only credential kept on this machine instead of Key Vault.
+ * Secrets (client secret, PFX password) live in the OS keyring, never in the app config.
+ */
+export type CredentialSource =
+    | { kind: 'pfx'; path: string }
+    | { kind: 'pem'; certPath: string; keyPath?: string }
+    | { kind: 'keyringSecret' }
+    | { kind: 'federated'; assertion: FederatedAssertion };
+
+/** Fields to pre-fill the add-app form with, e.g. from an `app/import` deep link */
+export interface AppDraft {
+    clientId: string;
+    tenantId: string;
+    keyVaultUri?: string | null;
+    secretName?: string | null;
+    certName?: string | null;
+}
+
 export interface AppConfig {
     // Identity
     id: string;                            // UUID
@@ -119,6 +157,12 @@ export interface AppConfig {
     
     // Key Vault Configuration (REQUIRED for app tokens)
     keyVault: KeyVaultConfig;
+    credential?: CredentialSource;         // Desktop: local credential used instead of Key Vault
+
+    // Desktop token backend override ('demo' serves offline, unsigned tokens)
+    backend?: TokenBackendKind;
+    authorityHost?: string;                // Native backend: token endpoint host (default login.microsoftonline.com)
+    assertionAlgorithm?: 'RS256' | 'PS256'; // Native backend: certificate assertion signature
     
     // Metadata
     createdAt: number;